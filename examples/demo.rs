@@ -31,9 +31,9 @@ impl App for MyApp {
             text("Billing address:", Color::hex("#1f282d"))
                 .font_size(12.0)
                 .font_weight(500),
-            text_input("address")
+            text_input().id("address")
                 .value(&self.address)
-                .on_change(|v| Action::UpdateAddress(v))
+                .on_change(Action::UpdateAddress)
                 .placeholder("Enter address")
                 .placeholder_color(Color::hex("#333333"))
                 .font_size(12.0)
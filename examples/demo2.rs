@@ -31,9 +31,9 @@ impl App for MyApp {
             text("Hover the text field — cursor becomes a text caret.", Color::hex("#aaaaaa"))
                 .font_size(13.0)
                 .margin(Margin::bottom(8.0)),
-            text_input("name")
+            text_input().id("name")
                 .value(&self.name)
-                .on_change(|v| Action::UpdateName(v))
+                .on_change(Action::UpdateName)
                 .placeholder("Type something...")
                 .width(px(320.0))
                 .padding(Edges::all(8.0))
@@ -26,10 +26,10 @@ impl App for MyApp {
             text("Hello, Bento!", Color::hex("#ffffff"))
                 .font_size(24.0)
                 .font_weight(700),
-            text_input("name")
+            text_input().id("name")
                 .value(&self.name)
                 .placeholder("Enter your name...")
-                .on_change(|v| Action::UpdateName(v))
+                .on_change(Action::UpdateName)
                 .width(px(300.0)),
             button("Submit")
                 .on_click(Action::Submit)
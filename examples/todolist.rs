@@ -117,10 +117,10 @@ impl MyApp {
         column(vec![
             // search + add row
             row(vec![
-                text_input("search")
+                text_input().id("search")
                     .placeholder("New task or search...")
                     .value(&self.search)
-                    .on_change(|v| Action::SearchChanged(v))
+                    .on_change(Action::SearchChanged)
                     .font_size(50.0)
                     .font("mono")
                     .text_color(Color::hex("#333333"))
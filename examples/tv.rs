@@ -47,7 +47,7 @@ impl App for MyApp {
                 let active = i == self.selected;
                 row(vec![
                     text(if active { "▸ " } else { "  " }, text_dim).font_size(11.0),
-                    text(*name, if active { text_bright } else { text_mid })
+                    text(name, if active { text_bright } else { text_mid })
                         .font_size(12.0)
                         .grow(1.0),
                 ])
@@ -101,7 +101,7 @@ impl App for MyApp {
             text_editor()
                 .id("body")
                 .value(&self.notes)
-                .on_change(|v| Action::UpdateNotes(v))
+                .on_change(Action::UpdateNotes)
                 .placeholder("// start typing...💁👌🎍😍 السَّلَامُ عَلَيْكُمْ")
                 .font("mono")
                 .font_size(self.font_size)
@@ -4,20 +4,30 @@ use std::sync::Arc;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
 use winit::{
     application::ApplicationHandler,
-    event::{ElementState, MouseButton as WinitMouseButton, MouseScrollDelta, WindowEvent},
-    event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
+    event::{ElementState, Ime, MouseButton as WinitMouseButton, MouseScrollDelta, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
     keyboard::PhysicalKey,
     window::{Window, WindowId},
 };
 
+use crate::clipboard::{Clipboard, SystemClipboard};
+use crate::component;
 use crate::draw::{Cursor, MouseState, draw};
 use crate::events::{Event, Key, Modifiers, MouseButton};
+use crate::focus::{self, FocusKind};
+use crate::hit::hit_test;
+use crate::ime;
 use crate::layout::do_layout;
 use crate::state::StateStore;
-use crate::task::Task;
+use crate::task::{Task, TaskKind, WidgetOp};
+use crate::widget;
+use crate::widgets::history::History;
+use crate::widgets::scroll;
 use crate::widgets::text_editor as te;
 use crate::widgets::text_input as ti;
-use crate::{Color, Element, Fonts, GpuContext, ShadowRenderer, ShapeRenderer, TextRenderer};
+use crate::{
+    Color, DisplayList, Element, Fonts, GpuContext, ShadowRenderer, ShapeRenderer, TextRenderer,
+};
 
 // settings
 
@@ -65,16 +75,16 @@ pub trait App: 'static + Sized {
 
     fn new() -> Self;
     fn view(&self) -> Element<Self::Action>;
-    fn update(&mut self, action: Self::Action) -> Vec<Task<Self::Action>> {
+    fn update(&mut self, _action: Self::Action) -> Vec<Task<Self::Action>> {
         vec![]
     }
     fn start(&mut self) -> Vec<Task<Self::Action>> {
         vec![]
     }
-    fn event(&mut self, event: Event) -> Option<Self::Action> {
+    fn event(&mut self, _event: Event) -> Option<Self::Action> {
         None
     }
-    fn fonts(&self, fonts: &mut Fonts) {}
+    fn fonts(&self, _fonts: &mut Fonts) {}
     fn run(settings: Settings) {
         run::<Self>(settings);
     }
@@ -89,10 +99,12 @@ fn run<A: App>(settings: Settings) {
 
     let event_loop = EventLoop::<Wake>::with_user_event().build().unwrap();
     let proxy = event_loop.create_proxy();
-    let (tx, rx) = unbounded_channel::<A::Action>();
+    let wake = Arc::new(move || {
+        let _ = proxy.send_event(Wake);
+    });
 
     event_loop
-        .run_app(&mut Runner::new(A::new(), settings, proxy, tx, rx))
+        .run_app(&mut Runner::<A>::new(settings, wake))
         .unwrap();
 }
 
@@ -106,9 +118,11 @@ struct Gfx {
     text_renderer: TextRenderer,
     shape_renderer: ShapeRenderer,
     shadow_renderer: ShadowRenderer,
-    fonts: Fonts,
+    list: DisplayList,
     clear_color: Color,
     current_cursor: Cursor,
+    // the IME cursor area the window was last given, None while IME is off
+    ime: Option<[f32; 4]>,
 }
 
 impl Gfx {
@@ -127,6 +141,22 @@ impl Gfx {
             .resize(&self.gpu.device, &self.gpu.queue, w, h);
     }
 
+    fn set_ime(&mut self, area: Option<[f32; 4]>) {
+        if area == self.ime {
+            return;
+        }
+        if area.is_some() != self.ime.is_some() {
+            self.window.set_ime_allowed(area.is_some());
+        }
+        if let Some([x, y, w, h]) = area {
+            self.window.set_ime_cursor_area(
+                winit::dpi::LogicalPosition::new(x, y),
+                winit::dpi::LogicalSize::new(w, h),
+            );
+        }
+        self.ime = area;
+    }
+
     fn set_cursor(&mut self, cursor: Cursor) {
         if cursor == self.current_cursor {
             return;
@@ -151,49 +181,72 @@ impl Gfx {
 
 // Tasks — async task machinery
 
-struct Tasks<Action: Clone + Send + 'static> {
+// called from task threads whenever an action is ready, so the event loop wakes up
+pub(crate) type Waker = Arc<dyn Fn() + Send + Sync>;
+
+pub(crate) struct Tasks<Action: Clone + Send + 'static> {
     tx: UnboundedSender<Action>,
     rx: UnboundedReceiver<Action>,
-    proxy: EventLoopProxy<Wake>,
+    wake: Waker,
     exclusive: HashMap<u64, tokio::task::AbortHandle>,
+    // tasks that end on their own and may still be running, so the harness can wait on them
+    running: Vec<tokio::task::JoinHandle<()>>,
 }
 
 impl<Action: Clone + Send + 'static> Tasks<Action> {
-    fn new(
-        tx: UnboundedSender<Action>,
-        rx: UnboundedReceiver<Action>,
-        proxy: EventLoopProxy<Wake>,
-    ) -> Self {
+    pub(crate) fn new(wake: Waker) -> Self {
+        let (tx, rx) = unbounded_channel::<Action>();
         Self {
             tx,
             rx,
-            proxy,
+            wake,
             exclusive: HashMap::new(),
+            running: Vec::new(),
         }
     }
 
     fn spawn(&mut self, tasks: Vec<Task<Action>>) {
         for task in tasks {
             let exclusive_key = task.exclusive_key;
-            if let Some(key) = exclusive_key {
-                if let Some(old) = self.exclusive.remove(&key) {
-                    old.abort();
-                }
+            let ends = !matches!(task.kind, TaskKind::Every(..));
+            if let Some(key) = exclusive_key
+                && let Some(old) = self.exclusive.remove(&key)
+            {
+                old.abort();
             }
             let tx = self.tx.clone();
-            let proxy = self.proxy.clone();
+            let wake = self.wake.clone();
             let send = Arc::new(move |action| {
                 let _ = tx.send(action);
-                let _ = proxy.send_event(Wake);
+                wake();
             });
             task.spawn(send, |handle| {
                 if let Some(key) = exclusive_key {
-                    self.exclusive.insert(key, handle);
+                    self.exclusive.insert(key, handle.abort_handle());
+                }
+                if ends {
+                    // pruned only here, so it stays non-empty until the harness waits
+                    self.running.retain(|handle| !handle.is_finished());
+                    self.running.push(handle);
                 }
             });
         }
     }
 
+    // hands an action over as if a task had produced it
+    fn send(&self, action: Action) {
+        let _ = self.tx.send(action);
+        (self.wake)();
+    }
+
+    // waits for the running tasks that end on their own, cancelled ones count as ended
+    async fn finish(&mut self) {
+        while let Some(handle) = self.running.last_mut() {
+            let _ = handle.await;
+            self.running.pop();
+        }
+    }
+
     fn drain(&mut self) -> Vec<Action> {
         let mut actions = vec![];
         while let Ok(action) = self.rx.try_recv() {
@@ -203,57 +256,431 @@ impl<Action: Clone + Send + 'static> Tasks<Action> {
     }
 }
 
-// which text widget currently has keyboard focus
-enum FocusedWidget {
-    TextInput(String),
-    TextEditor(String),
+// shell
+// everything between the platform and the app: input state, widget state, fonts,
+// tasks and the view -> layout -> draw pass. knows nothing about winit or wgpu,
+// the Runner feeds it window events and the TestHarness feeds it synthetic ones
+
+pub(crate) struct Shell<A: App> {
+    pub(crate) app: A,
+    pub(crate) state: StateStore,
+    pub(crate) mouse: MouseState,
+    pub(crate) modifiers: Modifiers,
+    pub(crate) fonts: Fonts,
+    pub(crate) clipboard: Box<dyn Clipboard>,
+    tasks: Tasks<A::Action>,
+    // widget tasks (scrolling and such) waiting for the next frame
+    ops: Vec<WidgetOp>,
+    // wheel movement in logical pixels since the last frame
+    wheel: [f32; 2],
+    // input the app didn't handle since the last frame, for custom widgets
+    events: Vec<Event>,
+    // every action passed to update, only kept when something asked for it (tests)
+    pub(crate) recorded: Option<Vec<A::Action>>,
+}
+
+impl<A: App> Shell<A> {
+    pub(crate) fn new(app: A, wake: Waker, clipboard: Box<dyn Clipboard>) -> Self {
+        let mut fonts = Fonts::new();
+        app.fonts(&mut fonts);
+        if fonts.default.is_none() {
+            fonts.add("default", "Arial", 14.0).default();
+        }
+
+        Self {
+            app,
+            state: StateStore::new(),
+            mouse: MouseState::new(),
+            modifiers: Modifiers::default(),
+            fonts,
+            clipboard,
+            tasks: Tasks::new(wake),
+            ops: Vec::new(),
+            wheel: [0.0, 0.0],
+            events: Vec::new(),
+            recorded: None,
+        }
+    }
+
+    pub(crate) fn start(&mut self) {
+        let tasks = self.app.start();
+        self.spawn(tasks);
+    }
+
+    pub(crate) fn update(&mut self, action: A::Action) {
+        if let Some(recorded) = &mut self.recorded {
+            recorded.push(action.clone());
+        }
+        let tasks = self.app.update(action);
+        self.spawn(tasks);
+    }
+
+    fn spawn(&mut self, tasks: Vec<Task<A::Action>>) {
+        let mut spawn = Vec::new();
+        for task in tasks {
+            match task.kind {
+                TaskKind::Widget(op) => self.ops.push(op),
+                TaskKind::ReadClipboard(f) => self.tasks.send(f(self.clipboard.read())),
+                TaskKind::WriteClipboard(text) => self.clipboard.write(&text),
+                _ => spawn.push(task),
+            }
+        }
+        self.tasks.spawn(spawn);
+    }
+
+    pub(crate) fn focused_id(&self) -> Option<&str> {
+        focus::focused(&self.state).map(|(id, _)| id)
+    }
+
+    // returns true if the app turned the event into an action
+    pub(crate) fn dispatch_event(&mut self, event: Event) -> bool {
+        if let Some(action) = self.app.event(event) {
+            self.update(action);
+            true
+        } else {
+            false
+        }
+    }
+
+    // the app sees the event first, custom widgets get it in the next frame otherwise
+    fn pass_on(&mut self, event: Event) {
+        if !self.dispatch_event(event.clone()) {
+            self.events.push(event);
+        }
+    }
+
+    // waits for the tasks that end on their own, what they produced is delivered by the
+    // next drain_channel
+    pub(crate) async fn finish_tasks(&mut self) {
+        self.tasks.finish().await;
+    }
+
+    // whether tasks were started since the last finish_tasks, their actions can start more
+    pub(crate) fn tasks_started(&self) -> bool {
+        !self.tasks.running.is_empty()
+    }
+
+    // returns true if any task delivered an action
+    pub(crate) fn drain_channel(&mut self) -> bool {
+        let actions = self.tasks.drain();
+        let had_any = !actions.is_empty();
+        for action in actions {
+            self.update(action);
+        }
+        had_any
+    }
+
+    pub(crate) fn mouse_moved(&mut self, x: f32, y: f32) {
+        let dx = x - self.mouse.x;
+        let dy = y - self.mouse.y;
+        self.mouse.x = x;
+        self.mouse.y = y;
+        self.pass_on(Event::MouseMoved { x, y, dx, dy });
+    }
+
+    // now is seconds since some fixed point, used for double/triple click detection
+    pub(crate) fn mouse_input(&mut self, btn: MouseButton, pressed: bool, now: f64) {
+        match btn {
+            MouseButton::Left => {
+                self.mouse.left_just_pressed = pressed && !self.mouse.left_pressed;
+                self.mouse.left_just_released = !pressed && self.mouse.left_pressed;
+                self.mouse.left_pressed = pressed;
+                if pressed {
+                    let dt = now - self.mouse.last_click_time;
+                    let dx = self.mouse.x - self.mouse.left_click_x;
+                    let dy = self.mouse.y - self.mouse.left_click_y;
+                    let dist = (dx * dx + dy * dy).sqrt();
+                    const DOUBLE_CLICK_TIME: f64 = 0.3;
+                    const DOUBLE_CLICK_DIST: f32 = 4.0;
+                    if dt < DOUBLE_CLICK_TIME && dist < DOUBLE_CLICK_DIST {
+                        self.mouse.left_click_count += 1;
+                    } else {
+                        self.mouse.left_click_count = 1;
+                    }
+                    self.mouse.left_click_x = self.mouse.x;
+                    self.mouse.left_click_y = self.mouse.y;
+                    self.mouse.last_click_time = now;
+                }
+            }
+            MouseButton::Right => {
+                self.mouse.right_just_pressed = pressed && !self.mouse.right_pressed;
+                self.mouse.right_pressed = pressed;
+            }
+            MouseButton::Middle => {
+                self.mouse.middle_just_pressed = pressed && !self.mouse.middle_pressed;
+                self.mouse.middle_pressed = pressed;
+            }
+        }
+        let x = self.mouse.x;
+        let y = self.mouse.y;
+        if pressed {
+            self.pass_on(Event::MousePressed { button: btn, x, y });
+        } else {
+            self.pass_on(Event::MouseReleased { button: btn, x, y });
+        }
+    }
+
+    // dx and dy are the wheel movement in logical pixels, the event carries whatever
+    // the platform reported. the app sees it first, scroll containers get it otherwise
+    pub(crate) fn mouse_scrolled(&mut self, event: Event, dx: f32, dy: f32) {
+        if !self.dispatch_event(event.clone()) {
+            self.wheel[0] += dx;
+            self.wheel[1] += dy;
+            self.events.push(event);
+        }
+    }
+
+    // text is what the key produced (already layout and shift aware), empty for non-printing keys
+    pub(crate) fn key_input(&mut self, key: Key, pressed: bool, text: &str) {
+        match key {
+            Key::LControl | Key::RControl => self.modifiers.ctrl = pressed,
+            Key::LShift | Key::RShift => self.modifiers.shift = pressed,
+            Key::LAlt | Key::RAlt => self.modifiers.alt = pressed,
+            _ => {}
+        }
+
+        let modifiers = self.modifiers;
+        let bento_event = if pressed {
+            Event::KeyPressed { key, modifiers }
+        } else {
+            Event::KeyReleased { key, modifiers }
+        };
+
+        // app gets first priority, if it handles the event, the widget doesnt see it
+        if self.dispatch_event(bento_event.clone()) {
+            return;
+        }
+
+        // tab moves focus between widgets, unless it's part of a shortcut. a focused
+        // editor that indents with tab takes it, ctrl+tab moves focus out of it instead
+        let takes_tab = focus::focused(&self.state).is_some_and(|(id, kind)| {
+            kind == FocusKind::TextEditor && te::takes_tab(&self.state, id)
+        });
+        if key == Key::Tab && self.modifiers.ctrl == takes_tab && !self.modifiers.alt {
+            if pressed {
+                focus::step(&mut self.state, !self.modifiers.shift);
+                self.focus_changed();
+            }
+            return;
+        }
+
+        // if app didnt consume it and a widget is focused, route to the widget
+        let Some((id, kind)) = focus::focused(&self.state) else {
+            return;
+        };
+        let id = id.to_string();
+        // ctrl shortcuts are handled by key, their text (a control char on some
+        // platforms) isn't typed. AltGr shows up as ctrl+alt on windows and does type
+        let shortcut = self.modifiers.ctrl && !self.modifiers.alt;
+        let text = if pressed && !shortcut { text } else { "" };
+
+        // custom widgets and buttons take their keys in the next frame, see
+        // Widget::on_event
+        if matches!(kind, FocusKind::Custom | FocusKind::Button) {
+            self.events.push(bento_event);
+            return;
+        }
+
+        let action = match kind {
+            // enter submits an input, escape lets go of it
+            FocusKind::TextInput if pressed && key == Key::Enter => {
+                ti::call_submit(&self.state, &id)
+            }
+            FocusKind::TextInput if pressed && key == Key::Escape => {
+                focus::set(&mut self.state, None, false);
+                self.focus_changed();
+                None
+            }
+            _ => self.edit_text(&id, kind, &bento_event, text),
+        };
+
+        if let Some(action) = action {
+            self.update(action);
+        }
+    }
+
+    // on_focus and on_blur for the inputs focus moved between since the last call,
+    // returns whether any action fired
+    fn focus_changed(&mut self) -> bool {
+        let mut fired = false;
+        for (id, kind, focused) in focus::take_changes(&mut self.state) {
+            if kind != FocusKind::TextInput {
+                continue;
+            }
+            if let Some(action) = ti::call_focus_callback(&self.state, &id, focused) {
+                self.update(action);
+                fired = true;
+            }
+        }
+        fired
+    }
+
+    // a key (and the text it typed) for the focused text widget, returns its on_change
+    // (or on_edit) action if the value changed
+    fn edit_text(
+        &mut self,
+        id: &str,
+        kind: FocusKind,
+        event: &Event,
+        text: &str,
+    ) -> Option<A::Action> {
+        let clipboard = &mut *self.clipboard;
+        match kind {
+            FocusKind::TextInput => ti::handle_key(&mut self.state, id, event, text, clipboard)
+                .and_then(|v| ti::call_callback::<A::Action>(&self.state, id, v)),
+            FocusKind::TextEditor => te::handle_key(&mut self.state, id, event, text, clipboard)
+                .and_then(|e| te::call_callback::<A::Action>(&self.state, id, e)),
+            FocusKind::Button | FocusKind::Custom => None,
+        }
+    }
+
+    // the IME's text so far, shown at the cursor of the focused text widget.
+    // an empty preedit ends the composition without typing anything
+    pub(crate) fn ime_preedit(&mut self, text: &str, cursor: Option<usize>) {
+        ime::set_preedit(&mut self.state, text, cursor);
+    }
+
+    // the IME finished composing, its text goes in as if it had been typed
+    pub(crate) fn ime_commit(&mut self, text: &str) {
+        ime::clear(&mut self.state);
+        let Some((id, kind)) = focus::focused(&self.state) else {
+            return;
+        };
+        let id = id.to_string();
+        let event = Event::KeyPressed {
+            key: Key::Unknown,
+            modifiers: Modifiers::default(),
+        };
+        if let Some(action) = self.edit_text(&id, kind, &event, text) {
+            self.update(action);
+        }
+    }
+
+    // where the window should put the IME's candidate box, None when no text widget
+    // has focus and IME should be off
+    pub(crate) fn ime_area(&self) -> Option<[f32; 4]> {
+        match focus::focused(&self.state) {
+            Some((_, FocusKind::TextInput | FocusKind::TextEditor)) => {
+                Some(ime::caret(&self.state).unwrap_or_default())
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn window_focused(&mut self, focused: bool) {
+        if !focused {
+            self.modifiers = Modifiers::default();
+        }
+        self.dispatch_event(if focused {
+            Event::Focused
+        } else {
+            Event::Unfocused
+        });
+    }
+
+    // view -> layout -> draw into the given display list, then feed the actions the
+    // interaction pass produced back into update. returns the cursor the hovered
+    // widget asked for and whether any action fired
+    pub(crate) fn frame(
+        &mut self,
+        width: f32,
+        height: f32,
+        scale_factor: f32,
+        list: &mut DisplayList,
+    ) -> (Option<Cursor>, bool) {
+        let mut tree = self.app.view();
+        do_layout(&mut tree, width, height, &mut self.fonts, &mut self.state);
+        focus::collect(&mut tree, &mut self.state);
+
+        // wheel and widget tasks, scrolling needs another layout
+        let mut relayout = false;
+        if self.wheel != [0.0, 0.0] {
+            let [dx, dy] = std::mem::take(&mut self.wheel);
+            hit_test(&mut tree, self.mouse.x, self.mouse.y);
+            relayout |= scroll::wheel(&mut tree, &mut self.state, dx, dy);
+        }
+        for op in std::mem::take(&mut self.ops) {
+            relayout |= match op {
+                WidgetOp::ScrollTo { id, x, y } => {
+                    scroll::scroll_to(&mut self.state, &id, x, y);
+                    true
+                }
+                WidgetOp::ScrollIntoView(id) => {
+                    scroll::scroll_into_view(&tree, &mut self.state, &id)
+                }
+                WidgetOp::Focus(id) => {
+                    focus::focus_id(&mut self.state, &id);
+                    false
+                }
+                WidgetOp::Blur => {
+                    focus::set(&mut self.state, None, false);
+                    false
+                }
+                WidgetOp::FocusNext | WidgetOp::FocusPrevious => {
+                    focus::step(&mut self.state, matches!(op, WidgetOp::FocusNext));
+                    false
+                }
+                WidgetOp::ClearHistory(id) => {
+                    // the input's history or the editor's, whichever it is
+                    self.state.remove::<History>(&id);
+                    self.state.remove::<History<ropey::Rope>>(&id);
+                    false
+                }
+                WidgetOp::Search(id, op) => {
+                    te::queue_search(&mut self.state, &id, op);
+                    false
+                }
+            };
+        }
+        if relayout {
+            do_layout(&mut tree, width, height, &mut self.fonts, &mut self.state);
+        }
+
+        let mut delivered = Vec::new();
+        if !self.events.is_empty() {
+            let events = std::mem::take(&mut self.events);
+            hit_test(&mut tree, self.mouse.x, self.mouse.y);
+            delivered = widget::deliver(&mut tree, &events, &mut self.state, &self.mouse);
+        }
+
+        ime::reset_caret(&mut self.state);
+        let (actions, cursor) = draw(
+            &mut tree,
+            list,
+            &mut self.fonts,
+            &mut self.state,
+            &self.mouse,
+            scale_factor,
+        );
+
+        let mut had_actions = !delivered.is_empty() || !actions.is_empty();
+        for action in delivered.into_iter().chain(actions) {
+            self.update(action);
+        }
+        // focus moved by a click or a task this frame
+        had_actions |= self.focus_changed();
+        // a component can change without telling the app
+        had_actions |= component::take_changed(&mut self.state);
+
+        self.mouse.end_frame();
+        (cursor, had_actions)
+    }
 }
 
 // runner
 
 struct Runner<A: App> {
-    app: A,
+    shell: Shell<A>,
     gfx: Option<Gfx>,
     init: Settings,
-    state: StateStore,
-    mouse: MouseState,
-    modifiers: Modifiers,
-    focused_widget: Option<FocusedWidget>,
-    tasks: Tasks<A::Action>,
 }
 
 impl<A: App> Runner<A> {
-    fn new(
-        app: A,
-        settings: Settings,
-        proxy: EventLoopProxy<Wake>,
-        tx: UnboundedSender<A::Action>,
-        rx: UnboundedReceiver<A::Action>,
-    ) -> Self {
+    fn new(settings: Settings, wake: Waker) -> Self {
         Self {
-            app,
+            shell: Shell::new(A::new(), wake, Box::new(SystemClipboard::new())),
             gfx: None,
             init: settings,
-            state: StateStore::new(),
-            mouse: MouseState {
-                x: 0.0,
-                y: 0.0,
-                left_pressed: false,
-                left_just_pressed: false,
-                left_just_released: false,
-                right_pressed: false,
-                right_just_pressed: false,
-                middle_pressed: false,
-                middle_just_pressed: false,
-                left_click_count: 0,
-                left_click_x: 0.0,
-                left_click_y: 0.0,
-                click_timer: std::time::Instant::now(),
-                last_click_time: -1.0,
-            },
-            modifiers: Modifiers::default(),
-            focused_widget: None,
-            tasks: Tasks::new(tx, rx, proxy),
         }
     }
 
@@ -265,24 +692,8 @@ impl<A: App> Runner<A> {
         self.gfx.as_mut().unwrap()
     }
 
-    fn dispatch_event(&mut self, event: Event) {
-        if let Some(action) = self.app.event(event) {
-            let tasks = self.app.update(action);
-            self.tasks.spawn(tasks);
-            self.gfx().window.request_redraw();
-        }
-    }
-
-    fn drain_channel(&mut self) {
-        let actions = self.tasks.drain();
-        let had_any = !actions.is_empty();
-        for action in actions {
-            let tasks = self.app.update(action);
-            self.tasks.spawn(tasks);
-        }
-        if had_any {
-            self.gfx().window.request_redraw();
-        }
+    fn request_redraw(&self) {
+        self.gfx().window.request_redraw();
     }
 
     fn render(&mut self) {
@@ -296,21 +707,32 @@ impl<A: App> Runner<A> {
         let (mut encoder, finisher, view) = frame.begin();
         let (width, height) = gfx.logical_size();
 
-        let mut tree = self.app.view();
-        do_layout(&mut tree, width, height, &mut gfx.fonts);
-
-        let (actions, cursor) = draw(
-            &mut tree,
+        gfx.list.clear();
+        let (cursor, had_actions) =
+            self.shell
+                .frame(width, height, gfx.scale_factor as f32, &mut gfx.list);
+        let layers = gfx.list.replay(
             &mut gfx.shape_renderer,
             &mut gfx.shadow_renderer,
             &mut gfx.text_renderer,
-            &mut gfx.fonts,
-            &mut self.state,
-            &self.mouse,
-            gfx.scale_factor as f32,
+            &mut self.shell.fonts,
+        );
+        let (device, queue) = (&gfx.gpu.device, &gfx.gpu.queue);
+        gfx.shadow_renderer.prepare(device, queue);
+        gfx.shape_renderer.prepare(device, queue);
+        let text_layers: Vec<_> = layers.iter().map(|l| l.texts.clone()).collect();
+        gfx.text_renderer.prepare(
+            &mut self.shell.fonts.font_system,
+            width,
+            height,
+            gfx.scale_factor,
+            device,
+            queue,
+            &text_layers,
         );
 
         gfx.set_cursor(cursor.unwrap_or(Cursor::Default));
+        gfx.set_ime(self.shell.ime_area());
 
         {
             let clear = gfx.clear_color;
@@ -334,19 +756,14 @@ impl<A: App> Runner<A> {
                 occlusion_query_set: None,
             });
 
-            gfx.shadow_renderer
-                .render(&gfx.gpu.device, &gfx.gpu.queue, &mut pass);
-            gfx.shape_renderer
-                .render(&gfx.gpu.device, &gfx.gpu.queue, &mut pass);
-            gfx.text_renderer.render(
-                &mut gfx.fonts.font_system,
-                width,
-                height,
-                gfx.scale_factor,
-                &gfx.gpu.device,
-                &gfx.gpu.queue,
-                &mut pass,
-            );
+            // layers paint in order, each one shadows -> shapes -> text
+            for (i, layer) in layers.iter().enumerate() {
+                gfx.shadow_renderer
+                    .render_range(&mut pass, layer.shadows.clone());
+                gfx.shape_renderer
+                    .render_range(&mut pass, layer.shapes.clone());
+                gfx.text_renderer.render_layer(i, &mut pass);
+            }
         }
 
         gfx.shadow_renderer.clear();
@@ -355,24 +772,9 @@ impl<A: App> Runner<A> {
         gfx.text_renderer.trim_atlas();
         finisher.present(encoder, &gfx.gpu.queue);
 
-        let had_actions = !actions.is_empty();
-        for action in actions {
-            let tasks = self.app.update(action);
-            self.tasks.spawn(tasks);
-        }
         if had_actions {
-            self.gfx().window.request_redraw();
+            self.request_redraw();
         }
-
-        self.focused_widget = ti::find_focused(&self.state)
-            .map(FocusedWidget::TextInput)
-            .or_else(|| te::find_focused(&self.state).map(FocusedWidget::TextEditor));
-
-        // reset one-frame flags
-        self.mouse.left_just_pressed = false;
-        self.mouse.left_just_released = false;
-        self.mouse.right_just_pressed = false;
-        self.mouse.middle_just_pressed = false;
     }
 }
 
@@ -408,12 +810,6 @@ impl<A: App> ApplicationHandler<Wake> for Runner<A> {
         let shape_renderer = ShapeRenderer::new(&gpu.device, format, w, h);
         let shadow_renderer = ShadowRenderer::new(&gpu.device, &gpu.queue, format, w, h);
 
-        let mut fonts = Fonts::new();
-        self.app.fonts(&mut fonts);
-        if fonts.default.is_none() {
-            fonts.add("default", "Arial", 14.0).default();
-        }
-
         self.gfx = Some(Gfx {
             window,
             gpu,
@@ -421,18 +817,20 @@ impl<A: App> ApplicationHandler<Wake> for Runner<A> {
             text_renderer,
             shape_renderer,
             shadow_renderer,
-            fonts,
             clear_color: self.init.clear_color,
+            list: DisplayList::new(),
             current_cursor: Cursor::Default,
+            ime: None,
         });
 
-        let tasks = self.app.start();
-        self.tasks.spawn(tasks);
-        self.gfx().window.request_redraw();
+        self.shell.start();
+        self.request_redraw();
     }
 
     fn user_event(&mut self, _event_loop: &ActiveEventLoop, _event: Wake) {
-        self.drain_channel();
+        if self.shell.drain_channel() && self.gfx.is_some() {
+            self.request_redraw();
+        }
     }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
@@ -446,12 +844,8 @@ impl<A: App> ApplicationHandler<Wake> for Runner<A> {
                 let scale = self.gfx().scale_factor;
                 let x = (position.x / scale) as f32;
                 let y = (position.y / scale) as f32;
-                let dx = x - self.mouse.x;
-                let dy = y - self.mouse.y;
-                self.mouse.x = x;
-                self.mouse.y = y;
-                self.dispatch_event(Event::MouseMoved { x, y, dx, dy });
-                self.gfx().window.request_redraw();
+                self.shell.mouse_moved(x, y);
+                self.request_redraw();
             }
             WindowEvent::MouseInput { state, button, .. } => {
                 let pressed = state == ElementState::Pressed;
@@ -462,155 +856,72 @@ impl<A: App> ApplicationHandler<Wake> for Runner<A> {
                     _ => None,
                 };
                 if let Some(btn) = btn {
-                    match btn {
-                        MouseButton::Left => {
-                            self.mouse.left_just_pressed = pressed && !self.mouse.left_pressed;
-                            self.mouse.left_just_released = !pressed && self.mouse.left_pressed;
-                            self.mouse.left_pressed = pressed;
-                            if pressed {
-                                let now = self.mouse.click_timer.elapsed().as_secs_f64();
-                                let dt = now - self.mouse.last_click_time;
-                                let dx = self.mouse.x - self.mouse.left_click_x;
-                                let dy = self.mouse.y - self.mouse.left_click_y;
-                                let dist = (dx * dx + dy * dy).sqrt();
-                                const DOUBLE_CLICK_TIME: f64 = 0.3;
-                                const DOUBLE_CLICK_DIST: f32 = 4.0;
-                                if dt < DOUBLE_CLICK_TIME && dist < DOUBLE_CLICK_DIST {
-                                    self.mouse.left_click_count += 1;
-                                } else {
-                                    self.mouse.left_click_count = 1;
-                                }
-                                self.mouse.left_click_x = self.mouse.x;
-                                self.mouse.left_click_y = self.mouse.y;
-                                self.mouse.last_click_time = now;
-                            }
-                        }
-                        MouseButton::Right => {
-                            self.mouse.right_just_pressed = pressed && !self.mouse.right_pressed;
-                            self.mouse.right_pressed = pressed;
-                        }
-                        MouseButton::Middle => {
-                            self.mouse.middle_just_pressed = pressed && !self.mouse.middle_pressed;
-                            self.mouse.middle_pressed = pressed;
-                        }
-                    }
-                    let x = self.mouse.x;
-                    let y = self.mouse.y;
-                    if pressed {
-                        self.dispatch_event(Event::MousePressed { button: btn, x, y });
-                    } else {
-                        self.dispatch_event(Event::MouseReleased { button: btn, x, y });
-                    }
+                    let now = self.shell.mouse.click_timer.elapsed().as_secs_f64();
+                    self.shell.mouse_input(btn, pressed, now);
                 }
-                self.gfx().window.request_redraw();
+                self.request_redraw();
             }
             WindowEvent::MouseWheel { delta, .. } => {
-                let (x, y) = match delta {
-                    MouseScrollDelta::LineDelta(x, y) => (x, y),
-                    MouseScrollDelta::PixelDelta(pos) => (pos.x as f32, pos.y as f32),
+                let scale = self.gfx().scale_factor as f32;
+                let ((x, y), (dx, dy)) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => {
+                        ((x, y), (x * scroll::LINE_HEIGHT, y * scroll::LINE_HEIGHT))
+                    }
+                    MouseScrollDelta::PixelDelta(pos) => {
+                        let (x, y) = (pos.x as f32, pos.y as f32);
+                        ((x, y), (x / scale, y / scale))
+                    }
                 };
-                self.dispatch_event(Event::MouseScrolled { x, y });
-                self.gfx().window.request_redraw();
+                self.shell
+                    .mouse_scrolled(Event::MouseScrolled { x, y }, dx, dy);
+                self.request_redraw();
             }
             WindowEvent::KeyboardInput { event, .. } => {
                 if let PhysicalKey::Code(key_code) = event.physical_key {
                     let key = crate::key_code_to_key(key_code);
                     let pressed = event.state == ElementState::Pressed;
-
-                    match key {
-                        Key::LControl | Key::RControl => self.modifiers.ctrl = pressed,
-                        Key::LShift | Key::RShift => self.modifiers.shift = pressed,
-                        Key::LAlt | Key::RAlt => self.modifiers.alt = pressed,
-                        _ => {}
-                    }
-
-                    let modifiers = self.modifiers;
-                    let bento_event = if pressed {
-                        Event::KeyPressed { key, modifiers }
-                    } else {
-                        Event::KeyReleased { key, modifiers }
-                    };
-
-                    // app gets first priority, if it handles the event, the widget doesnt see it
-                    let app_consumed = if let Some(action) = self.app.event(bento_event.clone()) {
-                        let tasks = self.app.update(action);
-                        self.tasks.spawn(tasks);
-                        self.gfx().window.request_redraw();
-                        true
-                    } else {
-                        false
-                    };
-
-                    // if app didnt consume it and a text widget is focused, route to the widget
-                    if !app_consumed {
-                        if let Some(focused) = &self.focused_widget {
-                            let text = if pressed && !self.modifiers.ctrl {
-                                event.text.as_ref().map(|t| t.as_str()).unwrap_or("")
-                            } else {
-                                ""
-                            };
-
-                            let result = match focused {
-                                FocusedWidget::TextInput(id) => {
-                                    let id = id.clone();
-                                    ti::handle_key(&mut self.state, &id, &bento_event, text).map(
-                                        |v| ti::call_callback::<A::Action>(&self.state, &id, v),
-                                    )
-                                }
-                                FocusedWidget::TextEditor(id) => {
-                                    let id = id.clone();
-                                    te::handle_key(&mut self.state, &id, &bento_event, text).map(
-                                        |v| te::call_callback::<A::Action>(&self.state, &id, v),
-                                    )
-                                }
-                            };
-
-                            if let Some(maybe_action) = result {
-                                if let Some(action) = maybe_action {
-                                    let tasks = self.app.update(action);
-                                    self.tasks.spawn(tasks);
-                                }
-                                self.gfx().window.request_redraw();
-                            }
-                        }
-                    }
+                    let text = event.text.as_ref().map(|t| t.as_str()).unwrap_or("");
+                    self.shell.key_input(key, pressed, text);
                 }
-                self.gfx().window.request_redraw();
+                self.request_redraw();
             }
             WindowEvent::Focused(focused) => {
-                if !focused {
-                    self.modifiers = Modifiers::default();
+                self.shell.window_focused(focused);
+                self.request_redraw();
+            }
+            WindowEvent::Ime(event) => {
+                match event {
+                    Ime::Preedit(text, cursor) => self
+                        .shell
+                        .ime_preedit(&text, cursor.map(|(start, _)| start)),
+                    Ime::Commit(text) => self.shell.ime_commit(&text),
+                    Ime::Disabled => self.shell.ime_preedit("", None),
+                    Ime::Enabled => {}
                 }
-                self.dispatch_event(if focused {
-                    Event::Focused
-                } else {
-                    Event::Unfocused
-                });
-                self.gfx().window.request_redraw();
+                self.request_redraw();
             }
-            WindowEvent::Ime(winit::event::Ime::Commit(_)) => {}
             WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
                 let size = self.gfx().window.inner_size();
                 self.gfx_mut().scale_factor = scale_factor;
                 self.gfx_mut().resize(size.width, size.height);
-                self.dispatch_event(Event::ScaleChanged(scale_factor));
-                self.gfx().window.request_redraw();
+                self.shell.dispatch_event(Event::ScaleChanged(scale_factor));
+                self.request_redraw();
             }
             WindowEvent::Resized(size) => {
                 self.gfx_mut().resize(size.width, size.height);
                 let (w, h) = self.gfx().logical_size();
-                self.dispatch_event(Event::Resized {
+                self.shell.dispatch_event(Event::Resized {
                     width: w,
                     height: h,
                 });
-                self.gfx().window.request_redraw();
+                self.request_redraw();
             }
             WindowEvent::RedrawRequested => {
-                self.drain_channel();
+                self.shell.drain_channel();
                 self.render();
             }
             WindowEvent::CloseRequested => {
-                self.dispatch_event(Event::CloseRequested);
+                self.shell.dispatch_event(Event::CloseRequested);
                 event_loop.exit();
             }
             _ => {}
@@ -0,0 +1,64 @@
+// Clipboard
+// what Ctrl+C / Ctrl+X / Ctrl+V in the text widgets and the clipboard tasks talk to.
+// the window runner uses the system clipboard, the test harness an in-memory one so
+// tests neither touch nor depend on whatever the machine has copied
+
+pub trait Clipboard {
+    // None when the clipboard is empty, holds something that isn't text, or can't be reached
+    fn read(&mut self) -> Option<String>;
+    fn write(&mut self, text: &str);
+}
+
+// the platform clipboard. connecting can fail (no display server, say), in which
+// case reads come back empty and writes go nowhere
+pub struct SystemClipboard {
+    inner: Option<arboard::Clipboard>,
+}
+
+impl SystemClipboard {
+    pub fn new() -> Self {
+        Self {
+            inner: arboard::Clipboard::new().ok(),
+        }
+    }
+}
+
+impl Default for SystemClipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clipboard for SystemClipboard {
+    fn read(&mut self) -> Option<String> {
+        self.inner.as_mut()?.get_text().ok()
+    }
+
+    fn write(&mut self, text: &str) {
+        if let Some(clipboard) = &mut self.inner {
+            let _ = clipboard.set_text(text);
+        }
+    }
+}
+
+// a clipboard that only lives as long as the value does
+#[derive(Default)]
+pub struct MemoryClipboard {
+    text: Option<String>,
+}
+
+impl MemoryClipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Clipboard for MemoryClipboard {
+    fn read(&mut self) -> Option<String> {
+        self.text.clone()
+    }
+
+    fn write(&mut self, text: &str) {
+        self.text = Some(text.to_string());
+    }
+}
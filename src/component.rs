@@ -0,0 +1,91 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::Element;
+use crate::element::Mapper;
+use crate::state::StateStore;
+
+// Component
+// a reusable piece of ui with its own state, view and update, a date picker say. the
+// state lives in the state store under the component's path in the tree, the same id a
+// widget in its place would get (see key.rs), and the events its view produces go to
+// its update instead of the app's. update hands the parent an action only when
+// something the parent cares about happened. component() is a placeholder in the tree
+// until the layout pass builds it, when the state and the path are at hand
+
+pub trait Component<A> {
+    type State: Default + 'static;
+    type Event: Clone + 'static;
+
+    fn view(&self, state: &Self::State) -> Element<Self::Event>;
+
+    fn update(&self, state: &mut Self::State, event: Self::Event) -> Option<A>;
+}
+
+// a component that hasn't been built yet
+pub struct ComponentNode<M: Clone + 'static> {
+    pub id: Option<String>,
+    pub key: Option<String>,
+    pub(crate) build: Build<M>,
+}
+
+// builds the view with the state stored under the id it's given
+pub(crate) type Build<M> = Box<dyn FnOnce(&mut StateStore, &str) -> Element<M>>;
+
+impl<M: Clone + 'static> ComponentNode<M> {
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    // the same component with its parent actions passed through f, see Element::map
+    pub(crate) fn map<B: Clone + 'static>(self, f: &Mapper<M, B>) -> ComponentNode<B> {
+        let (build, f) = (self.build, f.clone());
+        ComponentNode {
+            id: self.id,
+            key: self.key,
+            build: Box::new(move |state, id| build(state, id).map_with(&f)),
+        }
+    }
+}
+
+// the state is shared with the handlers of the component's view, so update can get at
+// it whenever one fires
+struct Local<S>(Rc<RefCell<S>>);
+
+impl<S: Default> Default for Local<S> {
+    fn default() -> Self {
+        Local(Rc::new(RefCell::new(S::default())))
+    }
+}
+
+// set when any component updates, a change the app never hears about still needs a redraw
+#[derive(Default)]
+struct Changed(Rc<Cell<bool>>);
+
+pub fn component<A: Clone + 'static, C: Component<A> + 'static>(component: C) -> Element<A> {
+    Element::Component(ComponentNode {
+        id: None,
+        key: None,
+        build: Box::new(move |store: &mut StateStore, id: &str| {
+            let state = store.get_or_default::<Local<C::State>>(id).0.clone();
+            let changed = store.get_or_default::<Changed>("").0.clone();
+            let view = component.view(&state.borrow());
+            let f: Mapper<C::Event, A> = Rc::new(move |event| {
+                changed.set(true);
+                component.update(&mut state.borrow_mut(), event)
+            });
+            view.map_with(&f)
+        }),
+    })
+}
+
+// whether a component updated since the last call
+pub(crate) fn take_changed(state: &mut StateStore) -> bool {
+    state.get_or_default::<Changed>("").0.take()
+}
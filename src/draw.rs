@@ -1,5 +1,8 @@
+use crate::focus;
+use crate::hit::{hit_test, overlay_order};
 use crate::state::StateStore;
-use crate::{Element, Fonts, ShadowRenderer, ShapeRenderer, TextRenderer};
+use crate::widgets::overlay::Overlay;
+use crate::{DisplayList, Element, Fonts};
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum Cursor {
@@ -35,11 +38,38 @@ pub struct MouseState {
     pub(crate) last_click_time: f64,
 }
 
+impl MouseState {
+    pub(crate) fn new() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            left_pressed: false,
+            left_just_pressed: false,
+            left_just_released: false,
+            right_pressed: false,
+            right_just_pressed: false,
+            middle_pressed: false,
+            middle_just_pressed: false,
+            left_click_count: 0,
+            left_click_x: 0.0,
+            left_click_y: 0.0,
+            click_timer: std::time::Instant::now(),
+            last_click_time: -1.0,
+        }
+    }
+
+    // clear the flags that only last for the frame they happened in
+    pub(crate) fn end_frame(&mut self) {
+        self.left_just_pressed = false;
+        self.left_just_released = false;
+        self.right_just_pressed = false;
+        self.middle_just_pressed = false;
+    }
+}
+
 // everything a widget needs to draw itself
 pub struct DrawCtx<'a, M> {
-    pub sr: &'a mut ShapeRenderer,
-    pub shadow: &'a mut ShadowRenderer,
-    pub tr: &'a mut TextRenderer,
+    pub list: &'a mut DisplayList,
     pub fonts: &'a mut Fonts,
     pub state: &'a mut StateStore,
     pub mouse: &'a MouseState,
@@ -49,11 +79,16 @@ pub struct DrawCtx<'a, M> {
     pub cursor: &'a mut Option<Cursor>,
 }
 
+impl<M> DrawCtx<'_, M> {
+    // whether the widget with this id has keyboard focus
+    pub fn focused(&self, id: &str) -> bool {
+        focus::is_focused(self.state, id)
+    }
+}
+
 pub fn draw<M: Clone + 'static>(
     element: &mut Element<M>,
-    sr: &mut ShapeRenderer,
-    shadow: &mut ShadowRenderer,
-    tr: &mut TextRenderer,
+    list: &mut DisplayList,
     fonts: &mut Fonts,
     state: &mut StateStore,
     mouse: &MouseState,
@@ -61,10 +96,11 @@ pub fn draw<M: Clone + 'static>(
 ) -> (Vec<M>, Option<Cursor>) {
     let mut actions = Vec::new();
     let mut cursor = None;
+
+    hit_test(element, mouse.x, mouse.y);
+
     let mut ctx = DrawCtx {
-        sr,
-        shadow,
-        tr,
+        list,
         fonts,
         state,
         mouse,
@@ -74,19 +110,52 @@ pub fn draw<M: Clone + 'static>(
         cursor: &mut cursor,
     };
     draw_element(element, &mut ctx);
+
+    // overlays go on top of everything, see hit::overlay_order
+    for i in overlay_order(element) {
+        let mut n = i;
+        if let Some(o) = nth_overlay(element, &mut n) {
+            o.draw(&mut ctx);
+        }
+    }
     (actions, cursor)
 }
 
+// the overlay at index n, counting in tree order with nested overlays right after their parent
+pub(crate) fn nth_overlay<'a, M: Clone + 'static>(
+    el: &'a mut Element<M>,
+    n: &mut usize,
+) -> Option<&'a mut Overlay<M>> {
+    match el {
+        Element::Overlay(o) => {
+            if *n == 0 {
+                return Some(o);
+            }
+            *n -= 1;
+            nth_overlay(&mut o.child, n)
+        }
+        Element::Row(r) => r.children.iter_mut().find_map(|c| nth_overlay(c, n)),
+        Element::Column(c) => c.children.iter_mut().find_map(|c| nth_overlay(c, n)),
+        _ => None,
+    }
+}
+
 pub fn draw_element<M: Clone + 'static>(el: &mut Element<M>, ctx: &mut DrawCtx<M>) {
     match el {
-        Element::Empty => {}
-        Element::Rect(r) => r.draw(ctx),
-        Element::Text(t) => t.draw(ctx),
-        Element::Button(b) => b.draw(ctx),
-        Element::TextInput(t) => t.draw(ctx),
-        Element::TextEditor(t) => t.draw(ctx),
-        Element::Row(r) => r.draw(ctx),
-        Element::Column(c) => c.draw(ctx),
+        // painted by draw() once the rest of the tree is done
+        Element::Overlay(_) => {}
+        // the built-in widgets fire their own handlers, at the point in draw they need to
+        Element::Custom(w) => {
+            w.draw(ctx);
+            if let Some(i) = w.interactions() {
+                check_interactions(i, ctx);
+            }
+        }
+        el => {
+            if let Some(w) = el.widget_mut() {
+                w.draw(ctx);
+            }
+        }
     }
 }
 
@@ -129,8 +198,9 @@ pub fn with_opacity(mut color: [f32; 4], opacity: f32) -> [f32; 4] {
     color
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn draw_shape(
-    sr: &mut ShapeRenderer,
+    list: &mut DisplayList,
     x: f32,
     y: f32,
     w: f32,
@@ -139,16 +209,9 @@ pub fn draw_shape(
     border_radius: f32,
     border_color: [f32; 4],
     border_thickness: f32,
-    clip: Option<[f32; 4]>,
 ) {
     if border_radius > 0.0 {
-        if let Some([cx, cy, cx2, cy2]) = clip {
-            if x < cx || y < cy || x + w > cx2 || y + h > cy2 {
-                sr.draw_rect_clipped(x, y, w, h, color, [cx, cy, cx2, cy2]);
-                return;
-            }
-        }
-        sr.draw_rounded_rect(
+        list.rounded_rect(
             x,
             y,
             w,
@@ -158,23 +221,14 @@ pub fn draw_shape(
             border_color,
             border_thickness,
         );
-    } else if let Some([cx, cy, cx2, cy2]) = clip {
-        sr.draw_rect_clipped(x, y, w, h, color, [cx, cy, cx2, cy2]);
     } else {
-        sr.draw_rect(x, y, w, h, color, border_color, border_thickness);
+        list.rect(x, y, w, h, color, border_color, border_thickness);
     }
 }
 
-pub fn draw_shadow(
-    shadow: &mut ShadowRenderer,
-    x: f32,
-    y: f32,
-    w: f32,
-    h: f32,
-    style: &crate::Style,
-) {
+pub fn draw_shadow(list: &mut DisplayList, x: f32, y: f32, w: f32, h: f32, style: &crate::Style) {
     if style.shadow_color.a > 0.0 && style.shadow_blur > 0.0 {
-        shadow.draw_shadow(
+        list.shadow(
             x,
             y,
             w,
@@ -188,30 +242,24 @@ pub fn draw_shadow(
     }
 }
 
+// fires the handlers of an element the pointer event reached, see hit.rs
 pub fn check_interactions<M: Clone + 'static>(
     interactions: &crate::Interactions<M>,
-    x: f32,
-    y: f32,
-    w: f32,
-    h: f32,
     ctx: &mut DrawCtx<M>,
 ) {
-    let hovered =
-        ctx.mouse.x >= x && ctx.mouse.x <= x + w && ctx.mouse.y >= y && ctx.mouse.y <= y + h;
-
-    if hovered {
-        if let Some(a) = &interactions.on_hover {
-            ctx.actions.push(a.clone());
+    if interactions.targeted {
+        if let Some(h) = &interactions.on_hover {
+            ctx.actions.extend(h.fire());
         }
-        if ctx.mouse.left_just_pressed {
-            if let Some(a) = &interactions.on_mouse_down {
-                ctx.actions.push(a.clone());
-            }
+        if ctx.mouse.left_just_pressed
+            && let Some(h) = &interactions.on_mouse_down
+        {
+            ctx.actions.extend(h.fire());
         }
-        if ctx.mouse.left_just_released {
-            if let Some(a) = &interactions.on_click {
-                ctx.actions.push(a.clone());
-            }
+        if ctx.mouse.left_just_released
+            && let Some(h) = &interactions.on_click
+        {
+            ctx.actions.extend(h.fire());
         }
     }
 }
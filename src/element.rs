@@ -1,8 +1,16 @@
-use crate::Color;
+use std::rc::Rc;
+
+use crate::component::ComponentNode;
+use crate::widget::Mapped;
 use crate::widgets::containers::{Column, Row};
+use crate::widgets::overlay::Overlay;
+pub use crate::widgets::overlay::{Anchor, Placement};
+pub use crate::widgets::text_editor::{Matches, Search, TextEdit};
+pub use crate::widgets::text_input::Validation;
 use crate::widgets::{
     button::Button, rect::Rect, text::Text, text_editor::TextEditor, text_input::TextInput,
 };
+use crate::{Color, SpanStyle, Widget};
 
 // alignment
 
@@ -19,7 +27,7 @@ pub enum Align {
 
 // text align
 
-#[derive(Clone, Copy, PartialEq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub enum TextAlign {
     #[default]
     Left,
@@ -242,9 +250,15 @@ pub enum Overflow {
 // interactions — attached to any element
 
 pub struct Interactions<M> {
-    pub on_click: Option<M>,
-    pub on_hover: Option<M>, // fires every frame while hovered
-    pub on_mouse_down: Option<M>,
+    pub on_click: Option<Handler<M>>,
+    pub on_hover: Option<Handler<M>>, // fires every frame while hovered
+    pub on_mouse_down: Option<Handler<M>>,
+    // keeps pointer events from bubbling up to ancestors
+    pub stop_propagation: bool,
+    // written by the hit test each frame: under the pointer and not covered,
+    // and whether the current pointer event reaches this element
+    pub(crate) hovered: bool,
+    pub(crate) targeted: bool,
 }
 
 impl<M> Default for Interactions<M> {
@@ -253,10 +267,88 @@ impl<M> Default for Interactions<M> {
             on_click: None,
             on_hover: None,
             on_mouse_down: None,
+            stop_propagation: false,
+            hovered: false,
+            targeted: false,
         }
     }
 }
 
+impl<M> Interactions<M> {
+    // under the pointer and not covered by anything, for drawing a hover state
+    pub fn hovered(&self) -> bool {
+        self.hovered
+    }
+
+    // whether the current pointer event reaches this element
+    pub fn targeted(&self) -> bool {
+        self.targeted
+    }
+}
+
+impl<M: Clone + 'static> Interactions<M> {
+    pub(crate) fn map<B: Clone + 'static>(self, f: &Mapper<M, B>) -> Interactions<B> {
+        Interactions {
+            on_click: self.on_click.map(|h| h.map(f)),
+            on_hover: self.on_hover.map(|h| h.map(f)),
+            on_mouse_down: self.on_mouse_down.map(|h| h.map(f)),
+            stop_propagation: self.stop_propagation,
+            hovered: self.hovered,
+            targeted: self.targeted,
+        }
+    }
+}
+
+// handler — the action an interaction fires. it's only worked out when the handler
+// fires, so a component's update (see component.rs) runs then and not in view(),
+// and can decide there's nothing for the parent
+
+pub struct Handler<M>(Rc<dyn Fn() -> Option<M>>);
+
+impl<M: Clone + 'static> Handler<M> {
+    pub fn new(action: M) -> Self {
+        Handler(Rc::new(move || Some(action.clone())))
+    }
+
+    pub(crate) fn fire(&self) -> Option<M> {
+        (self.0)()
+    }
+
+    pub(crate) fn map<B: Clone + 'static>(&self, f: &Mapper<M, B>) -> Handler<B> {
+        let (h, f) = (self.0.clone(), f.clone());
+        Handler(Rc::new(move || h().and_then(&*f)))
+    }
+}
+
+impl<M> Clone for Handler<M> {
+    fn clone(&self) -> Self {
+        Handler(self.0.clone())
+    }
+}
+
+// the closures widgets call with their value (on_change and such), None when a
+// mapped one has nothing to say
+pub(crate) type Callback<T, M> = Box<dyn Fn(T) -> Option<M>>;
+
+pub(crate) fn callback<T, M>(f: impl Fn(T) -> M + 'static) -> Callback<T, M> {
+    Box::new(move |t| Some(f(t)))
+}
+
+// map — lets a module's view produce its own action type, the parent turns those
+// into its own with Element::map. every handler in the mapped tree shares the one f,
+// which can also drop an action, the way a component does
+
+pub(crate) type Mapper<A, B> = Rc<dyn Fn(A) -> Option<B>>;
+
+// a widget's callback followed by f
+pub(crate) fn map_handler<T: 'static, A: 'static, B: 'static>(
+    handler: Option<Callback<T, A>>,
+    f: &Mapper<A, B>,
+) -> Option<Callback<T, B>> {
+    let f = f.clone();
+    handler.map(|h| Box::new(move |t| h(t).and_then(&*f)) as Callback<T, B>)
+}
+
 // layout — sizing, flex, alignment, spacing, position
 // consumed by the layout pass (taffy), not used for rendering
 
@@ -374,12 +466,69 @@ pub enum Element<M: Clone + 'static = ()> {
     TextEditor(TextEditor<M>),
     Row(Row<M>),
     Column(Column<M>),
+    Overlay(Overlay<M>),
+    Custom(Box<dyn Widget<M>>),
+    // built by the shell before layout, see component.rs
+    Component(ComponentNode<M>),
+}
+
+impl<M: Clone + 'static> Element<M> {
+    // the same tree with every action it produces passed through f, for embedding a
+    // view that has its own action type
+    pub fn map<B: Clone + 'static>(self, f: impl Fn(M) -> B + 'static) -> Element<B> {
+        self.map_with(&(Rc::new(move |m| Some(f(m))) as Mapper<M, B>))
+    }
+
+    pub(crate) fn map_with<B: Clone + 'static>(self, f: &Mapper<M, B>) -> Element<B> {
+        match self {
+            Element::Empty => Element::Empty,
+            Element::Rect(w) => Element::Rect(w.map(f)),
+            Element::Text(w) => Element::Text(w.map(f)),
+            Element::Button(w) => Element::Button(w.map(f)),
+            Element::TextInput(w) => Element::TextInput(w.map(f)),
+            Element::TextEditor(w) => Element::TextEditor(w.map(f)),
+            Element::Row(w) => Element::Row(w.map(f)),
+            Element::Column(w) => Element::Column(w.map(f)),
+            Element::Overlay(w) => Element::Overlay(w.map(f)),
+            Element::Custom(w) => Element::Custom(Box::new(Mapped::new(w, f))),
+            Element::Component(node) => Element::Component(node.map(f)),
+        }
+    }
+
+    // the element as a widget, None for the ones the tree places itself
+    pub(crate) fn widget(&self) -> Option<&dyn Widget<M>> {
+        Some(match self {
+            Element::Empty | Element::Overlay(_) | Element::Component(_) => return None,
+            Element::Rect(w) => w,
+            Element::Text(w) => w,
+            Element::Button(w) => w,
+            Element::TextInput(w) => w,
+            Element::TextEditor(w) => w,
+            Element::Row(w) => w,
+            Element::Column(w) => w,
+            Element::Custom(w) => w.as_ref(),
+        })
+    }
+
+    pub(crate) fn widget_mut(&mut self) -> Option<&mut dyn Widget<M>> {
+        Some(match self {
+            Element::Empty | Element::Overlay(_) | Element::Component(_) => return None,
+            Element::Rect(w) => w,
+            Element::Text(w) => w,
+            Element::Button(w) => w,
+            Element::TextInput(w) => w,
+            Element::TextEditor(w) => w,
+            Element::Row(w) => w,
+            Element::Column(w) => w,
+            Element::Custom(w) => w.as_mut(),
+        })
+    }
 }
 
 // forwarding methods on Element so button("label").on_click(...) etc. keep working
 
 impl<M: Clone + 'static> Element<M> {
-    // id — forwarded to all widgets
+    // id and key — forwarded to all widgets
     pub fn id(self, id: &str) -> Self {
         match self {
             Element::Rect(w) => Element::Rect(w.id(id)),
@@ -389,6 +538,48 @@ impl<M: Clone + 'static> Element<M> {
             Element::TextEditor(w) => Element::TextEditor(w.id(id)),
             Element::Row(w) => Element::Row(w.id(id)),
             Element::Column(w) => Element::Column(w.id(id)),
+            Element::Component(c) => Element::Component(c.id(id)),
+            other => other,
+        }
+    }
+    // stands in for the position in the parent when deriving the id, so an item keeps
+    // its state when its list is reordered, see key.rs
+    pub fn key(self, key: &str) -> Self {
+        match self {
+            Element::Rect(w) => Element::Rect(w.key(key)),
+            Element::Text(w) => Element::Text(w.key(key)),
+            Element::Button(w) => Element::Button(w.key(key)),
+            Element::TextInput(w) => Element::TextInput(w.key(key)),
+            Element::TextEditor(w) => Element::TextEditor(w.key(key)),
+            Element::Row(w) => Element::Row(w.key(key)),
+            Element::Column(w) => Element::Column(w.key(key)),
+            Element::Component(c) => Element::Component(c.key(key)),
+            other => other,
+        }
+    }
+
+    // overlay — only meaningful on overlay(...)
+    pub fn anchor(self, id: &str) -> Self {
+        match self {
+            Element::Overlay(w) => Element::Overlay(w.anchor(id)),
+            other => other,
+        }
+    }
+    pub fn placement(self, placement: Placement) -> Self {
+        match self {
+            Element::Overlay(w) => Element::Overlay(w.placement(placement)),
+            other => other,
+        }
+    }
+    pub fn offset(self, x: f32, y: f32) -> Self {
+        match self {
+            Element::Overlay(w) => Element::Overlay(w.offset(x, y)),
+            other => other,
+        }
+    }
+    pub fn z_index(self, z: i32) -> Self {
+        match self {
+            Element::Overlay(w) => Element::Overlay(w.z_index(z)),
             other => other,
         }
     }
@@ -426,6 +617,26 @@ impl<M: Clone + 'static> Element<M> {
         }
     }
 
+    pub fn tab_index(self, index: i32) -> Self {
+        match self {
+            Element::Button(w) => Element::Button(w.tab_index(index)),
+            Element::TextInput(w) => Element::TextInput(w.tab_index(index)),
+            Element::TextEditor(w) => Element::TextEditor(w.tab_index(index)),
+            other => other,
+        }
+    }
+    pub fn stop_propagation(self) -> Self {
+        match self {
+            Element::Rect(w) => Element::Rect(w.stop_propagation()),
+            Element::Button(w) => Element::Button(w.stop_propagation()),
+            Element::TextInput(w) => Element::TextInput(w.stop_propagation()),
+            Element::TextEditor(w) => Element::TextEditor(w.stop_propagation()),
+            Element::Row(w) => Element::Row(w.stop_propagation()),
+            Element::Column(w) => Element::Column(w.stop_propagation()),
+            other => other,
+        }
+    }
+
     // layout
     pub fn width(self, v: Val) -> Self {
         match self {
@@ -728,6 +939,124 @@ impl<M: Clone + 'static> Element<M> {
             other => other,
         }
     }
+    pub fn on_submit(self, f: impl Fn(String) -> M + 'static) -> Self {
+        match self {
+            Element::TextInput(w) => Element::TextInput(w.on_submit(f)),
+            other => other,
+        }
+    }
+    pub fn on_focus(self, msg: M) -> Self {
+        match self {
+            Element::TextInput(w) => Element::TextInput(w.on_focus(msg)),
+            other => other,
+        }
+    }
+    pub fn on_blur(self, msg: M) -> Self {
+        match self {
+            Element::TextInput(w) => Element::TextInput(w.on_blur(msg)),
+            other => other,
+        }
+    }
+    pub fn password(self, password: bool) -> Self {
+        match self {
+            Element::TextInput(w) => Element::TextInput(w.password(password)),
+            other => other,
+        }
+    }
+    pub fn revealed(self, revealed: bool) -> Self {
+        match self {
+            Element::TextInput(w) => Element::TextInput(w.revealed(revealed)),
+            other => other,
+        }
+    }
+    pub fn max_length(self, chars: usize) -> Self {
+        match self {
+            Element::TextInput(w) => Element::TextInput(w.max_length(chars)),
+            other => other,
+        }
+    }
+    pub fn numeric(self, min: f64, max: f64, step: f64) -> Self {
+        match self {
+            Element::TextInput(w) => Element::TextInput(w.numeric(min, max, step)),
+            other => other,
+        }
+    }
+    pub fn validate(self, f: impl Fn(&str) -> Validation + 'static) -> Self {
+        match self {
+            Element::TextInput(w) => Element::TextInput(w.validate(f)),
+            other => other,
+        }
+    }
+    pub fn error_color(self, color: Color) -> Self {
+        match self {
+            Element::TextInput(w) => Element::TextInput(w.error_color(color)),
+            other => other,
+        }
+    }
+    pub fn on_error(self, f: impl Fn(Option<String>) -> M + 'static) -> Self {
+        match self {
+            Element::TextInput(w) => Element::TextInput(w.on_error(f)),
+            other => other,
+        }
+    }
+    pub fn on_edit(self, f: impl Fn(TextEdit) -> M + 'static) -> Self {
+        match self {
+            Element::TextEditor(w) => Element::TextEditor(w.on_edit(f)),
+            other => other,
+        }
+    }
+    pub fn highlight(
+        self,
+        f: impl Fn(&str) -> Vec<(std::ops::Range<usize>, SpanStyle)> + 'static,
+    ) -> Self {
+        match self {
+            Element::TextEditor(w) => Element::TextEditor(w.highlight(f)),
+            other => other,
+        }
+    }
+    pub fn revision(self, revision: u64) -> Self {
+        match self {
+            Element::TextEditor(w) => Element::TextEditor(w.revision(revision)),
+            other => other,
+        }
+    }
+    // a text editor's own wrap, wrap() is the rows' and columns'
+    pub fn soft_wrap(self, wrap: bool) -> Self {
+        match self {
+            Element::TextEditor(w) => Element::TextEditor(w.wrap(wrap)),
+            other => other,
+        }
+    }
+    pub fn line_numbers(self, show: bool) -> Self {
+        match self {
+            Element::TextEditor(w) => Element::TextEditor(w.line_numbers(show)),
+            other => other,
+        }
+    }
+    pub fn current_line(self, color: Color) -> Self {
+        match self {
+            Element::TextEditor(w) => Element::TextEditor(w.current_line(color)),
+            other => other,
+        }
+    }
+    pub fn indent(self, unit: &str) -> Self {
+        match self {
+            Element::TextEditor(w) => Element::TextEditor(w.indent(unit)),
+            other => other,
+        }
+    }
+    pub fn search(self, query: Search) -> Self {
+        match self {
+            Element::TextEditor(w) => Element::TextEditor(w.search(query)),
+            other => other,
+        }
+    }
+    pub fn on_matches(self, f: impl Fn(Matches) -> M + 'static) -> Self {
+        match self {
+            Element::TextEditor(w) => Element::TextEditor(w.on_matches(f)),
+            other => other,
+        }
+    }
 }
 
 // constructor functions — public API, identical to before from user's perspective
@@ -764,6 +1093,16 @@ pub fn column<M: Clone + 'static>(children: Vec<Element<M>>) -> Element<M> {
     Element::Column(Column::new(children))
 }
 
+// content drawn above everything else, anchored to the window until .anchor() is set
+pub fn overlay<M: Clone + 'static>(child: Element<M>) -> Element<M> {
+    Element::Overlay(Overlay::new(child))
+}
+
+// an app's own widget, see Widget
+pub fn custom<M: Clone + 'static>(widget: impl Widget<M> + 'static) -> Element<M> {
+    Element::Custom(Box::new(widget))
+}
+
 pub fn exit() {
     std::process::exit(0);
 }
@@ -133,6 +133,7 @@ pub enum Key {
     Minus,
     Space,
     Enter,
+    Tab,
     Backspace,
     Delete,
     Insert,
@@ -295,6 +296,7 @@ pub fn key_code_to_key(key: KeyCode) -> Key {
         KeyCode::Minus => Key::Minus,
         KeyCode::Space => Key::Space,
         KeyCode::Enter => Key::Enter,
+        KeyCode::Tab => Key::Tab,
         KeyCode::Backspace => Key::Backspace,
         KeyCode::Delete => Key::Delete,
         KeyCode::Insert => Key::Insert,
@@ -0,0 +1,194 @@
+use crate::ime;
+use crate::state::StateStore;
+use crate::widgets::text_editor::TextEditorState;
+use crate::widgets::text_input::TextInputState;
+use crate::{Color, DisplayList, Element};
+
+// Focus
+// which widget has keyboard focus, kept in the state store so both the draw pass and
+// the shell can reach it. text inputs, text editors, buttons and focusable custom
+// widgets can be focused, by clicking them or with Tab / Shift+Tab. the tab order is
+// rebuilt from the tree every frame: positive tab indices first in ascending order,
+// then everything else in tree order. a negative tab index still takes focus on click
+// but is skipped by Tab
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum FocusKind {
+    TextInput,
+    TextEditor,
+    Button,
+    // an app's own widget, see Widget::focusable
+    Custom,
+}
+
+#[derive(Default)]
+pub(crate) struct FocusState {
+    pub(crate) focused: Option<(String, FocusKind)>,
+    // the ring only shows once focus was moved with the keyboard, not on click
+    pub(crate) ring: bool,
+    // every focusable widget this frame as (key, kind, tab index), in tree order
+    order: Vec<(String, FocusKind, i32)>,
+    // widgets that gained (true) or lost focus since the shell last asked, oldest first
+    changes: Vec<(String, FocusKind, bool)>,
+}
+
+// there's one focus for the whole window, stored under an empty id
+const KEY: &str = "";
+
+pub(crate) fn focused(state: &StateStore) -> Option<(&str, FocusKind)> {
+    let f = state.get::<FocusState>(KEY)?;
+    f.focused.as_ref().map(|(id, kind)| (id.as_str(), *kind))
+}
+
+pub(crate) fn is_focused(state: &StateStore, key: &str) -> bool {
+    focused(state).is_some_and(|(id, _)| id == key)
+}
+
+pub(crate) fn ring_visible(state: &StateStore, key: &str) -> bool {
+    is_focused(state, key) && state.get::<FocusState>(KEY).is_some_and(|f| f.ring)
+}
+
+// moves focus, keeping the focused flags in the text widgets' own state in step
+pub(crate) fn set(state: &mut StateStore, target: Option<(&str, FocusKind)>, ring: bool) {
+    let f = state.get_or_default_mut::<FocusState>(KEY);
+    let old = f.focused.take();
+    f.focused = target.map(|(id, kind)| (id.to_string(), kind));
+    f.ring = ring && target.is_some();
+
+    // a composition in progress belongs to the widget that had focus. both widgets'
+    // on_blur and on_focus fire once the shell takes the changes
+    if old.as_ref().map(|(id, _)| id.as_str()) != target.map(|(id, _)| id) {
+        ime::clear(state);
+        let changes = &mut state.get_or_default_mut::<FocusState>(KEY).changes;
+        if let Some((id, kind)) = &old {
+            changes.push((id.clone(), *kind, false));
+        }
+        if let Some((id, kind)) = target {
+            changes.push((id.to_string(), kind, true));
+        }
+    }
+
+    if let Some((id, kind)) = old {
+        set_flag(state, &id, kind, false);
+    }
+    if let Some((id, kind)) = target {
+        set_flag(state, id, kind, true);
+    }
+}
+
+fn set_flag(state: &mut StateStore, id: &str, kind: FocusKind, value: bool) {
+    match kind {
+        FocusKind::TextInput => state.get_or_default_mut::<TextInputState>(id).focused = value,
+        FocusKind::TextEditor => state.get_or_default_mut::<TextEditorState>(id).focused = value,
+        FocusKind::Button | FocusKind::Custom => {}
+    }
+}
+
+// the focus changes since the last call, for the shell to tell the widgets' apps
+pub(crate) fn take_changes(state: &mut StateStore) -> Vec<(String, FocusKind, bool)> {
+    std::mem::take(&mut state.get_or_default_mut::<FocusState>(KEY).changes)
+}
+
+// focus by id, for Task::focus. ids that aren't focusable this frame are ignored
+pub(crate) fn focus_id(state: &mut StateStore, id: &str) {
+    let kind = state
+        .get::<FocusState>(KEY)
+        .and_then(|f| f.order.iter().find(|(key, _, _)| key == id))
+        .map(|(_, kind, _)| *kind);
+    if let Some(kind) = kind {
+        set(state, Some((id, kind)), false);
+    }
+}
+
+// called by focusable widgets on a left press: the one under the pointer takes focus,
+// the focused one lets go if the press landed anywhere else
+pub(crate) fn press(state: &mut StateStore, key: &str, kind: FocusKind, hovered: bool) {
+    if hovered {
+        set(state, Some((key, kind)), false);
+    } else if is_focused(state, key) {
+        set(state, None, false);
+    }
+}
+
+// Tab moves forward, Shift+Tab back, wrapping at either end
+pub(crate) fn step(state: &mut StateStore, forward: bool) {
+    let f = state.get_or_default::<FocusState>(KEY);
+    let mut order: Vec<&(String, FocusKind, i32)> =
+        f.order.iter().filter(|(_, _, tab)| *tab >= 0).collect();
+    // stable, so equal tab indices keep tree order and 0 goes after every positive one
+    order.sort_by_key(|(_, _, tab)| if *tab == 0 { i32::MAX } else { *tab });
+    if order.is_empty() {
+        return;
+    }
+
+    let current = f
+        .focused
+        .as_ref()
+        .and_then(|(id, _)| order.iter().position(|(key, _, _)| key == id));
+    let next = match (current, forward) {
+        (Some(i), true) => (i + 1) % order.len(),
+        (Some(i), false) => (i + order.len() - 1) % order.len(),
+        (None, true) => 0,
+        (None, false) => order.len() - 1,
+    };
+    let (key, kind, _) = order[next].clone();
+    set(state, Some((&key, kind)), true);
+}
+
+// rebuilds the tab order from the laid out tree. focus on a widget that's gone is dropped
+pub(crate) fn collect<M: Clone + 'static>(element: &mut Element<M>, state: &mut StateStore) {
+    let mut order = Vec::new();
+    walk(element, &mut order);
+
+    let gone = focused(state).is_some_and(|(id, _)| !order.iter().any(|(key, _, _)| key == id));
+    if gone {
+        set(state, None, false);
+    }
+    state.get_or_default_mut::<FocusState>(KEY).order = order;
+}
+
+fn walk<M: Clone + 'static>(el: &mut Element<M>, order: &mut Vec<(String, FocusKind, i32)>) {
+    match el {
+        Element::Button(b) => {
+            order.push((b.require_id().to_string(), FocusKind::Button, b.tab_index));
+        }
+        Element::TextInput(t) => {
+            order.push((
+                t.require_id().to_string(),
+                FocusKind::TextInput,
+                t.tab_index,
+            ));
+        }
+        Element::TextEditor(t) => {
+            order.push((
+                t.require_id().to_string(),
+                FocusKind::TextEditor,
+                t.tab_index,
+            ));
+        }
+        Element::Row(r) => r.children.iter_mut().for_each(|c| walk(c, order)),
+        Element::Column(c) => c.children.iter_mut().for_each(|c| walk(c, order)),
+        Element::Custom(w) => {
+            if let (true, Some(id)) = (w.focusable(), w.id()) {
+                order.push((id.to_string(), FocusKind::Custom, w.tab_index()));
+            }
+        }
+        Element::Overlay(o) => walk(&mut o.child, order),
+        Element::Empty | Element::Rect(_) | Element::Text(_) | Element::Component(_) => {}
+    }
+}
+
+// outline drawn just outside a keyboard focused widget
+pub(crate) fn draw_ring(list: &mut DisplayList, x: f32, y: f32, w: f32, h: f32, radius: f32) {
+    let pad = 3.0;
+    list.rounded_rect(
+        x - pad,
+        y - pad,
+        w + pad * 2.0,
+        h + pad * 2.0,
+        (radius + pad).max(pad),
+        Color::TRANSPARENT.to_array(),
+        Color::new(0.45, 0.6, 1.0, 1.0).to_array(),
+        2.0,
+    );
+}
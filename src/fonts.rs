@@ -1,5 +1,11 @@
-use glyphon::{Attrs, Buffer, Family, FontSystem, Metrics, Shaping, Weight, fontdb};
+use glyphon::cosmic_text::Align as CosmicAlign;
+use glyphon::{Attrs, Buffer, Family, FontSystem, Metrics, Shaping, Style, Weight, Wrap, fontdb};
 use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Range;
+
+use crate::render::text_renderer::rich_spans;
+use crate::{SpanStyle, TextAlign, TextRun};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct FontId(pub(crate) usize);
@@ -9,11 +15,26 @@ pub struct FontEntry {
     pub size: f32,
 }
 
+// the part of a run's spans that changes its shape, colors don't: range, weight, italic
+type ShapeSpans = Vec<(Range<usize>, Option<u16>, bool)>;
+
+// font, text, size, weight and spans of measure_spans
+type SpansKey = (usize, String, u32, u16, ShapeSpans);
+
+// family, text, size, weight, italic, align, width and spans of a drawn run
+type ExtentKey = (String, String, u32, u16, bool, u8, u32, ShapeSpans);
+
+// past this many entries a cache starts over, an editor measures every line it shows
+// and each edit of one is new text
+const CACHE_LIMIT: usize = 4096;
+
 pub struct Fonts {
     pub(crate) font_system: FontSystem,
     entries: Vec<FontEntry>,
     // cache key includes weight so heavier fonts don't alias to lighter measurements
     measure_cache: HashMap<(usize, String, u32, u16), (f32, f32)>,
+    spans_cache: HashMap<SpansKey, (f32, f32)>,
+    extent_cache: HashMap<ExtentKey, [f32; 3]>,
     name_to_id: HashMap<String, FontId>,
     pub(crate) default: Option<FontId>,
     fonts_loaded: bool,
@@ -31,6 +52,12 @@ impl<'a> FontBuilder<'a> {
     }
 }
 
+impl Default for Fonts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Fonts {
     pub fn new() -> Self {
         let db = fontdb::Database::new();
@@ -39,6 +66,8 @@ impl Fonts {
             font_system,
             entries: Vec::new(),
             measure_cache: HashMap::new(),
+            spans_cache: HashMap::new(),
+            extent_cache: HashMap::new(),
             name_to_id: HashMap::new(),
             default: None,
             fonts_loaded: false,
@@ -64,6 +93,16 @@ impl Fonts {
         FontBuilder { fonts: self, id }
     }
 
+    // a font file's bytes, for fonts that ship with the app rather than being installed.
+    // add() finds it by its family name like any other
+    pub fn load(&mut self, data: Vec<u8>) {
+        self.font_system.db_mut().load_font_data(data);
+        // a family that was missing may measure differently now
+        self.measure_cache.clear();
+        self.spans_cache.clear();
+        self.extent_cache.clear();
+    }
+
     pub fn get(&self, id: FontId) -> &FontEntry {
         &self.entries[id.0]
     }
@@ -84,7 +123,7 @@ impl Fonts {
     }
 
     // measure at the font's default size and weight 400
-    // used by widgets (button, text) that don't vary weight at measure time
+    // used by widgets (button) that don't vary weight at measure time
     pub fn measure(&mut self, text: &str, id: FontId) -> (f32, f32) {
         let size = self.entries[id.0].size;
         self.measure_sized(text, id, size, 400)
@@ -118,7 +157,168 @@ impl Fonts {
             height += line_height;
         }
         let result = (width, height);
-        self.measure_cache.insert(key, result);
+        insert_bounded(&mut self.measure_cache, key, result);
         result
     }
+
+    // measure_sized for text with styled spans. only weight and italic change the
+    // size, when no span sets either it's the plain measurement
+    pub fn measure_spans(
+        &mut self,
+        text: &str,
+        id: FontId,
+        size: f32,
+        weight: u16,
+        spans: &[(Range<usize>, SpanStyle)],
+    ) -> (f32, f32) {
+        let shaping = shape_spans(spans);
+        if shaping.is_empty() {
+            return self.measure_sized(text, id, size, weight);
+        }
+        let key = (
+            id.0,
+            text.to_string(),
+            (size * 10.0) as u32,
+            weight,
+            shaping,
+        );
+        if let Some(&cached) = self.spans_cache.get(&key) {
+            return cached;
+        }
+        let family = self.entries[id.0].family.clone();
+        let line_height = size * 1.4;
+        let mut buffer = Buffer::new(&mut self.font_system, Metrics::new(size, line_height));
+        buffer.set_size(&mut self.font_system, None, None);
+        let attrs = Attrs::new()
+            .family(Family::Name(family.as_str()))
+            .weight(Weight(weight));
+        buffer.set_rich_text(
+            &mut self.font_system,
+            rich_spans(text, spans, &attrs),
+            &attrs,
+            Shaping::Advanced,
+            None,
+        );
+        buffer.shape_until_scroll(&mut self.font_system, false);
+        let mut width: f32 = 0.0;
+        let mut height: f32 = 0.0;
+        for run in buffer.layout_runs() {
+            width = width.max(run.line_w);
+            height += line_height;
+        }
+        insert_bounded(&mut self.spans_cache, key, (width, height));
+        (width, height)
+    }
+
+    // where one line of text breaks when wrapped to width: the byte offset each visual
+    // row after the first starts at, so empty if it fits. breaks between words where
+    // it can, inside a word that's wider than width on its own. not cached, the editor
+    // keeps each line's breaks until the line changes
+    pub fn wrap(
+        &mut self,
+        text: &str,
+        id: FontId,
+        size: f32,
+        weight: u16,
+        width: f32,
+    ) -> Vec<usize> {
+        let family = self.entries[id.0].family.clone();
+        let mut buffer = Buffer::new(&mut self.font_system, Metrics::new(size, size * 1.4));
+        buffer.set_wrap(&mut self.font_system, Wrap::WordOrGlyph);
+        buffer.set_size(&mut self.font_system, Some(width.max(1.0)), None);
+        buffer.set_text(
+            &mut self.font_system,
+            text,
+            &Attrs::new()
+                .family(Family::Name(family.as_str()))
+                .weight(Weight(weight)),
+            Shaping::Advanced,
+        );
+        buffer.shape_until_scroll(&mut self.font_system, false);
+        buffer
+            .layout_runs()
+            .skip(1)
+            .filter_map(|run| run.glyphs.iter().map(|g| g.start).min())
+            .collect()
+    }
+
+    // left, right and height of what a drawn run actually covers, relative to its origin.
+    // laid out like the renderers lay it out, a run's width is often just a large bound
+    pub(crate) fn extent(&mut self, run: &TextRun) -> [f32; 3] {
+        let key = (
+            run.family.clone(),
+            run.text.clone(),
+            (run.size * 10.0) as u32,
+            run.weight,
+            run.italic,
+            run.align as u8,
+            run.width.to_bits(),
+            shape_spans(&run.spans),
+        );
+        if let Some(&cached) = self.extent_cache.get(&key) {
+            return cached;
+        }
+        let line_height = run.size * 1.4;
+        let mut buffer = Buffer::new(&mut self.font_system, Metrics::new(run.size, line_height));
+        // f32::MAX means to the edge of the screen, which isn't known here
+        let width = (run.width != f32::MAX).then_some(run.width);
+        buffer.set_size(&mut self.font_system, width, None);
+        let attrs = Attrs::new()
+            .family(Family::Name(run.family.as_str()))
+            .weight(Weight(run.weight))
+            .style(if run.italic {
+                Style::Italic
+            } else {
+                Style::Normal
+            });
+        buffer.set_rich_text(
+            &mut self.font_system,
+            rich_spans(&run.text, &run.spans, &attrs),
+            &attrs,
+            Shaping::Advanced,
+            None,
+        );
+        let align = match run.align {
+            TextAlign::Left => CosmicAlign::Left,
+            TextAlign::Center => CosmicAlign::Center,
+            TextAlign::Right => CosmicAlign::Right,
+        };
+        for line in buffer.lines.iter_mut() {
+            line.set_align(Some(align));
+        }
+        buffer.shape_until_scroll(&mut self.font_system, false);
+        let (mut left, mut right, mut height) = (f32::MAX, f32::MIN, 0.0);
+        for layout_run in buffer.layout_runs() {
+            for glyph in layout_run.glyphs {
+                left = left.min(glyph.x);
+                right = right.max(glyph.x + glyph.w);
+            }
+            height += line_height;
+        }
+        if left > right {
+            (left, right) = (0.0, 0.0);
+        }
+        // centered or right aligned against the screen edge, could be anywhere along it
+        if width.is_none() && run.align != TextAlign::Left {
+            right = f32::MAX;
+        }
+        let extent = [left, right, height];
+        insert_bounded(&mut self.extent_cache, key, extent);
+        extent
+    }
+}
+
+fn shape_spans(spans: &[(Range<usize>, SpanStyle)]) -> ShapeSpans {
+    spans
+        .iter()
+        .filter(|(_, s)| s.weight.is_some() || s.italic)
+        .map(|(r, s)| (r.clone(), s.weight, s.italic))
+        .collect()
+}
+
+fn insert_bounded<K: Hash + Eq, V>(cache: &mut HashMap<K, V>, key: K, value: V) {
+    if cache.len() >= CACHE_LIMIT {
+        cache.clear();
+    }
+    cache.insert(key, value);
 }
@@ -0,0 +1,369 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::app::Shell;
+use crate::draw::Cursor;
+use crate::events::{Event, Key, Modifiers, MouseButton};
+use crate::{App, Color, DisplayList, Image, MemoryClipboard, Settings, SoftwareRenderer};
+
+// headless runner for tests
+// drives an App through the same shell the window runner uses, but with synthetic
+// events, painting into a display list instead of the gpu, so no window, surface or gpu is needed.
+// every injected event is followed by a frame, same as a redraw would be in the runner
+
+pub struct TestHarness<A: App> {
+    shell: Shell<A>,
+    runtime: tokio::runtime::Runtime,
+    list: DisplayList,
+    software: Option<SoftwareRenderer>,
+    clear_color: Color,
+    width: f32,
+    height: f32,
+    scale_factor: f32,
+    cursor: Cursor,
+    // virtual clock for double/triple click detection, only moves when advance() is called
+    time: f64,
+}
+
+impl<A: App> TestHarness<A> {
+    pub fn new() -> Self {
+        Self::with_app(A::new())
+    }
+
+    pub fn with_app(app: A) -> Self {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut shell = Shell::new(app, Arc::new(|| {}), Box::new(MemoryClipboard::new()));
+        shell.recorded = Some(Vec::new());
+
+        let mut harness = Self {
+            shell,
+            runtime,
+            list: DisplayList::new(),
+            software: None,
+            clear_color: Settings::default().clear_color,
+            width: 800.0,
+            height: 600.0,
+            scale_factor: 1.0,
+            cursor: Cursor::Default,
+            time: 0.0,
+        };
+        {
+            let _guard = harness.runtime.enter();
+            harness.shell.start();
+        }
+        harness.frame();
+        harness
+    }
+
+    // logical window size, defaults to 800x600
+    pub fn size(mut self, width: f32, height: f32) -> Self {
+        self.resize(width, height);
+        self
+    }
+
+    pub fn scale_factor(mut self, scale_factor: f32) -> Self {
+        self.scale_factor = scale_factor;
+        self.frame();
+        self
+    }
+
+    // background render() clears to, same role as Settings::clear_color
+    pub fn clear_color(mut self, color: Color) -> Self {
+        self.clear_color = color;
+        self
+    }
+
+    // --- inspection ---
+
+    pub fn app(&self) -> &A {
+        &self.shell.app
+    }
+
+    pub fn app_mut(&mut self) -> &mut A {
+        &mut self.shell.app
+    }
+
+    // every action that reached App::update so far, oldest first
+    pub fn actions(&self) -> &[A::Action] {
+        self.shell.recorded.as_deref().unwrap_or(&[])
+    }
+
+    pub fn take_actions(&mut self) -> Vec<A::Action> {
+        self.shell
+            .recorded
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    // cursor requested by the widget under the mouse in the last frame
+    pub fn cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    // everything the last frame painted, in paint order
+    pub fn display_list(&self) -> &DisplayList {
+        &self.list
+    }
+
+    // rasterize the last frame on the cpu, at the harness size and scale factor
+    pub fn render(&mut self) -> &SoftwareRenderer {
+        let (width, height, scale) = (self.width, self.height, self.scale_factor);
+        let renderer = self
+            .software
+            .get_or_insert_with(|| SoftwareRenderer::new(width, height, scale));
+        if renderer.width() != (width * scale).round() as u32
+            || renderer.height() != (height * scale).round() as u32
+            || renderer.scale_factor() != scale
+        {
+            renderer.resize(width, height, scale);
+        }
+        renderer.clear(self.clear_color);
+        renderer.render(&self.list, &mut self.shell.fonts);
+        renderer
+    }
+
+    // the last frame as an image, see render()
+    pub fn screenshot(&mut self) -> Image {
+        self.render().to_image()
+    }
+
+    // id of the widget that currently has keyboard focus, widgets without an explicit
+    // id show up under their path, see key.rs
+    pub fn focused(&self) -> Option<&str> {
+        self.shell.focused_id()
+    }
+
+    // what's on the harness's own clipboard, it starts out empty
+    pub fn clipboard(&mut self) -> Option<String> {
+        self.shell.clipboard.read()
+    }
+
+    // the rect the runner would give the window as the IME cursor area,
+    // None when no text widget has focus and IME would be off
+    pub fn ime_area(&self) -> Option<[f32; 4]> {
+        self.shell.ime_area()
+    }
+
+    // --- driving ---
+
+    // as if something outside the app had copied text
+    pub fn set_clipboard(&mut self, text: &str) {
+        self.shell.clipboard.write(text);
+    }
+
+    // view -> layout -> draw, exactly what a redraw does in the runner
+    pub fn frame(&mut self) {
+        let _guard = self.runtime.enter();
+        self.shell.drain_channel();
+        self.list.clear();
+        let (cursor, _) =
+            self.shell
+                .frame(self.width, self.height, self.scale_factor, &mut self.list);
+        self.cursor = cursor.unwrap_or(Cursor::Default);
+    }
+
+    // feed any event through the same path the runner would take for it
+    pub fn event(&mut self, event: Event) {
+        {
+            let _guard = self.runtime.enter();
+            match event {
+                Event::MouseMoved { x, y, .. } => self.shell.mouse_moved(x, y),
+                Event::MousePressed { button, x, y } => {
+                    self.move_to(x, y);
+                    self.shell.mouse_input(button, true, self.time);
+                }
+                Event::MouseReleased { button, x, y } => {
+                    self.move_to(x, y);
+                    self.shell.mouse_input(button, false, self.time);
+                }
+                Event::KeyPressed { key, modifiers } => {
+                    self.shell.modifiers = modifiers;
+                    self.shell.key_input(key, true, "");
+                }
+                Event::KeyReleased { key, modifiers } => {
+                    self.shell.modifiers = modifiers;
+                    self.shell.key_input(key, false, "");
+                }
+                Event::Resized { width, height } => {
+                    self.width = width;
+                    self.height = height;
+                    self.shell.dispatch_event(event);
+                }
+                Event::MouseScrolled { x, y } => self.shell.mouse_scrolled(event, x, y),
+                Event::Focused => self.shell.window_focused(true),
+                Event::Unfocused => self.shell.window_focused(false),
+                other => {
+                    self.shell.dispatch_event(other);
+                }
+            }
+        }
+        self.frame();
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.event(Event::Resized { width, height });
+    }
+
+    pub fn mouse_move(&mut self, x: f32, y: f32) {
+        self.event(Event::MouseMoved {
+            x,
+            y,
+            dx: x - self.shell.mouse.x,
+            dy: y - self.shell.mouse.y,
+        });
+    }
+
+    pub fn mouse_press(&mut self, button: MouseButton) {
+        let (x, y) = (self.shell.mouse.x, self.shell.mouse.y);
+        self.event(Event::MousePressed { button, x, y });
+    }
+
+    pub fn mouse_release(&mut self, button: MouseButton) {
+        let (x, y) = (self.shell.mouse.x, self.shell.mouse.y);
+        self.event(Event::MouseReleased { button, x, y });
+    }
+
+    // move, press and release the left button at a point
+    pub fn click(&mut self, x: f32, y: f32) {
+        self.mouse_move(x, y);
+        self.mouse_press(MouseButton::Left);
+        self.mouse_release(MouseButton::Left);
+    }
+
+    // wheel movement in logical pixels at the current mouse position,
+    // positive y scrolls up like a wheel turned away from you
+    pub fn scroll(&mut self, x: f32, y: f32) {
+        self.event(Event::MouseScrolled { x, y });
+    }
+
+    // press and release a key with no modifiers held
+    pub fn key(&mut self, key: Key) {
+        self.key_with(key, Modifiers::default());
+    }
+
+    // press and release a key while holding the given modifiers
+    pub fn key_with(&mut self, key: Key, modifiers: Modifiers) {
+        self.event(Event::KeyPressed { key, modifiers });
+        self.event(Event::KeyReleased { key, modifiers });
+    }
+
+    // one key press per char, each carrying the char as its text like winit does
+    pub fn type_text(&mut self, text: &str) {
+        let mut buf = [0u8; 4];
+        for c in text.chars() {
+            let key = char_to_key(c);
+            {
+                let _guard = self.runtime.enter();
+                self.shell.modifiers = Modifiers::default();
+                self.shell.key_input(key, true, c.encode_utf8(&mut buf));
+            }
+            self.frame();
+            {
+                let _guard = self.runtime.enter();
+                self.shell.key_input(key, false, "");
+            }
+            self.frame();
+        }
+    }
+
+    // an IME composing text, the way the runner passes on WindowEvent::Ime. cursor is
+    // the IME's caret in bytes into the preedit, an empty preedit ends the composition
+    pub fn ime_preedit(&mut self, text: &str, cursor: Option<usize>) {
+        self.shell.ime_preedit(text, cursor);
+        self.frame();
+    }
+
+    pub fn ime_commit(&mut self, text: &str) {
+        {
+            let _guard = self.runtime.enter();
+            self.shell.ime_commit(text);
+        }
+        self.frame();
+    }
+
+    // move the click clock forward, so the next click isn't counted as a double click
+    pub fn advance(&mut self, duration: Duration) {
+        self.time += duration.as_secs_f64();
+    }
+
+    // block until the running tasks have finished, and the ones their actions started, and
+    // deliver what they produced. Task::every never finishes and isn't waited on. gives
+    // up after timeout, for a task that hangs
+    pub fn wait_for_tasks(&mut self, timeout: Duration) {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let shell = &mut self.shell;
+            let finish = async { tokio::time::timeout_at(deadline, shell.finish_tasks()).await };
+            let finished = self.runtime.block_on(finish).is_ok();
+            self.frame();
+            if !finished || !self.shell.tasks_started() {
+                break;
+            }
+        }
+    }
+
+    fn move_to(&mut self, x: f32, y: f32) {
+        if x != self.shell.mouse.x || y != self.shell.mouse.y {
+            self.shell.mouse_moved(x, y);
+        }
+    }
+}
+
+impl<A: App> Default for TestHarness<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// best effort physical key for a typed char, widgets only look at the text
+fn char_to_key(c: char) -> Key {
+    match c.to_ascii_lowercase() {
+        'a' => Key::A,
+        'b' => Key::B,
+        'c' => Key::C,
+        'd' => Key::D,
+        'e' => Key::E,
+        'f' => Key::F,
+        'g' => Key::G,
+        'h' => Key::H,
+        'i' => Key::I,
+        'j' => Key::J,
+        'k' => Key::K,
+        'l' => Key::L,
+        'm' => Key::M,
+        'n' => Key::N,
+        'o' => Key::O,
+        'p' => Key::P,
+        'q' => Key::Q,
+        'r' => Key::R,
+        's' => Key::S,
+        't' => Key::T,
+        'u' => Key::U,
+        'v' => Key::V,
+        'w' => Key::W,
+        'x' => Key::X,
+        'y' => Key::Y,
+        'z' => Key::Z,
+        '0' => Key::Num0,
+        '1' => Key::Num1,
+        '2' => Key::Num2,
+        '3' => Key::Num3,
+        '4' => Key::Num4,
+        '5' => Key::Num5,
+        '6' => Key::Num6,
+        '7' => Key::Num7,
+        '8' => Key::Num8,
+        '9' => Key::Num9,
+        ' ' => Key::Space,
+        '\n' | '\r' => Key::Enter,
+        '\t' => Key::Tab,
+        ',' => Key::Comma,
+        '.' => Key::Period,
+        '\'' => Key::Quote,
+        '/' => Key::Slash,
+        '-' => Key::Minus,
+        '=' => Key::Equal,
+        _ => Key::Unknown,
+    }
+}
@@ -0,0 +1,168 @@
+use crate::draw::{make_child_clip, nth_overlay};
+use crate::layout::element_rect;
+use crate::widgets::overlay::Overlay;
+use crate::{Element, Interactions};
+
+// hit testing — runs on the laid out tree before each draw.
+// the topmost element under the pointer is the last one painted there, with overflow
+// clips respected and overlays on top in z order. that element and its ancestors
+// are hovered; mouse events start at it and bubble up until an element that stops
+// propagation. bubbling ends at an overlay's root, overlays don't leak into the
+// element they're declared in
+
+pub(crate) fn hit_test<M: Clone + 'static>(element: &mut Element<M>, mouse_x: f32, mouse_y: f32) {
+    reset(element);
+    let overlays = overlay_order(element);
+
+    // overlays are on top, so they get first pick, highest first
+    let mut found = None;
+    for &i in overlays.iter().rev() {
+        let Some(child) = overlay_child(element, i) else {
+            continue;
+        };
+        if let Some(path) = find(child, mouse_x, mouse_y, None) {
+            found = Some((Some(i), path));
+            break;
+        }
+    }
+    if found.is_none() {
+        found = find(element, mouse_x, mouse_y, None).map(|path| (None, path));
+    }
+    let Some((overlay, path)) = found else {
+        return;
+    };
+
+    let root = match overlay {
+        Some(i) => overlay_child(element, i).unwrap(),
+        None => element,
+    };
+
+    // the deepest element that stops propagation is as far up as the event goes
+    let mut stop = 0;
+    let mut el = &*root;
+    for depth in 0..=path.len() {
+        if interactions(el).is_some_and(|i| i.stop_propagation) {
+            stop = depth;
+        }
+        if depth < path.len() {
+            el = &children(el)[path[depth]];
+        }
+    }
+
+    let mut el = root;
+    for depth in 0..=path.len() {
+        if let Some(i) = interactions_mut(el) {
+            i.hovered = true;
+            i.targeted = depth >= stop;
+        }
+        if depth < path.len() {
+            el = &mut children_mut(el)[path[depth]];
+        }
+    }
+}
+
+// child indices from `el` down to the topmost element under the point
+fn find<M: Clone + 'static>(
+    el: &Element<M>,
+    x: f32,
+    y: f32,
+    clip: Option<[f32; 4]>,
+) -> Option<Vec<usize>> {
+    let (_, [ex, ey, ew, eh]) = element_rect(el)?;
+    if let Some([cx, cy, cx2, cy2]) = clip
+        && (x < cx || y < cy || x > cx2 || y > cy2)
+    {
+        return None;
+    }
+
+    let on_bar = match el {
+        Element::Row(r) => r.scroll.on_bar([ex, ey, ew, eh], x, y),
+        Element::Column(c) => c.scroll.on_bar([ex, ey, ew, eh], x, y),
+        _ => false,
+    };
+    if on_bar {
+        return Some(vec![]);
+    }
+
+    let child_clip = match el {
+        Element::Row(r) => make_child_clip(ex, ey, ew, eh, r.layout.overflow, clip),
+        Element::Column(c) => make_child_clip(ex, ey, ew, eh, c.layout.overflow, clip),
+        _ => clip,
+    };
+    // later children paint over earlier ones
+    for (i, child) in children(el).iter().enumerate().rev() {
+        if let Some(mut path) = find(child, x, y, child_clip) {
+            path.insert(0, i);
+            return Some(path);
+        }
+    }
+
+    if x >= ex && x <= ex + ew && y >= ey && y <= ey + eh {
+        Some(vec![])
+    } else {
+        None
+    }
+}
+
+fn reset<M: Clone + 'static>(el: &mut Element<M>) {
+    if let Some(i) = interactions_mut(el) {
+        i.hovered = false;
+        i.targeted = false;
+    }
+    match el {
+        Element::Overlay(o) => reset(&mut o.child),
+        _ => children_mut(el).iter_mut().for_each(reset),
+    }
+}
+
+// overlays in paint order: by z, tree order among equal z. indices count overlays
+// in tree order, nested ones right after their parent, see nth_overlay
+pub(crate) fn overlay_order<M: Clone + 'static>(element: &Element<M>) -> Vec<usize> {
+    fn collect<'a, M: Clone + 'static>(el: &'a Element<M>, out: &mut Vec<&'a Overlay<M>>) {
+        match el {
+            Element::Overlay(o) => {
+                out.push(o);
+                collect(&o.child, out);
+            }
+            _ => children(el).iter().for_each(|c| collect(c, out)),
+        }
+    }
+    let mut overlays = Vec::new();
+    collect(element, &mut overlays);
+    let mut order: Vec<usize> = (0..overlays.len()).collect();
+    order.sort_by_key(|&i| overlays[i].z_index);
+    order
+}
+
+// the child of the overlay at index n, skipping ones whose anchor wasn't found
+fn overlay_child<M: Clone + 'static>(el: &mut Element<M>, n: usize) -> Option<&mut Element<M>> {
+    let mut n = n;
+    nth_overlay(el, &mut n)
+        .filter(|o| o.placed)
+        .map(|o| &mut *o.child)
+}
+
+// overlays are left out, they're tested on their own
+fn children<M: Clone + 'static>(el: &Element<M>) -> &[Element<M>] {
+    match el {
+        Element::Row(r) => &r.children,
+        Element::Column(c) => &c.children,
+        _ => &[],
+    }
+}
+
+fn children_mut<M: Clone + 'static>(el: &mut Element<M>) -> &mut [Element<M>] {
+    match el {
+        Element::Row(r) => &mut r.children,
+        Element::Column(c) => &mut c.children,
+        _ => &mut [],
+    }
+}
+
+fn interactions<M: Clone + 'static>(el: &Element<M>) -> Option<&Interactions<M>> {
+    el.widget()?.interactions()
+}
+
+fn interactions_mut<M: Clone + 'static>(el: &mut Element<M>) -> Option<&mut Interactions<M>> {
+    el.widget_mut()?.interactions_mut()
+}
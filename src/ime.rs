@@ -0,0 +1,66 @@
+use crate::state::StateStore;
+
+// Ime
+// input method composition, for CJK input and the like. while an IME composes, the
+// text so far is a preedit: shown underlined at the cursor of the focused text widget
+// but not part of its value until the IME commits it. the focused widget also leaves
+// its caret rect here each frame, so the window can put the IME's candidate box next to it
+
+#[derive(Default)]
+pub(crate) struct ImeState {
+    preedit: String,
+    // where the IME wants its caret inside the preedit, in bytes. None hides it
+    cursor: Option<usize>,
+    caret: Option<[f32; 4]>,
+}
+
+// one composition for the whole window, like focus
+const KEY: &str = "";
+
+pub(crate) fn preedit(state: &StateStore) -> Option<(&str, Option<usize>)> {
+    let s = state.get::<ImeState>(KEY)?;
+    (!s.preedit.is_empty()).then_some((s.preedit.as_str(), s.cursor))
+}
+
+pub(crate) fn set_preedit(state: &mut StateStore, text: &str, cursor: Option<usize>) {
+    let s = state.get_or_default_mut::<ImeState>(KEY);
+    s.preedit = text.to_string();
+    s.cursor = cursor.map(|c| c.min(text.len()));
+}
+
+pub(crate) fn clear(state: &mut StateStore) {
+    set_preedit(state, "", None);
+}
+
+// cleared before each draw, set again by the focused text widget if it's drawn
+pub(crate) fn reset_caret(state: &mut StateStore) {
+    state.get_or_default_mut::<ImeState>(KEY).caret = None;
+}
+
+pub(crate) fn set_caret(state: &mut StateStore, rect: [f32; 4]) {
+    state.get_or_default_mut::<ImeState>(KEY).caret = Some(rect);
+}
+
+pub(crate) fn caret(state: &StateStore) -> Option<[f32; 4]> {
+    state.get::<ImeState>(KEY)?.caret
+}
+
+// a text widget's value with the preedit spliced in at its cursor
+pub(crate) struct Composed {
+    pub(crate) text: String,
+    // where the preedit sits in text
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    // the IME's caret in text, None if it asked for it hidden
+    pub(crate) caret: Option<usize>,
+}
+
+pub(crate) fn compose(state: &StateStore, value: &str, cursor: usize) -> Option<Composed> {
+    let (preedit, caret) = preedit(state)?;
+    Some(Composed {
+        text: format!("{}{}{}", &value[..cursor], preedit, &value[cursor..]),
+        start: cursor,
+        end: cursor + preedit.len(),
+        caret: caret.map(|c| cursor + c),
+    })
+}
@@ -0,0 +1,66 @@
+use crate::Element;
+use crate::state::StateStore;
+
+// Key
+// stateful widgets keep their state in the store under their id, so before layout every
+// built-in widget without one is given its path through the tree: "#" for the root,
+// then its position under each parent, "#/2/0". .key() stands in for the position so
+// an item keeps its state when its list is reordered, and an explicit .id() replaces
+// the whole path, its children's paths start from it too. ids have to be unique across
+// the window, keys only among their siblings. components are built on the way, with
+// their state under the path they sit at. custom widgets keep the id they report, one
+// that reports none is handed its path through Widget::set_id
+
+pub(crate) fn assign<M: Clone + 'static>(element: &mut Element<M>, state: &mut StateStore) {
+    visit(element, "#".to_string(), state);
+}
+
+fn visit<M: Clone + 'static>(el: &mut Element<M>, path: String, state: &mut StateStore) {
+    if let Element::Component(_) = el {
+        let Element::Component(node) = std::mem::replace(el, Element::Empty) else {
+            unreachable!()
+        };
+        // the view takes the component's place, and its path
+        let path = node.id.unwrap_or(path);
+        *el = (node.build)(state, &path);
+        return visit(el, path, state);
+    }
+    if let Element::Custom(w) = el {
+        if w.id().is_none() {
+            w.set_id(path);
+        }
+        return;
+    }
+    let path = match ids(el) {
+        Some((id, _)) => id.get_or_insert(path).clone(),
+        None => path,
+    };
+    let children = match el {
+        Element::Row(r) => &mut r.children,
+        Element::Column(c) => &mut c.children,
+        Element::Overlay(o) => return visit(&mut o.child, format!("{path}/0"), state),
+        _ => return,
+    };
+    for (i, child) in children.iter_mut().enumerate() {
+        let segment = match ids(child).and_then(|(_, key)| key) {
+            Some(key) => format!("[{key}]"),
+            None => i.to_string(),
+        };
+        visit(child, format!("{path}/{segment}"), state);
+    }
+}
+
+// the id to fill in and the key, for the elements that have them
+fn ids<M: Clone + 'static>(el: &mut Element<M>) -> Option<(&mut Option<String>, Option<&str>)> {
+    Some(match el {
+        Element::Rect(w) => (&mut w.id, w.key.as_deref()),
+        Element::Text(w) => (&mut w.id, w.key.as_deref()),
+        Element::Button(w) => (&mut w.id, w.key.as_deref()),
+        Element::TextInput(w) => (&mut w.id, w.key.as_deref()),
+        Element::TextEditor(w) => (&mut w.id, w.key.as_deref()),
+        Element::Row(w) => (&mut w.id, w.key.as_deref()),
+        Element::Column(w) => (&mut w.id, w.key.as_deref()),
+        Element::Component(c) => (&mut c.id, c.key.as_deref()),
+        _ => return None,
+    })
+}
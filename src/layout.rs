@@ -1,13 +1,23 @@
+use std::collections::HashMap;
+
 use taffy::prelude::*;
 
-use crate::{Align, Element, Fonts, Overflow, Position, Val};
+use crate::key;
+use crate::state::StateStore;
+use crate::widgets::overlay::Anchor;
+use crate::widgets::scroll::{self, Scroll};
+use crate::{Align, Element, Fonts, Overflow, Position, Val, Widget};
 
 pub fn do_layout<M: Clone + 'static>(
     element: &mut Element<M>,
     width: f32,
     height: f32,
     fonts: &mut Fonts,
+    state: &mut StateStore,
 ) {
+    // components are built and ids filled in before anything needs them
+    key::assign(element, state);
+
     let mut taffy: TaffyTree<()> = TaffyTree::new();
     let root = build_taffy_node(&mut taffy, element, fonts);
     taffy
@@ -19,7 +29,92 @@ pub fn do_layout<M: Clone + 'static>(
             },
         )
         .unwrap();
-    apply_layout(&taffy, element, root, 0.0, 0.0);
+    apply_layout(&taffy, element, root, 0.0, 0.0, state);
+
+    // overlays go last so anything in the tree can be their anchor
+    let mut rects = HashMap::new();
+    collect_rects(element, &mut rects);
+    layout_overlays(element, width, height, fonts, state, &mut rects);
+}
+
+// each overlay's child gets its own taffy tree with the whole window as available
+// space, then is moved next to its anchor. overlays inside overlays are handled
+// after their parent, so they can anchor to anything the parent contains
+fn layout_overlays<M: Clone + 'static>(
+    element: &mut Element<M>,
+    width: f32,
+    height: f32,
+    fonts: &mut Fonts,
+    state: &mut StateStore,
+    rects: &mut HashMap<String, [f32; 4]>,
+) {
+    match element {
+        Element::Overlay(o) => {
+            let anchor = match &o.anchor {
+                Anchor::Window => None,
+                Anchor::Element(id) => match rects.get(id) {
+                    Some(r) => Some(*r),
+                    None => {
+                        o.placed = false;
+                        return;
+                    }
+                },
+            };
+            let mut taffy: TaffyTree<()> = TaffyTree::new();
+            let root = build_taffy_node(&mut taffy, &o.child, fonts);
+            taffy
+                .compute_layout(
+                    root,
+                    taffy::geometry::Size {
+                        width: AvailableSpace::Definite(width),
+                        height: AvailableSpace::Definite(height),
+                    },
+                )
+                .unwrap();
+            let size = taffy.layout(root).unwrap().size;
+            let (x, y) = o.place(anchor, size.width, size.height, width, height);
+            apply_layout(&taffy, &mut o.child, root, x, y, state);
+            o.x = x;
+            o.y = y;
+            o.w = size.width;
+            o.h = size.height;
+            o.placed = true;
+
+            collect_rects(&o.child, rects);
+            layout_overlays(&mut o.child, width, height, fonts, state, rects);
+        }
+        Element::Row(r) => {
+            for child in &mut r.children {
+                layout_overlays(child, width, height, fonts, state, rects);
+            }
+        }
+        Element::Column(c) => {
+            for child in &mut c.children {
+                layout_overlays(child, width, height, fonts, state, rects);
+            }
+        }
+        _ => {}
+    }
+}
+
+// id -> [x, y, w, h] of every laid out element that has an id
+fn collect_rects<M: Clone + 'static>(element: &Element<M>, rects: &mut HashMap<String, [f32; 4]>) {
+    if let Some((Some(id), rect)) = element_rect(element) {
+        rects.insert(id.to_string(), rect);
+    }
+    match element {
+        Element::Row(r) => r.children.iter().for_each(|c| collect_rects(c, rects)),
+        Element::Column(c) => c.children.iter().for_each(|c| collect_rects(c, rects)),
+        _ => {}
+    }
+}
+
+// id and resolved [x, y, w, h] of an element, None for ones that take no space
+pub(crate) fn element_rect<M: Clone + 'static>(
+    element: &Element<M>,
+) -> Option<(Option<&str>, [f32; 4])> {
+    let w = element.widget()?;
+    Some((w.id(), w.bounds()))
 }
 
 pub fn build_taffy_node_pub<M: Clone + 'static>(
@@ -37,13 +132,8 @@ fn build_taffy_node<M: Clone + 'static>(
 ) -> NodeId {
     match element {
         Element::Empty => taffy.new_leaf(taffy::Style::default()).unwrap(),
-        Element::Rect(r) => r.layout_node(taffy, fonts),
-        Element::Text(t) => t.layout_node(taffy, fonts),
-        Element::Button(b) => b.layout_node(taffy, fonts),
-        Element::TextInput(t) => t.layout_node(taffy, fonts),
-        Element::TextEditor(t) => t.layout_node(taffy, fonts),
-        Element::Row(r) => r.layout_node(taffy, fonts),
-        Element::Column(c) => c.layout_node(taffy, fonts),
+        Element::Overlay(o) => o.layout_node(taffy),
+        el => el.widget().unwrap().layout_node(taffy, fonts),
     }
 }
 
@@ -53,6 +143,7 @@ fn apply_layout<M: Clone + 'static>(
     node: NodeId,
     parent_x: f32,
     parent_y: f32,
+    state: &mut StateStore,
 ) {
     let layout = taffy.layout(node).unwrap();
     let x = parent_x + layout.location.x;
@@ -61,27 +152,56 @@ fn apply_layout<M: Clone + 'static>(
     let h = layout.size.height;
 
     match element {
-        Element::Empty => {}
-        Element::Rect(r) => r.apply_layout(x, y, w, h),
-        Element::Text(t) => t.apply_layout(x, y, w, h),
-        Element::Button(b) => b.apply_layout(x, y, w, h),
-        Element::TextInput(t) => t.apply_layout(x, y, w, h),
-        Element::TextEditor(t) => t.apply_layout(x, y, w, h),
+        // placed separately by layout_overlays
+        Element::Empty | Element::Overlay(_) => {}
         Element::Row(r) => {
             r.apply_layout(x, y, w, h);
+            r.scroll = resolve_scroll(r.id.as_deref(), r.layout.overflow, layout, state);
+            let [sx, sy] = r.scroll.offset;
             let child_nodes = taffy.children(node).unwrap();
             for (child, child_node) in r.children.iter_mut().zip(child_nodes.iter()) {
-                apply_layout(taffy, child, *child_node, x, y);
+                apply_layout(taffy, child, *child_node, x - sx, y - sy, state);
             }
         }
         Element::Column(c) => {
             c.apply_layout(x, y, w, h);
+            c.scroll = resolve_scroll(c.id.as_deref(), c.layout.overflow, layout, state);
+            let [sx, sy] = c.scroll.offset;
             let child_nodes = taffy.children(node).unwrap();
             for (child, child_node) in c.children.iter_mut().zip(child_nodes.iter()) {
-                apply_layout(taffy, child, *child_node, x, y);
+                apply_layout(taffy, child, *child_node, x - sx, y - sy, state);
             }
         }
+        el => el.widget_mut().unwrap().apply_layout(x, y, w, h),
+    }
+}
+
+// scroll containers keep their offset in the state store, the rest never scroll
+fn resolve_scroll(
+    id: Option<&str>,
+    overflow: Overflow,
+    layout: &taffy::Layout,
+    state: &mut StateStore,
+) -> Scroll {
+    if overflow != Overflow::Scroll {
+        return Scroll::default();
     }
+    let id = id.expect("a Row or Column is given an id before layout, see key.rs");
+    // taffy leaves the end padding out of the content size, keep it scrollable into view
+    let (sw, sh) = (layout.scroll_width(), layout.scroll_height());
+    let max = [
+        if sw > 0.0 {
+            sw + layout.padding.right
+        } else {
+            0.0
+        },
+        if sh > 0.0 {
+            sh + layout.padding.bottom
+        } else {
+            0.0
+        },
+    ];
+    scroll::resolve(state, id, max)
 }
 
 // shared layout helpers used by widget structs
@@ -1,35 +1,52 @@
-#![allow(dead_code)]
-#![allow(unused_variables)]
-#![allow(unused_imports)]
-#![allow(unused_mut)]
-
 mod render {
+    pub mod display_list;
     pub mod gpu;
+    pub mod image;
     pub mod shadow_renderer;
     pub mod shape_renderer;
+    pub mod software;
     pub mod text_renderer;
 }
 
+pub use render::display_list::{DisplayList, Layer, Primitive, SpanStyle, TextRun};
 pub use render::gpu::{FrameFinisher, GpuContext, RenderFrame};
+pub use render::image::{Image, render_to_image};
 pub use render::shadow_renderer::ShadowRenderer;
 pub use render::shape_renderer::ShapeRenderer;
+pub use render::software::SoftwareRenderer;
 pub use render::text_renderer::TextRenderer;
 
 mod app;
+mod clipboard;
 mod color;
+mod component;
 mod draw;
 mod element;
 mod events;
+mod focus;
 mod fonts;
+mod harness;
+mod hit;
+mod ime;
+mod key;
 mod layout;
 mod state;
 mod task;
+mod widget;
 pub(crate) mod widgets;
 
 pub use app::{App, Settings};
+pub use clipboard::{Clipboard, MemoryClipboard, SystemClipboard};
 pub use color::Color;
-pub use draw::Cursor;
+pub use component::{Component, ComponentNode, component};
+pub use draw::{Cursor, DrawCtx, MouseState};
 pub use element::*;
-pub use events::{Event, Key, MouseButton, key_code_to_key};
+pub use events::{Event, Key, Modifiers, MouseButton, key_code_to_key};
 pub use fonts::{FontBuilder, FontId, Fonts};
+pub use harness::TestHarness;
+pub use state::StateStore;
 pub use task::Task;
+pub use widget::{EventCtx, Widget};
+
+// custom widgets build their layout nodes with it
+pub use taffy;
@@ -0,0 +1,485 @@
+use std::ops::Range;
+
+use crate::{Color, Fonts, ShadowRenderer, ShapeRenderer, TextAlign, TextRenderer};
+
+// what a frame paints, in paint order
+// widgets push into this during draw, a backend replays it afterwards.
+// coordinates are logical pixels, same as layout
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Primitive {
+    Rect {
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        color: [f32; 4],
+        border_color: [f32; 4],
+        border_thickness: f32,
+    },
+    RoundedRect {
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        radius: f32,
+        color: [f32; 4],
+        border_color: [f32; 4],
+        border_thickness: f32,
+    },
+    Shadow {
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        color: [f32; 4],
+        radius: f32,
+        blur: f32,
+        offset_x: f32,
+        offset_y: f32,
+    },
+    Text(TextRun),
+    // clips everything up to the matching PopClip, nested clips intersect
+    PushClip([f32; 4]),
+    PopClip,
+}
+
+// drawable primitives of one layer, each with the clip it is drawn with
+pub type Layer<'a> = Vec<(&'a Primitive, Option<[f32; 4]>)>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextRun {
+    pub text: String,
+    pub family: String,
+    pub size: f32,
+    pub weight: u16,
+    pub italic: bool,
+    pub align: TextAlign,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub color: Color,
+    // ranges of text styled differently from the rest, in order and not overlapping
+    pub spans: Vec<(Range<usize>, SpanStyle)>,
+}
+
+// how a span of a text run differs from the rest of it, anything unset stays the run's
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpanStyle {
+    pub color: Option<Color>,
+    pub weight: Option<u16>,
+    pub italic: bool,
+}
+
+impl SpanStyle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+    pub fn weight(mut self, weight: u16) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DisplayList {
+    items: Vec<Primitive>,
+}
+
+impl DisplayList {
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn items(&self) -> &[Primitive] {
+        &self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn push(&mut self, primitive: Primitive) {
+        self.items.push(primitive);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn rect(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        color: [f32; 4],
+        border_color: [f32; 4],
+        border_thickness: f32,
+    ) {
+        self.items.push(Primitive::Rect {
+            x,
+            y,
+            w,
+            h,
+            color,
+            border_color,
+            border_thickness,
+        });
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn rounded_rect(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        radius: f32,
+        color: [f32; 4],
+        border_color: [f32; 4],
+        border_thickness: f32,
+    ) {
+        self.items.push(Primitive::RoundedRect {
+            x,
+            y,
+            w,
+            h,
+            radius,
+            color,
+            border_color,
+            border_thickness,
+        });
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn shadow(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        color: [f32; 4],
+        radius: f32,
+        blur: f32,
+        offset_x: f32,
+        offset_y: f32,
+    ) {
+        self.items.push(Primitive::Shadow {
+            x,
+            y,
+            w,
+            h,
+            color,
+            radius,
+            blur,
+            offset_x,
+            offset_y,
+        });
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn text(
+        &mut self,
+        family: String,
+        size: f32,
+        weight: u16,
+        italic: bool,
+        align: TextAlign,
+        text: &str,
+        x: f32,
+        y: f32,
+        width: f32,
+        color: Color,
+    ) {
+        self.rich_text(
+            family,
+            size,
+            weight,
+            italic,
+            align,
+            text,
+            x,
+            y,
+            width,
+            color,
+            Vec::new(),
+        );
+    }
+
+    // text with spans styled apart from the rest, e.g. highlighted code
+    #[allow(clippy::too_many_arguments)]
+    pub fn rich_text(
+        &mut self,
+        family: String,
+        size: f32,
+        weight: u16,
+        italic: bool,
+        align: TextAlign,
+        text: &str,
+        x: f32,
+        y: f32,
+        width: f32,
+        color: Color,
+        spans: Vec<(Range<usize>, SpanStyle)>,
+    ) {
+        self.items.push(Primitive::Text(TextRun {
+            text: text.to_string(),
+            family,
+            size,
+            weight,
+            italic,
+            align,
+            x,
+            y,
+            width,
+            color,
+            spans,
+        }));
+    }
+
+    pub fn push_clip(&mut self, clip: [f32; 4]) {
+        self.items.push(Primitive::PushClip(clip));
+    }
+
+    pub fn pop_clip(&mut self) {
+        self.items.push(Primitive::PopClip);
+    }
+
+    // the drawable primitives split into layers that can each be painted as
+    // shadows -> shapes -> text without changing what the frame looks like.
+    // a new layer starts whenever a primitive would otherwise end up underneath
+    // something of a later pass that it overlaps, e.g. a panel drawn over earlier text.
+    // clip push/pop are resolved into the clip each primitive is drawn with, and
+    // primitives clipped away entirely are dropped. text is bounded by the glyphs it
+    // lays out, so fonts measures it
+    pub fn layers(&self, fonts: &mut Fonts) -> Vec<Layer<'_>> {
+        let mut layers = Vec::new();
+        let mut layer: Layer = Vec::new();
+        // bounds of what the current layer holds, per pass
+        let mut passes: [Vec<[f32; 4]>; 3] = Default::default();
+        let mut clips: Vec<[f32; 4]> = Vec::new();
+
+        for item in &self.items {
+            let clip = clips.last().copied();
+            let pass = match item {
+                Primitive::PushClip(c) => {
+                    clips.push(match clip {
+                        Some(p) => intersect(*c, p),
+                        None => *c,
+                    });
+                    continue;
+                }
+                Primitive::PopClip => {
+                    clips.pop();
+                    continue;
+                }
+                Primitive::Shadow { .. } => 0,
+                Primitive::Rect { .. } | Primitive::RoundedRect { .. } => 1,
+                Primitive::Text(_) => 2,
+            };
+            let Some(bounds) = bounds(item, clip, fonts) else {
+                continue;
+            };
+            let covered = passes[pass + 1..]
+                .iter()
+                .flatten()
+                .any(|b| overlaps(*b, bounds));
+            if covered {
+                layers.push(std::mem::take(&mut layer));
+                passes.iter_mut().for_each(Vec::clear);
+            }
+            layer.push((item, clip));
+            passes[pass].push(bounds);
+        }
+        if !layer.is_empty() {
+            layers.push(layer);
+        }
+        layers
+    }
+
+    // feed the list into the wgpu renderers, returning which instances of each
+    // renderer belong to which layer
+    pub(crate) fn replay(
+        &self,
+        sr: &mut ShapeRenderer,
+        shadow: &mut ShadowRenderer,
+        tr: &mut TextRenderer,
+        fonts: &mut Fonts,
+    ) -> Vec<GpuLayer> {
+        let mut out = Vec::new();
+        for layer in self.layers(fonts) {
+            let start = (shadow.instance_count(), sr.instance_count(), tr.run_count());
+            for (item, clip) in layer {
+                match item {
+                    Primitive::Rect {
+                        x,
+                        y,
+                        w,
+                        h,
+                        color,
+                        border_color,
+                        border_thickness,
+                    } => match clip {
+                        Some(clip) => sr.draw_rounded_rect_clipped(
+                            *x,
+                            *y,
+                            *w,
+                            *h,
+                            0.0,
+                            *color,
+                            *border_color,
+                            *border_thickness,
+                            clip,
+                        ),
+                        None => {
+                            sr.draw_rect(*x, *y, *w, *h, *color, *border_color, *border_thickness)
+                        }
+                    },
+                    Primitive::RoundedRect {
+                        x,
+                        y,
+                        w,
+                        h,
+                        radius,
+                        color,
+                        border_color,
+                        border_thickness,
+                    } => match clip {
+                        Some(clip) => sr.draw_rounded_rect_clipped(
+                            *x,
+                            *y,
+                            *w,
+                            *h,
+                            *radius,
+                            *color,
+                            *border_color,
+                            *border_thickness,
+                            clip,
+                        ),
+                        None => sr.draw_rounded_rect(
+                            *x,
+                            *y,
+                            *w,
+                            *h,
+                            *radius,
+                            *color,
+                            *border_color,
+                            *border_thickness,
+                        ),
+                    },
+                    // the shadow pipeline has no clip support, shadows always paint in full
+                    Primitive::Shadow {
+                        x,
+                        y,
+                        w,
+                        h,
+                        color,
+                        radius,
+                        blur,
+                        offset_x,
+                        offset_y,
+                    } => shadow
+                        .draw_shadow(*x, *y, *w, *h, *color, *radius, *blur, *offset_x, *offset_y),
+                    Primitive::Text(run) => tr.draw(
+                        &mut fonts.font_system,
+                        run.family.clone(),
+                        run.size,
+                        run.weight,
+                        run.italic,
+                        run.align,
+                        &run.text,
+                        run.x,
+                        run.y,
+                        run.width,
+                        clip,
+                        run.color,
+                        &run.spans,
+                    ),
+                    Primitive::PushClip(_) | Primitive::PopClip => {}
+                }
+            }
+            out.push(GpuLayer {
+                shadows: start.0..shadow.instance_count(),
+                shapes: start.1..sr.instance_count(),
+                texts: start.2..tr.run_count(),
+            });
+        }
+        out
+    }
+}
+
+// instance ranges of one layer in each wgpu renderer
+pub(crate) struct GpuLayer {
+    pub(crate) shadows: Range<u32>,
+    pub(crate) shapes: Range<u32>,
+    pub(crate) texts: Range<usize>,
+}
+
+// screen area a primitive can touch, None if the clip hides it completely
+fn bounds(item: &Primitive, clip: Option<[f32; 4]>, fonts: &mut Fonts) -> Option<[f32; 4]> {
+    let b = match item {
+        // shapes bleed a pixel of antialiasing past their rect
+        Primitive::Rect { x, y, w, h, .. } | Primitive::RoundedRect { x, y, w, h, .. } => {
+            [x - 1.0, y - 1.0, x + w + 1.0, y + h + 1.0]
+        }
+        // shadows ignore the clip, see replay
+        Primitive::Shadow {
+            x,
+            y,
+            w,
+            h,
+            blur,
+            offset_x,
+            offset_y,
+            ..
+        } => {
+            let e = blur * 2.0;
+            return Some([
+                x + offset_x - e,
+                y + offset_y - e,
+                x + offset_x + w + e,
+                y + offset_y + h + e,
+            ]);
+        }
+        Primitive::Text(run) => {
+            let [left, right, height] = fonts.extent(run);
+            [run.x + left, run.y, run.x + right, run.y + height]
+        }
+        Primitive::PushClip(_) | Primitive::PopClip => return None,
+    };
+    let b = match clip {
+        Some(c) => intersect(b, c),
+        None => b,
+    };
+    if b[2] <= b[0] || b[3] <= b[1] {
+        return None;
+    }
+    Some(b)
+}
+
+fn overlaps(a: [f32; 4], b: [f32; 4]) -> bool {
+    a[0] < b[2] && b[0] < a[2] && a[1] < b[3] && b[1] < a[3]
+}
+
+pub(crate) fn intersect(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    [
+        a[0].max(b[0]),
+        a[1].max(b[1]),
+        a[2].min(b[2]),
+        a[3].min(b[3]),
+    ]
+}
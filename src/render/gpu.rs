@@ -22,7 +22,7 @@ impl GpuContext {
                 force_fallback_adapter: false,
             })
             .await
-            .unwrap();
+            .expect("no gpu adapter, a window needs one (render_to_image doesn't)");
 
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor::default())
@@ -0,0 +1,155 @@
+use std::io;
+use std::path::Path;
+
+use crate::draw::{MouseState, draw};
+use crate::layout::do_layout;
+use crate::state::StateStore;
+use crate::{Color, DisplayList, Element, Fonts, SoftwareRenderer};
+
+// srgb rgba8 pixels, row major. what the offscreen render produces and what golden
+// screenshots are stored as
+
+#[derive(Clone, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl std::fmt::Debug for Image {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Image({}x{})", self.width, self.height)
+    }
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * self.width + x) * 4) as usize;
+        [
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        ]
+    }
+
+    pub fn encode_png(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&self.pixels).unwrap();
+        writer.finish().unwrap();
+        out
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.encode_png())
+    }
+
+    pub fn decode_png(bytes: &[u8]) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(io::Error::other)?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).map_err(io::Error::other)?;
+        buf.truncate(info.buffer_size());
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buf,
+            png::ColorType::Rgb => buf
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buf
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => buf.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+            png::ColorType::Indexed => {
+                return Err(io::Error::other("indexed png was not expanded"));
+            }
+        };
+        Ok(Self::new(info.width, info.height, pixels))
+    }
+
+    pub fn load_png(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::decode_png(&std::fs::read(path)?)
+    }
+
+    // number of pixels where any channel differs by more than `tolerance`.
+    // None when the sizes don't match
+    pub fn diff(&self, other: &Image, tolerance: u8) -> Option<usize> {
+        if self.width != other.width || self.height != other.height {
+            return None;
+        }
+        let count = self
+            .pixels
+            .chunks_exact(4)
+            .zip(other.pixels.chunks_exact(4))
+            .filter(|(a, b)| {
+                a.iter()
+                    .zip(b.iter())
+                    .any(|(x, y)| x.abs_diff(*y) > tolerance)
+            })
+            .count();
+        Some(count)
+    }
+}
+
+impl SoftwareRenderer {
+    pub fn to_image(&self) -> Image {
+        Image::new(self.width(), self.height(), self.rgba8())
+    }
+}
+
+// lays out and paints an element tree offscreen on the cpu, no window, surface or gpu.
+// width and height are logical, the image is scaled by scale_factor.
+// nothing is hovered or focused, widgets draw in their resting state
+pub fn render_to_image<M: Clone + 'static>(
+    element: &mut Element<M>,
+    fonts: &mut Fonts,
+    width: f32,
+    height: f32,
+    scale_factor: f32,
+    clear_color: Color,
+) -> Image {
+    if fonts.default.is_none() {
+        fonts.add("default", "Arial", 14.0).default();
+    }
+    let mut state = StateStore::new();
+    do_layout(element, width, height, fonts, &mut state);
+
+    let mut list = DisplayList::new();
+    let mut mouse = MouseState::new();
+    mouse.x = -1.0;
+    mouse.y = -1.0;
+    draw(element, &mut list, fonts, &mut state, &mouse, scale_factor);
+
+    let mut renderer = SoftwareRenderer::new(width, height, scale_factor);
+    renderer.clear(clear_color);
+    renderer.render(&list, fonts);
+    renderer.to_image()
+}
@@ -1,4 +1,5 @@
 use std::mem;
+use std::ops::Range;
 use wgpu;
 
 // one instance per shadow, passed directly to the vertex shader
@@ -161,6 +162,7 @@ impl ShadowRenderer {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_shadow(
         &mut self,
         x: f32,
@@ -184,7 +186,7 @@ impl ShadowRenderer {
         self.instances.clear();
     }
 
-    pub fn resize(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, width: f32, height: f32) {
+    pub fn resize(&mut self, _device: &wgpu::Device, queue: &wgpu::Queue, width: f32, height: f32) {
         self.screen_width = width;
         self.screen_height = height;
         let screen_uniform = ScreenUniform {
@@ -194,12 +196,12 @@ impl ShadowRenderer {
         queue.write_buffer(&self.screen_buffer, 0, bytemuck::bytes_of(&screen_uniform));
     }
 
-    pub fn render<'pass>(
-        &'pass mut self,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        pass: &mut wgpu::RenderPass<'pass>,
-    ) {
+    pub fn instance_count(&self) -> u32 {
+        self.instances.len() as u32
+    }
+
+    // upload this frame's instances, once per frame before any render_range
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
         if self.instances.is_empty() {
             return;
         }
@@ -215,10 +217,28 @@ impl ShadowRenderer {
         }
 
         queue.write_buffer(&self.instance_buffer, 0, data);
+    }
+
+    // draw a slice of the prepared instances, lets layers interleave with other renderers
+    pub fn render_range<'pass>(&'pass self, pass: &mut wgpu::RenderPass<'pass>, range: Range<u32>) {
+        if range.is_empty() {
+            return;
+        }
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, &self.bind_group, &[]);
         pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
         // 6 vertices per instance (two triangles = one quad)
-        pass.draw(0..6, 0..self.instances.len() as u32);
+        pass.draw(0..6, range);
+    }
+
+    pub fn render<'pass>(
+        &'pass mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pass: &mut wgpu::RenderPass<'pass>,
+    ) {
+        self.prepare(device, queue);
+        let len = self.instance_count();
+        self.render_range(pass, 0..len);
     }
 }
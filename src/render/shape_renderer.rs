@@ -1,4 +1,5 @@
 use std::mem;
+use std::ops::Range;
 use wgpu;
 
 #[repr(C)]
@@ -130,6 +131,7 @@ impl ShapeRenderer {
 
     /// Axis-aligned rectangle with optional border. No rounding.
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_rect(
         &mut self,
         x: f32,
@@ -169,6 +171,7 @@ impl ShapeRenderer {
 
     /// rounded rectangle with optional border
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_rounded_rect(
         &mut self,
         x: f32,
@@ -194,6 +197,25 @@ impl ShapeRenderer {
         );
     }
 
+    /// rounded rectangle with optional border, clipped to [cx, cy, cx2, cy2]
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_rounded_rect_clipped(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        radius: f32,
+        color: [f32; 4],
+        outline_color: [f32; 4],
+        outline_thickness: f32,
+        clip: [f32; 4],
+    ) {
+        let r = radius.min(w * 0.5).min(h * 0.5);
+        self.push_clipped(x, y, w, h, r, outline_thickness, color, outline_color, clip);
+    }
+
     /// circle. radius is the outer radius; the circle is centered at (cx, cy)
     #[inline]
     pub fn draw_circle(
@@ -220,6 +242,7 @@ impl ShapeRenderer {
     }
 
     #[inline(always)]
+    #[allow(clippy::too_many_arguments)]
     fn push(
         &mut self,
         x: f32,
@@ -243,6 +266,7 @@ impl ShapeRenderer {
     }
 
     #[inline(always)]
+    #[allow(clippy::too_many_arguments)]
     fn push_clipped(
         &mut self,
         x: f32,
@@ -272,12 +296,12 @@ impl ShapeRenderer {
 
     // render
 
-    pub fn render<'pass>(
-        &'pass mut self,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        pass: &mut wgpu::RenderPass<'pass>,
-    ) {
+    pub fn instance_count(&self) -> u32 {
+        self.instances.len() as u32
+    }
+
+    // upload this frame's instances, once per frame before any render_range
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
         if self.instances.is_empty() {
             return;
         }
@@ -295,9 +319,27 @@ impl ShapeRenderer {
         }
 
         queue.write_buffer(&self.instance_buffer, 0, data);
+    }
+
+    // draw a slice of the prepared instances, lets layers interleave with other renderers
+    pub fn render_range<'pass>(&'pass self, pass: &mut wgpu::RenderPass<'pass>, range: Range<u32>) {
+        if range.is_empty() {
+            return;
+        }
         pass.set_pipeline(&self.pipeline);
         pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
-        pass.draw(0..6, 0..self.instances.len() as u32);
+        pass.draw(0..6, range);
+    }
+
+    pub fn render<'pass>(
+        &'pass mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pass: &mut wgpu::RenderPass<'pass>,
+    ) {
+        self.prepare(device, queue);
+        let len = self.instance_count();
+        self.render_range(pass, 0..len);
     }
 
     pub fn resize(&mut self, width: f32, height: f32) {
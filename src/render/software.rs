@@ -0,0 +1,453 @@
+use glyphon::cosmic_text::Align as CosmicAlign;
+use glyphon::{
+    Attrs, Buffer, Family, FontSystem, Metrics, Shaping, Style as GlyphonStyle, SwashCache,
+    SwashContent, Weight,
+};
+
+use crate::render::text_renderer::rich_spans;
+use crate::{Color, DisplayList, Fonts, Primitive, TextAlign, TextRun};
+
+// cpu backend, rasterizes a display list into an rgba buffer with no gpu at all. it's
+// for offscreen rendering, render_to_image and the harness, windows always use wgpu.
+// the shape and shadow math is a straight port of rounded_rect.wgsl / shadow.wgsl and
+// glyphs come from the same cosmic-text shaping + swash rasterizer glyphon uses, so
+// the output should match the wgpu renderers to within antialiasing noise.
+//
+// like the gpu path this blends in linear space and encodes to srgb on the way out,
+// which is what an srgb surface does. painting order matches Runner::render too,
+// see DisplayList::layers
+
+pub struct SoftwareRenderer {
+    // physical pixels
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+    // linear, straight alpha
+    pixels: Vec<[f32; 4]>,
+    swash_cache: SwashCache,
+}
+
+impl SoftwareRenderer {
+    // width and height are logical, the buffer is width * scale_factor wide
+    pub fn new(width: f32, height: f32, scale_factor: f32) -> Self {
+        let mut renderer = Self {
+            width: 0,
+            height: 0,
+            scale_factor,
+            pixels: Vec::new(),
+            swash_cache: SwashCache::new(),
+        };
+        renderer.resize(width, height, scale_factor);
+        renderer
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32, scale_factor: f32) {
+        self.width = (width * scale_factor).round().max(0.0) as u32;
+        self.height = (height * scale_factor).round().max(0.0) as u32;
+        self.scale_factor = scale_factor;
+        self.pixels = vec![[0.0; 4]; (self.width * self.height) as usize];
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        self.pixels.fill(color.to_array());
+    }
+
+    // srgb encoded rgba8, row major, width * height * 4 bytes
+    pub fn rgba8(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.pixels.len() * 4);
+        for p in &self.pixels {
+            out.push(to_u8(linear_to_srgb(p[0])));
+            out.push(to_u8(linear_to_srgb(p[1])));
+            out.push(to_u8(linear_to_srgb(p[2])));
+            out.push(to_u8(p[3]));
+        }
+        out
+    }
+
+    // srgb encoded rgba8 of a single physical pixel
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let p = self.pixels[(y * self.width + x) as usize];
+        [
+            to_u8(linear_to_srgb(p[0])),
+            to_u8(linear_to_srgb(p[1])),
+            to_u8(linear_to_srgb(p[2])),
+            to_u8(p[3]),
+        ]
+    }
+
+    pub fn render(&mut self, list: &DisplayList, fonts: &mut Fonts) {
+        // same layering as the wgpu runner, each layer is shadows -> shapes -> text
+        for layer in list.layers(fonts) {
+            for (item, _) in &layer {
+                if let Primitive::Shadow {
+                    x,
+                    y,
+                    w,
+                    h,
+                    color,
+                    radius,
+                    blur,
+                    offset_x,
+                    offset_y,
+                } = item
+                {
+                    self.draw_shadow(*x, *y, *w, *h, *color, *radius, *blur, *offset_x, *offset_y);
+                }
+            }
+            for (item, clip) in &layer {
+                match item {
+                    Primitive::Rect {
+                        x,
+                        y,
+                        w,
+                        h,
+                        color,
+                        border_color,
+                        border_thickness,
+                    } => self.draw_shape(
+                        *x,
+                        *y,
+                        *w,
+                        *h,
+                        0.0,
+                        *color,
+                        *border_color,
+                        *border_thickness,
+                        *clip,
+                    ),
+                    Primitive::RoundedRect {
+                        x,
+                        y,
+                        w,
+                        h,
+                        radius,
+                        color,
+                        border_color,
+                        border_thickness,
+                    } => self.draw_shape(
+                        *x,
+                        *y,
+                        *w,
+                        *h,
+                        radius.min(w * 0.5).min(h * 0.5),
+                        *color,
+                        *border_color,
+                        *border_thickness,
+                        *clip,
+                    ),
+                    _ => {}
+                }
+            }
+            for (item, clip) in &layer {
+                if let Primitive::Text(run) = item {
+                    self.draw_text(&mut fonts.font_system, run, *clip);
+                }
+            }
+        }
+    }
+
+    // ─── shapes ────────────────────────────────────────────────────────────────
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_shape(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        radius: f32,
+        fill: [f32; 4],
+        border: [f32; 4],
+        border_w: f32,
+        clip: Option<[f32; 4]>,
+    ) {
+        if let Some([cx, cy, cx2, cy2]) = clip
+            && (x + w <= cx || y + h <= cy || x >= cx2 || y >= cy2)
+        {
+            return;
+        }
+        let s = self.scale_factor;
+        let half = [w * 0.5, h * 0.5];
+        let (mid_x, mid_y) = (x + half[0], y + half[1]);
+        // the quad is grown by the 1px aa band, same as the vertex shader
+        let Some((px0, py0, px1, py1)) =
+            self.pixel_bounds(x - 1.0, y - 1.0, x + w + 1.0, y + h + 1.0)
+        else {
+            return;
+        };
+        let step = 1.0 / s;
+        for py in py0..py1 {
+            for px in px0..px1 {
+                let lx = (px as f32 + 0.5) / s;
+                let ly = (py as f32 + 0.5) / s;
+                if let Some([cx, cy, cx2, cy2]) = clip
+                    && (lx < cx || ly < cy || lx > cx2 || ly > cy2)
+                {
+                    continue;
+                }
+                let p = [lx - mid_x, ly - mid_y];
+                let d = sdf_rrect(p, half, radius);
+                // fwidth() by finite differences one physical pixel over
+                let fw = (sdf_rrect([p[0] + step, p[1]], half, radius) - d).abs()
+                    + (sdf_rrect([p[0], p[1] + step], half, radius) - d).abs();
+                let outer = aa_coverage(d, fw);
+                if outer <= 0.0 {
+                    continue;
+                }
+                let color = if border_w > 0.0 && border[3] > 0.0 {
+                    let inner = aa_coverage(d + border_w, fw);
+                    mix(border, fill, inner)
+                } else {
+                    fill
+                };
+                self.blend(px, py, [color[0], color[1], color[2], color[3] * outer]);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_shadow(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        color: [f32; 4],
+        radius: f32,
+        blur: f32,
+        offset_x: f32,
+        offset_y: f32,
+    ) {
+        let s = self.scale_factor;
+        let expand = blur * 2.0;
+        let (rx, ry) = (x + offset_x, y + offset_y);
+        let Some((px0, py0, px1, py1)) =
+            self.pixel_bounds(rx - expand, ry - expand, rx + w + expand, ry + h + expand)
+        else {
+            return;
+        };
+        let half = [w * 0.5, h * 0.5];
+        let (mid_x, mid_y) = (rx + half[0], ry + half[1]);
+        let sigma = (blur * 0.5).max(0.0001);
+        for py in py0..py1 {
+            for px in px0..px1 {
+                let lx = (px as f32 + 0.5) / s;
+                let ly = (py as f32 + 0.5) / s;
+                let d = sdf_rrect([lx - mid_x, ly - mid_y], half, radius);
+                let a = (-0.5 * (d / sigma) * (d / sigma)).exp() * color[3];
+                self.blend(px, py, [color[0], color[1], color[2], a]);
+            }
+        }
+    }
+
+    // ─── text ──────────────────────────────────────────────────────────────────
+
+    fn draw_text(&mut self, font_system: &mut FontSystem, run: &TextRun, clip: Option<[f32; 4]>) {
+        let s = self.scale_factor;
+        let logical_w = self.width as f32 / s;
+        let logical_h = self.height as f32 / s;
+
+        // same buffer setup as TextRenderer::draw
+        let mut buffer = Buffer::new(font_system, Metrics::new(run.size, run.size * 1.4));
+        buffer.set_size(
+            font_system,
+            Some(if run.width == f32::MAX {
+                logical_w - run.x
+            } else {
+                run.width
+            }),
+            Some(logical_h - run.y),
+        );
+        let attrs = Attrs::new()
+            .family(Family::Name(run.family.as_str()))
+            .weight(Weight(run.weight))
+            .style(if run.italic {
+                GlyphonStyle::Italic
+            } else {
+                GlyphonStyle::Normal
+            });
+        buffer.set_rich_text(
+            font_system,
+            rich_spans(&run.text, &run.spans, &attrs),
+            &attrs,
+            Shaping::Advanced,
+            None,
+        );
+        let align = match run.align {
+            TextAlign::Left => CosmicAlign::Left,
+            TextAlign::Center => CosmicAlign::Center,
+            TextAlign::Right => CosmicAlign::Right,
+        };
+        for line in buffer.lines.iter_mut() {
+            line.set_align(Some(align));
+        }
+        buffer.shape_until_scroll(font_system, false);
+
+        // glyphon keeps the color as srgb bytes and linearizes it in the shader
+        let run_color = [
+            srgb_to_linear(((run.color.r * 255.0) as u8) as f32 / 255.0),
+            srgb_to_linear(((run.color.g * 255.0) as u8) as f32 / 255.0),
+            srgb_to_linear(((run.color.b * 255.0) as u8) as f32 / 255.0),
+            1.0,
+        ];
+        let (bx0, by0, bx1, by1) = match clip {
+            Some([cx, cy, cx2, cy2]) => (
+                ((cx * s) as i32).max(0),
+                ((cy * s) as i32).max(0),
+                ((cx2 * s) as i32).min(self.width as i32),
+                ((cy2 * s) as i32).min(self.height as i32),
+            ),
+            None => (0, 0, self.width as i32, self.height as i32),
+        };
+
+        for layout_run in buffer.layout_runs() {
+            for glyph in layout_run.glyphs.iter() {
+                // a span's color, if the glyph is in one
+                let text_color = match glyph.color_opt {
+                    Some(c) => [
+                        srgb_to_linear(c.r() as f32 / 255.0),
+                        srgb_to_linear(c.g() as f32 / 255.0),
+                        srgb_to_linear(c.b() as f32 / 255.0),
+                        1.0,
+                    ],
+                    None => run_color,
+                };
+                let physical = glyph.physical((run.x * s, run.y * s), s);
+                let Some(image) = self
+                    .swash_cache
+                    .get_image(font_system, physical.cache_key)
+                    .as_ref()
+                else {
+                    continue;
+                };
+                let gx = physical.x + image.placement.left;
+                let gy = (layout_run.line_y * s).round() as i32 + physical.y - image.placement.top;
+                let (gw, gh) = (image.placement.width as i32, image.placement.height as i32);
+                let content = image.content;
+                let data = image.data.clone();
+                for iy in 0..gh {
+                    let py = gy + iy;
+                    if py < by0 || py >= by1 {
+                        continue;
+                    }
+                    for ix in 0..gw {
+                        let px = gx + ix;
+                        if px < bx0 || px >= bx1 {
+                            continue;
+                        }
+                        let i = (iy * gw + ix) as usize;
+                        let src = match content {
+                            SwashContent::Mask => [
+                                text_color[0],
+                                text_color[1],
+                                text_color[2],
+                                data[i] as f32 / 255.0,
+                            ],
+                            SwashContent::SubpixelMask => {
+                                let a = data[i * 4..i * 4 + 3]
+                                    .iter()
+                                    .map(|&v| v as u32)
+                                    .sum::<u32>();
+                                [
+                                    text_color[0],
+                                    text_color[1],
+                                    text_color[2],
+                                    a as f32 / (3.0 * 255.0),
+                                ]
+                            }
+                            SwashContent::Color => [
+                                srgb_to_linear(data[i * 4] as f32 / 255.0),
+                                srgb_to_linear(data[i * 4 + 1] as f32 / 255.0),
+                                srgb_to_linear(data[i * 4 + 2] as f32 / 255.0),
+                                data[i * 4 + 3] as f32 / 255.0,
+                            ],
+                        };
+                        self.blend(px as u32, py as u32, src);
+                    }
+                }
+            }
+        }
+    }
+
+    // ─── helpers ───────────────────────────────────────────────────────────────
+
+    // logical rect -> clamped physical pixel range, None if nothing is on screen
+    fn pixel_bounds(&self, x0: f32, y0: f32, x1: f32, y1: f32) -> Option<(u32, u32, u32, u32)> {
+        let s = self.scale_factor;
+        let px0 = (x0 * s).floor().max(0.0) as u32;
+        let py0 = (y0 * s).floor().max(0.0) as u32;
+        let px1 = ((x1 * s).ceil().max(0.0) as u32).min(self.width);
+        let py1 = ((y1 * s).ceil().max(0.0) as u32).min(self.height);
+        if px0 >= px1 || py0 >= py1 {
+            return None;
+        }
+        Some((px0, py0, px1, py1))
+    }
+
+    // wgpu::BlendState::ALPHA_BLENDING
+    #[inline]
+    fn blend(&mut self, x: u32, y: u32, src: [f32; 4]) {
+        let a = src[3].clamp(0.0, 1.0);
+        if a <= 0.0 {
+            return;
+        }
+        let dst = &mut self.pixels[(y * self.width + x) as usize];
+        dst[0] = src[0] * a + dst[0] * (1.0 - a);
+        dst[1] = src[1] * a + dst[1] * (1.0 - a);
+        dst[2] = src[2] * a + dst[2] * (1.0 - a);
+        dst[3] = a + dst[3] * (1.0 - a);
+    }
+}
+
+fn sdf_rrect(p: [f32; 2], half_size: [f32; 2], radius: f32) -> f32 {
+    let qx = p[0].abs() - half_size[0] + radius;
+    let qy = p[1].abs() - half_size[1] + radius;
+    let outside = (qx.max(0.0) * qx.max(0.0) + qy.max(0.0) * qy.max(0.0)).sqrt();
+    outside + qx.max(qy).min(0.0) - radius
+}
+
+fn aa_coverage(d: f32, fw: f32) -> f32 {
+    (0.5 - d / fw.max(0.0001)).clamp(0.0, 1.0)
+}
+
+fn mix(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+        a[3] + (b[3] - a[3]) * t,
+    ]
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn to_u8(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
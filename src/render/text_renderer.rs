@@ -1,10 +1,11 @@
-use crate::{Color, TextAlign};
+use crate::{Color, SpanStyle, TextAlign};
 use cosmic_text::Align as CosmicAlign;
 use glyphon::{
     Attrs, Buffer, Cache, Color as GlyphonColor, Family, FontSystem, Metrics, Resolution, Shaping,
     Style as GlyphonStyle, SwashCache, TextArea, TextAtlas, TextBounds,
     TextRenderer as GlyphonRenderer, Viewport, Weight, cosmic_text,
 };
+use std::ops::Range;
 use wgpu;
 
 struct TextEntry {
@@ -21,14 +22,16 @@ struct TextEntry {
     italic: bool,
     text_align: TextAlign,
     color: GlyphonColor,
+    spans: Vec<(Range<usize>, SpanStyle)>,
 }
 
 pub struct TextRenderer {
-    cache: Cache,
     swash_cache: SwashCache,
     pub atlas: TextAtlas,
     viewport: Viewport,
-    renderer: GlyphonRenderer,
+    renderers: Vec<GlyphonRenderer>,
+    // which renderers got text in the last prepare
+    prepared: Vec<bool>,
     entries: Vec<TextEntry>,
     active: usize,
     screen_width: f32,
@@ -41,24 +44,15 @@ impl TextRenderer {
         let cache = Cache::new(device);
         let swash_cache = SwashCache::new();
         let mut atlas = TextAtlas::new(device, queue, &cache, format);
-        let renderer = GlyphonRenderer::new(
-            &mut atlas,
-            device,
-            wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            None,
-        );
+        let renderer = new_glyphon_renderer(&mut atlas, device);
         let viewport = Viewport::new(device, &cache);
 
         Self {
-            cache,
             swash_cache,
             atlas,
             viewport,
-            renderer,
+            renderers: vec![renderer],
+            prepared: Vec::new(),
             entries: Vec::new(),
             active: 0,
             screen_width: 800.0,
@@ -73,6 +67,7 @@ impl TextRenderer {
         self.scale_factor = scale_factor;
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn draw(
         &mut self,
         font_system: &mut FontSystem,
@@ -87,12 +82,9 @@ impl TextRenderer {
         width: f32,
         clip: Option<[f32; 4]>,
         color: Color,
+        spans: &[(Range<usize>, SpanStyle)],
     ) {
-        let glyphon_color = GlyphonColor::rgb(
-            (color.r * 255.0) as u8,
-            (color.g * 255.0) as u8,
-            (color.b * 255.0) as u8,
-        );
+        let glyphon_color = glyphon_color(color);
 
         let scale = self.scale_factor as f32;
         let line_height = size * 1.4;
@@ -134,7 +126,8 @@ impl TextRenderer {
                 || entry.weight != weight
                 || entry.italic != italic
                 || entry.text_align != text_align
-                || entry.width != width;
+                || entry.width != width
+                || entry.spans != spans;
             if content_changed {
                 entry.text = text.to_string();
                 entry.family = family.clone();
@@ -143,6 +136,7 @@ impl TextRenderer {
                 entry.italic = italic;
                 entry.text_align = text_align;
                 entry.width = width;
+                entry.spans = spans.to_vec();
                 entry
                     .buffer
                     .set_metrics(font_system, Metrics::new(size, line_height));
@@ -155,9 +149,13 @@ impl TextRenderer {
                     }),
                     Some(self.screen_height - y),
                 );
-                entry
-                    .buffer
-                    .set_text(font_system, text, &attrs, Shaping::Advanced);
+                entry.buffer.set_rich_text(
+                    font_system,
+                    rich_spans(text, spans, &attrs),
+                    &attrs,
+                    Shaping::Advanced,
+                    None,
+                );
                 apply_align(&mut entry.buffer);
                 entry.buffer.shape_until_scroll(font_system, false);
             }
@@ -172,7 +170,13 @@ impl TextRenderer {
                 }),
                 Some(self.screen_height - y),
             );
-            buffer.set_text(font_system, text, &attrs, Shaping::Advanced);
+            buffer.set_rich_text(
+                font_system,
+                rich_spans(text, spans, &attrs),
+                &attrs,
+                Shaping::Advanced,
+                None,
+            );
             apply_align(&mut buffer);
             buffer.shape_until_scroll(font_system, false);
             self.entries.push(TextEntry {
@@ -189,19 +193,27 @@ impl TextRenderer {
                 italic,
                 text_align,
                 color: glyphon_color,
+                spans: spans.to_vec(),
             });
         }
     }
 
-    pub fn render<'pass>(
-        &'pass mut self,
+    pub fn run_count(&self) -> usize {
+        self.active
+    }
+
+    // shape this frame's text into one glyphon batch per layer, each layer being a
+    // range of draw() calls. once per frame before any render_layer
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
+        &mut self,
         font_system: &mut FontSystem,
         screen_width: f32,
         screen_height: f32,
         scale_factor: f64,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        pass: &mut wgpu::RenderPass<'pass>,
+        layers: &[Range<usize>],
     ) {
         let physical_width = (screen_width * scale_factor as f32) as u32;
         let physical_height = (screen_height * scale_factor as f32) as u32;
@@ -214,58 +226,96 @@ impl TextRenderer {
             },
         );
 
-        if self.active == 0 {
-            return;
+        // glyphon renders everything it was prepared with in one go, so every layer
+        // that has text gets its own renderer. they all share the atlas
+        while self.renderers.len() < layers.len() {
+            self.renderers
+                .push(new_glyphon_renderer(&mut self.atlas, device));
         }
+        // and when a frame has fewer layers than before, the spare ones and their
+        // vertex buffers go. the first stays for render()
+        self.renderers.truncate(layers.len().max(1));
 
-        let text_areas: Vec<TextArea> = self.entries[..self.active]
-            .iter()
-            .map(|entry| {
-                let scale = entry.scale;
-                let bounds = if let Some([cx, cy, cx2, cy2]) = entry.clip {
-                    TextBounds {
-                        left: (cx * scale) as i32,
-                        top: (cy * scale) as i32,
-                        right: (cx2 * scale) as i32,
-                        bottom: (cy2 * scale) as i32,
-                    }
-                } else {
-                    TextBounds {
-                        left: 0,
-                        top: 0,
-                        right: physical_width as i32,
-                        bottom: physical_height as i32,
+        for (renderer, range) in self.renderers.iter_mut().zip(layers) {
+            let text_areas: Vec<TextArea> = self.entries[range.clone()]
+                .iter()
+                .map(|entry| {
+                    let scale = entry.scale;
+                    let bounds = if let Some([cx, cy, cx2, cy2]) = entry.clip {
+                        TextBounds {
+                            left: (cx * scale) as i32,
+                            top: (cy * scale) as i32,
+                            right: (cx2 * scale) as i32,
+                            bottom: (cy2 * scale) as i32,
+                        }
+                    } else {
+                        TextBounds {
+                            left: 0,
+                            top: 0,
+                            right: physical_width as i32,
+                            bottom: physical_height as i32,
+                        }
+                    };
+                    TextArea {
+                        buffer: &entry.buffer,
+                        left: entry.x * scale,
+                        top: entry.y * scale,
+                        scale,
+                        bounds,
+                        default_color: entry.color,
+                        custom_glyphs: &[],
                     }
-                };
-                TextArea {
-                    buffer: &entry.buffer,
-                    left: entry.x * scale,
-                    top: entry.y * scale,
-                    scale,
-                    bounds,
-                    default_color: entry.color,
-                    custom_glyphs: &[],
-                }
-            })
-            .collect();
+                })
+                .collect();
 
-        self.renderer
-            .prepare(
-                device,
-                queue,
-                font_system,
-                &mut self.atlas,
-                &self.viewport,
-                text_areas,
-                &mut self.swash_cache,
-            )
-            .unwrap();
+            renderer
+                .prepare(
+                    device,
+                    queue,
+                    font_system,
+                    &mut self.atlas,
+                    &self.viewport,
+                    text_areas,
+                    &mut self.swash_cache,
+                )
+                .unwrap();
+        }
+        self.prepared = layers.iter().map(|r| !r.is_empty()).collect();
+    }
 
-        self.renderer
+    pub fn render_layer<'pass>(&'pass self, layer: usize, pass: &mut wgpu::RenderPass<'pass>) {
+        if !self.prepared.get(layer).copied().unwrap_or(false) {
+            return;
+        }
+        self.renderers[layer]
             .render(&self.atlas, &self.viewport, pass)
             .unwrap();
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn render<'pass>(
+        &'pass mut self,
+        font_system: &mut FontSystem,
+        screen_width: f32,
+        screen_height: f32,
+        scale_factor: f64,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pass: &mut wgpu::RenderPass<'pass>,
+    ) {
+        let all = 0..self.active;
+        self.prepare(
+            font_system,
+            screen_width,
+            screen_height,
+            scale_factor,
+            device,
+            queue,
+            &[all],
+        );
+        self.render_layer(0, pass);
+    }
+
     pub fn trim_atlas(&mut self) {
         self.atlas.trim();
     }
@@ -274,3 +324,65 @@ impl TextRenderer {
         self.active = 0;
     }
 }
+
+fn glyphon_color(color: Color) -> GlyphonColor {
+    GlyphonColor::rgb(
+        (color.r * 255.0) as u8,
+        (color.g * 255.0) as u8,
+        (color.b * 255.0) as u8,
+    )
+}
+
+// text cut into the pieces glyphon's set_rich_text takes: the spans with their style
+// laid over base, and the text between them with base as is. spans that overlap an
+// earlier one, run past the text or split a char are left out
+pub(crate) fn rich_spans<'a>(
+    text: &'a str,
+    spans: &[(Range<usize>, SpanStyle)],
+    base: &Attrs<'a>,
+) -> Vec<(&'a str, Attrs<'a>)> {
+    let mut out = Vec::new();
+    let mut at = 0;
+    for (range, style) in spans {
+        let end = range.end.min(text.len());
+        if range.start < at
+            || range.start >= end
+            || !text.is_char_boundary(range.start)
+            || !text.is_char_boundary(end)
+        {
+            continue;
+        }
+        if at < range.start {
+            out.push((&text[at..range.start], base.clone()));
+        }
+        let mut attrs = base.clone();
+        if let Some(color) = style.color {
+            attrs = attrs.color(glyphon_color(color));
+        }
+        if let Some(weight) = style.weight {
+            attrs = attrs.weight(Weight(weight));
+        }
+        if style.italic {
+            attrs = attrs.style(GlyphonStyle::Italic);
+        }
+        out.push((&text[range.start..end], attrs));
+        at = end;
+    }
+    if at < text.len() || out.is_empty() {
+        out.push((&text[at..], base.clone()));
+    }
+    out
+}
+
+fn new_glyphon_renderer(atlas: &mut TextAtlas, device: &wgpu::Device) -> GlyphonRenderer {
+    GlyphonRenderer::new(
+        atlas,
+        device,
+        wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        None,
+    )
+}
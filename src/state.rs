@@ -8,6 +8,12 @@ pub struct StateStore {
     callbacks: HashMap<(TypeId, String), Box<dyn Any>>,
 }
 
+impl Default for StateStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StateStore {
     pub fn new() -> Self {
         Self {
@@ -61,12 +67,11 @@ impl StateStore {
     pub fn find<T: Any, F: Fn(&T) -> bool>(&self, pred: F) -> Option<String> {
         let target = TypeId::of::<T>();
         for ((type_id, id), value) in &self.state {
-            if *type_id == target {
-                if let Some(val) = value.downcast_ref::<T>() {
-                    if pred(val) {
-                        return Some(id.clone());
-                    }
-                }
+            if *type_id == target
+                && let Some(val) = value.downcast_ref::<T>()
+                && pred(val)
+            {
+                return Some(id.clone());
             }
         }
         None
@@ -3,6 +3,8 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 
+use crate::widgets::text_editor::SearchOp;
+
 pub struct Task<A: Send + Clone + 'static> {
     pub(crate) kind: TaskKind<A>,
     pub(crate) exclusive_key: Option<u64>,
@@ -14,6 +16,23 @@ pub(crate) enum TaskKind<A: Send + 'static> {
     Background(Box<dyn FnOnce() -> A + Send + 'static>),
     Delay(Duration, A),
     Every(Duration, A),
+    // acts on widget state instead of producing an action, applied by the shell
+    // before the next frame is drawn
+    Widget(WidgetOp),
+    // clipboard access goes through the shell, which owns the clipboard
+    ReadClipboard(Box<dyn FnOnce(Option<String>) -> A + Send + 'static>),
+    WriteClipboard(String),
+}
+
+pub(crate) enum WidgetOp {
+    ScrollTo { id: String, x: f32, y: f32 },
+    ScrollIntoView(String),
+    Focus(String),
+    Blur,
+    FocusNext,
+    FocusPrevious,
+    ClearHistory(String),
+    Search(String, SearchOp),
 }
 
 impl<A: Send + Clone + 'static> Task<A> {
@@ -49,6 +68,100 @@ impl<A: Send + Clone + 'static> Task<A> {
         }
     }
 
+    // scroll the overflow_scroll container with this id to an offset from its start.
+    // clamped to how far its content overflows
+    pub fn scroll_to(id: &str, x: f32, y: f32) -> Self {
+        Task::widget(WidgetOp::ScrollTo {
+            id: id.to_string(),
+            x,
+            y,
+        })
+    }
+
+    // scroll the nearest overflow_scroll container around the element with this id
+    // just far enough to show all of it
+    pub fn scroll_into_view(id: &str) -> Self {
+        Task::widget(WidgetOp::ScrollIntoView(id.to_string()))
+    }
+
+    // give keyboard focus to the text input, text editor or button with this id
+    pub fn focus(id: &str) -> Self {
+        Task::widget(WidgetOp::Focus(id.to_string()))
+    }
+
+    // take keyboard focus away from whatever has it
+    pub fn blur() -> Self {
+        Task::widget(WidgetOp::Blur)
+    }
+
+    // move focus the way Tab does
+    pub fn focus_next() -> Self {
+        Task::widget(WidgetOp::FocusNext)
+    }
+
+    // move focus the way Shift+Tab does
+    pub fn focus_previous() -> Self {
+        Task::widget(WidgetOp::FocusPrevious)
+    }
+
+    // forget the undo history of the text input or editor with this id, for when the
+    // app replaces its value with something the user didn't type
+    pub fn clear_history(id: &str) -> Self {
+        Task::widget(WidgetOp::ClearHistory(id.to_string()))
+    }
+
+    // select the next match of the text editor's search after the cursor, wrapping
+    // around at the end, and scroll it into view
+    pub fn find_next(id: &str) -> Self {
+        Task::widget(WidgetOp::Search(id.to_string(), SearchOp::Next))
+    }
+
+    pub fn find_previous(id: &str) -> Self {
+        Task::widget(WidgetOp::Search(id.to_string(), SearchOp::Previous))
+    }
+
+    // replace the selected match of the text editor's search and select the next one.
+    // a regex search fills in $1 or $name in with from the match's groups
+    pub fn replace(id: &str, with: &str) -> Self {
+        Task::widget(WidgetOp::Search(
+            id.to_string(),
+            SearchOp::Replace(with.to_string()),
+        ))
+    }
+
+    // replace every match of the text editor's search, as one undo step
+    pub fn replace_all(id: &str, with: &str) -> Self {
+        Task::widget(WidgetOp::Search(
+            id.to_string(),
+            SearchOp::ReplaceAll(with.to_string()),
+        ))
+    }
+
+    // read the clipboard and turn its text into an action, None if there's no text
+    pub fn read_clipboard(f: impl FnOnce(Option<String>) -> A + Send + 'static) -> Self {
+        Task {
+            kind: TaskKind::ReadClipboard(Box::new(f)),
+            exclusive_key: None,
+            timeout: None,
+        }
+    }
+
+    pub fn write_clipboard(text: &str) -> Self {
+        Task {
+            kind: TaskKind::WriteClipboard(text.to_string()),
+            exclusive_key: None,
+            timeout: None,
+        }
+    }
+
+    fn widget(op: WidgetOp) -> Self {
+        Task {
+            kind: TaskKind::Widget(op),
+            exclusive_key: None,
+            timeout: None,
+        }
+    }
+
     // only one task from this call site can run at a time
     // if one is already running it gets cancelled before this one starts
     #[track_caller]
@@ -65,6 +178,29 @@ impl<A: Send + Clone + 'static> Task<A> {
         self
     }
 
+    // the same task with the action it produces passed through f, for running a
+    // module's tasks from its parent, like Element::map
+    pub fn map<B: Send + Clone + 'static>(self, f: impl Fn(A) -> B + Send + 'static) -> Task<B> {
+        let timeout = self.timeout.map(|(duration, action)| (duration, f(action)));
+        let kind = match self.kind {
+            TaskKind::Future(fut) => TaskKind::Future(Box::pin(async move {
+                let action = fut.await;
+                f(action)
+            })),
+            TaskKind::Background(g) => TaskKind::Background(Box::new(move || f(g()))),
+            TaskKind::Delay(duration, action) => TaskKind::Delay(duration, f(action)),
+            TaskKind::Every(duration, action) => TaskKind::Every(duration, f(action)),
+            TaskKind::Widget(op) => TaskKind::Widget(op),
+            TaskKind::ReadClipboard(g) => TaskKind::ReadClipboard(Box::new(move |t| f(g(t)))),
+            TaskKind::WriteClipboard(text) => TaskKind::WriteClipboard(text),
+        };
+        Task {
+            kind,
+            exclusive_key: self.exclusive_key,
+            timeout,
+        }
+    }
+
     // if the task takes longer than duration, fire the fallback action instead
     pub fn timeout(mut self, duration: Duration, action: A) -> Self {
         self.timeout = Some((duration, action));
@@ -74,7 +210,7 @@ impl<A: Send + Clone + 'static> Task<A> {
     pub(crate) fn spawn(
         self,
         send: Arc<dyn Fn(A) + Send + Sync + 'static>,
-        on_abort_handle: impl FnOnce(tokio::task::AbortHandle),
+        on_spawn: impl FnOnce(tokio::task::JoinHandle<()>),
     ) {
         match self.kind {
             TaskKind::Future(fut) => {
@@ -88,26 +224,23 @@ impl<A: Send + Clone + 'static> Task<A> {
                     } else {
                         send(fut.await);
                     }
-                })
-                .abort_handle();
-                on_abort_handle(handle);
+                });
+                on_spawn(handle);
             }
             TaskKind::Background(f) => {
                 let send = send.clone();
                 let handle = tokio::spawn(async move {
                     let action = tokio::task::spawn_blocking(f).await.unwrap();
                     send(action);
-                })
-                .abort_handle();
-                on_abort_handle(handle);
+                });
+                on_spawn(handle);
             }
             TaskKind::Delay(duration, action) => {
                 let handle = tokio::spawn(async move {
                     tokio::time::sleep(duration).await;
                     send(action);
-                })
-                .abort_handle();
-                on_abort_handle(handle);
+                });
+                on_spawn(handle);
             }
             TaskKind::Every(duration, action) => {
                 let handle = tokio::spawn(async move {
@@ -117,10 +250,11 @@ impl<A: Send + Clone + 'static> Task<A> {
                         interval.tick().await;
                         send(action.clone());
                     }
-                })
-                .abort_handle();
-                on_abort_handle(handle);
+                });
+                on_spawn(handle);
             }
+            // taken out by the shell before anything is spawned
+            TaskKind::Widget(_) | TaskKind::ReadClipboard(_) | TaskKind::WriteClipboard(_) => {}
         }
     }
 }
@@ -0,0 +1,246 @@
+use taffy::prelude::*;
+
+use crate::draw::{DrawCtx, MouseState};
+use crate::element::Mapper;
+use crate::focus::{self, FocusKind};
+use crate::state::StateStore;
+use crate::{Element, Event, Fonts, Interactions};
+
+// Widget
+// what the tree needs from an element: a taffy node to size it, the box taffy gave it,
+// and a way to paint it. apps implement it for their own leaf widgets and add them with
+// custom(). the built-in leaf widgets are sized and painted through it too, but apart
+// from a button's Enter and Space they take their input from the shell and fire their
+// handlers while they draw, not through on_event, and rows, columns and overlays are
+// still placed by the tree itself. a widget is rebuilt by view() every frame like the
+// rest of the tree, anything that has to outlive the frame belongs in the state store
+// under the widget's id
+
+pub trait Widget<M> {
+    fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId;
+
+    // absolute position and size, once the layout pass is done
+    fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32);
+
+    fn draw(&mut self, ctx: &mut DrawCtx<M>);
+
+    // the [x, y, w, h] apply_layout was last given, for hit testing
+    fn bounds(&self) -> [f32; 4];
+
+    // needed to be focused or to be found by tasks
+    fn id(&self) -> Option<&str> {
+        None
+    }
+
+    // the widget's path through the tree, handed over before layout when id() is None.
+    // a widget that keeps it and reports it from id() needs no id of its own, see key.rs
+    fn set_id(&mut self, _id: String) {}
+
+    // handlers the hit test marks hovered and targeted, and fires after draw
+    fn interactions(&self) -> Option<&Interactions<M>> {
+        None
+    }
+
+    fn interactions_mut(&mut self) -> Option<&mut Interactions<M>> {
+        None
+    }
+
+    // takes focus on click and with Tab, needs an id, its own or the one set_id gave it
+    fn focusable(&self) -> bool {
+        false
+    }
+
+    // where Tab gets to it, like tab_index on the built-ins: positive ones first in
+    // ascending order, then 0 in tree order, negative ones never
+    fn tab_index(&self) -> i32 {
+        0
+    }
+
+    // input the app didn't handle, delivered before the frame is drawn. keys only reach
+    // the focused widget, pointer events reach every widget, topmost first, until one
+    // returns true. of the built-ins only button takes its keys here, the rest take
+    // their input from the shell
+    fn on_event(&mut self, _event: &Event, _ctx: &mut EventCtx<M>) -> bool {
+        false
+    }
+}
+
+// a custom widget under Element::map. the hit test marks a mapped copy of its
+// interactions, and whatever actions it pushes go through f on the way out
+pub(crate) struct Mapped<A, B> {
+    widget: Box<dyn Widget<A>>,
+    f: Mapper<A, B>,
+    interactions: Option<Interactions<B>>,
+}
+
+impl<A: Clone + 'static, B: Clone + 'static> Mapped<A, B> {
+    pub(crate) fn new(widget: Box<dyn Widget<A>>, f: &Mapper<A, B>) -> Self {
+        let interactions = widget.interactions().map(|i| Interactions {
+            on_click: i.on_click.clone(),
+            on_hover: i.on_hover.clone(),
+            on_mouse_down: i.on_mouse_down.clone(),
+            stop_propagation: i.stop_propagation,
+            ..Interactions::default()
+        });
+        Self {
+            widget,
+            f: f.clone(),
+            interactions: interactions.map(|i| i.map(f)),
+        }
+    }
+
+    // the hit test only marks the mapped copy, the widget reads its own
+    fn sync(&mut self) {
+        if let (Some(from), Some(to)) = (&self.interactions, self.widget.interactions_mut()) {
+            to.hovered = from.hovered;
+            to.targeted = from.targeted;
+        }
+    }
+}
+
+impl<A: Clone + 'static, B: Clone + 'static> Widget<B> for Mapped<A, B> {
+    fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
+        self.widget.layout_node(taffy, fonts)
+    }
+
+    fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        self.widget.apply_layout(x, y, w, h);
+    }
+
+    fn draw(&mut self, ctx: &mut DrawCtx<B>) {
+        self.sync();
+        let mut actions = Vec::new();
+        self.widget.draw(&mut DrawCtx {
+            list: ctx.list,
+            fonts: ctx.fonts,
+            state: ctx.state,
+            mouse: ctx.mouse,
+            clip: ctx.clip,
+            actions: &mut actions,
+            scale_factor: ctx.scale_factor,
+            cursor: ctx.cursor,
+        });
+        ctx.actions.extend(actions.into_iter().filter_map(&*self.f));
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        self.widget.bounds()
+    }
+
+    fn id(&self) -> Option<&str> {
+        self.widget.id()
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.widget.set_id(id);
+    }
+
+    fn interactions(&self) -> Option<&Interactions<B>> {
+        self.interactions.as_ref()
+    }
+
+    fn interactions_mut(&mut self) -> Option<&mut Interactions<B>> {
+        self.interactions.as_mut()
+    }
+
+    fn focusable(&self) -> bool {
+        self.widget.focusable()
+    }
+
+    fn tab_index(&self) -> i32 {
+        self.widget.tab_index()
+    }
+
+    fn on_event(&mut self, event: &Event, ctx: &mut EventCtx<B>) -> bool {
+        self.sync();
+        let mut actions = Vec::new();
+        let used = self.widget.on_event(
+            event,
+            &mut EventCtx {
+                state: ctx.state,
+                mouse: ctx.mouse,
+                actions: &mut actions,
+                hovered: ctx.hovered,
+                focused: ctx.focused,
+            },
+        );
+        ctx.actions.extend(actions.into_iter().filter_map(&*self.f));
+        used
+    }
+}
+
+// everything a widget needs to handle an event
+pub struct EventCtx<'a, M> {
+    pub state: &'a mut StateStore,
+    pub mouse: &'a MouseState,
+    pub actions: &'a mut Vec<M>,
+    // under the pointer and not covered by anything
+    pub hovered: bool,
+    pub focused: bool,
+}
+
+// hands the events queued since the last frame to the widgets in the laid out tree that
+// take them through on_event, returns the actions they pushed. expects a fresh hit test
+pub(crate) fn deliver<M: Clone + 'static>(
+    element: &mut Element<M>,
+    events: &[Event],
+    state: &mut StateStore,
+    mouse: &MouseState,
+) -> Vec<M> {
+    let mut widgets = Vec::new();
+    collect(element, &mut widgets);
+    let mut actions = Vec::new();
+
+    for event in events {
+        let key = matches!(event, Event::KeyPressed { .. } | Event::KeyReleased { .. });
+        // later widgets paint over earlier ones
+        for w in widgets.iter_mut().rev() {
+            let hovered = hovered(&**w, mouse);
+            if let (Event::MousePressed { button, .. }, Some(id)) = (event, w.id())
+                && *button == crate::MouseButton::Left
+                && w.focusable()
+            {
+                focus::press(state, id, FocusKind::Custom, hovered);
+            }
+            let focused = w.id().is_some_and(|id| focus::is_focused(state, id));
+            if key && !focused {
+                continue;
+            }
+            let mut ctx = EventCtx {
+                state,
+                mouse,
+                actions: &mut actions,
+                hovered,
+                focused,
+            };
+            if w.on_event(event, &mut ctx) {
+                break;
+            }
+        }
+    }
+    actions
+}
+
+// the hit test's answer when the widget has interactions, its bounds otherwise
+fn hovered<M>(w: &dyn Widget<M>, mouse: &MouseState) -> bool {
+    match w.interactions() {
+        Some(i) => i.hovered,
+        None => {
+            let [x, y, bw, bh] = w.bounds();
+            mouse.x >= x && mouse.x <= x + bw && mouse.y >= y && mouse.y <= y + bh
+        }
+    }
+}
+
+// custom widgets and buttons in tree order, overlays' after the element they're
+// declared in
+fn collect<'a, M: Clone + 'static>(el: &'a mut Element<M>, out: &mut Vec<&'a mut dyn Widget<M>>) {
+    match el {
+        Element::Custom(w) => out.push(w.as_mut()),
+        Element::Button(w) => out.push(w),
+        Element::Row(r) => r.children.iter_mut().for_each(|c| collect(c, out)),
+        Element::Column(c) => c.children.iter_mut().for_each(|c| collect(c, out)),
+        Element::Overlay(o) => collect(&mut o.child, out),
+        _ => {}
+    }
+}
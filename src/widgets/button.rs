@@ -1,12 +1,19 @@
 use taffy::prelude::*;
 
 use crate::draw::{DrawCtx, check_interactions, draw_shadow, draw_shape, is_outside, with_opacity};
+use crate::element::{Handler, Mapper};
+use crate::focus::{self, FocusKind};
 use crate::layout::{align_to_self, margin_to_rect_lpa, val_to_dimension};
-use crate::{Align, Color, Edges, Fonts, Interactions, Layout, Margin, Style, TextAlign, Val};
+use crate::{
+    Align, Color, Event, EventCtx, Fonts, Interactions, Key, Layout, Margin, Style, TextAlign, Val,
+    Widget,
+};
 
 pub struct Button<M: Clone + 'static> {
     pub id: Option<String>,
+    pub key: Option<String>,
     pub label: String,
+    pub tab_index: i32,
     pub layout: Layout,
     pub style: Style,
     pub interactions: Interactions<M>,
@@ -20,7 +27,9 @@ impl<M: Clone + 'static> Button<M> {
     pub fn new(label: &str) -> Self {
         Self {
             id: None,
+            key: None,
             label: label.to_string(),
+            tab_index: 0,
             layout: Layout::default(),
             style: Style::default(),
             interactions: Interactions::default(),
@@ -36,14 +45,46 @@ impl<M: Clone + 'static> Button<M> {
         self
     }
 
-    pub fn draw(&mut self, ctx: &mut DrawCtx<M>) {
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    pub(crate) fn map<B: Clone + 'static>(self, f: &Mapper<M, B>) -> Button<B> {
+        Button {
+            id: self.id,
+            key: self.key,
+            label: self.label,
+            tab_index: self.tab_index,
+            layout: self.layout,
+            style: self.style,
+            interactions: self.interactions.map(f),
+            x: self.x,
+            y: self.y,
+            w: self.w,
+            h: self.h,
+        }
+    }
+
+    pub(crate) fn require_id(&self) -> &str {
+        self.id
+            .as_deref()
+            .expect("a Button is given an id before layout, see key.rs")
+    }
+}
+
+impl<M: Clone + 'static> Widget<M> for Button<M> {
+    fn draw(&mut self, ctx: &mut DrawCtx<M>) {
         let (x, y, w, h) = (self.x, self.y, self.w, self.h);
+        let hovered = self.interactions.hovered;
+
+        // even out of view, a focused button lets go when the press lands elsewhere
+        if ctx.mouse.left_just_pressed {
+            focus::press(ctx.state, self.require_id(), FocusKind::Button, hovered);
+        }
         if is_outside(x, y, w, h, ctx.clip) {
             return;
         }
-
-        let hovered =
-            ctx.mouse.x >= x && ctx.mouse.x <= x + w && ctx.mouse.y >= y && ctx.mouse.y <= y + h;
         let pressed = hovered && ctx.mouse.left_just_pressed;
 
         if hovered {
@@ -80,14 +121,14 @@ impl<M: Clone + 'static> Button<M> {
                 .unwrap_or(Color::new(0.25, 0.25, 0.35, 1.0))
         };
 
-        draw_shadow(ctx.shadow, x, y, w, h, &self.style);
+        draw_shadow(ctx.list, x, y, w, h, &self.style);
         let border = self
             .style
             .border_color
             .unwrap_or(Color::TRANSPARENT)
             .to_array();
         draw_shape(
-            ctx.sr,
+            ctx.list,
             x,
             y,
             w,
@@ -96,8 +137,10 @@ impl<M: Clone + 'static> Button<M> {
             self.style.border_radius,
             with_opacity(border, self.style.opacity),
             self.style.border_thickness,
-            ctx.clip,
         );
+        if focus::ring_visible(ctx.state, self.require_id()) {
+            focus::draw_ring(ctx.list, x, y, w, h, self.style.border_radius);
+        }
 
         let font_id = ctx.fonts.default_id().unwrap();
         let family = ctx.fonts.get(font_id).family.clone();
@@ -109,8 +152,7 @@ impl<M: Clone + 'static> Button<M> {
             .style
             .text_color
             .unwrap_or(Color::new(0.92, 0.92, 0.95, 1.0));
-        ctx.tr.draw(
-            &mut ctx.fonts.font_system,
+        ctx.list.text(
             family,
             size,
             400,
@@ -120,14 +162,13 @@ impl<M: Clone + 'static> Button<M> {
             tx,
             ty,
             w,
-            ctx.clip,
             with_opacity(label_color.to_array(), self.style.opacity).into(),
         );
 
-        check_interactions(&self.interactions, x, y, w, h, ctx);
+        check_interactions(&self.interactions, ctx);
     }
 
-    pub fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
+    fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
         let font_id = fonts.default_id().unwrap();
         let (tw, th) = fonts.measure(&self.label, font_id);
         let natural_w = tw + 24.0;
@@ -160,13 +201,47 @@ impl<M: Clone + 'static> Button<M> {
             .unwrap()
     }
 
-    pub fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
+    fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
         self.x = x;
         self.y = y;
         self.w = w;
         self.h = h;
     }
 
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        [self.x, self.y, self.w, self.h]
+    }
+
+    fn interactions(&self) -> Option<&Interactions<M>> {
+        Some(&self.interactions)
+    }
+
+    fn interactions_mut(&mut self) -> Option<&mut Interactions<M>> {
+        Some(&mut self.interactions)
+    }
+
+    // enter and space press a focused button like a click would
+    fn on_event(&mut self, event: &Event, ctx: &mut EventCtx<M>) -> bool {
+        match event {
+            Event::KeyPressed {
+                key: Key::Enter | Key::Space,
+                ..
+            } if ctx.focused => {
+                if let Some(on_click) = &self.interactions.on_click {
+                    ctx.actions.extend(on_click.fire());
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<M: Clone + 'static> Button<M> {
     // layout builder methods
     pub fn width(mut self, v: Val) -> Self {
         self.layout.width = v;
@@ -220,16 +295,26 @@ impl<M: Clone + 'static> Button<M> {
     }
 
     // interaction builder methods
+    // order Tab visits it in, see focus.rs. negative leaves it out
+    pub fn tab_index(mut self, index: i32) -> Self {
+        self.tab_index = index;
+        self
+    }
+
     pub fn on_click(mut self, msg: M) -> Self {
-        self.interactions.on_click = Some(msg);
+        self.interactions.on_click = Some(Handler::new(msg));
         self
     }
     pub fn on_hover(mut self, msg: M) -> Self {
-        self.interactions.on_hover = Some(msg);
+        self.interactions.on_hover = Some(Handler::new(msg));
+        self
+    }
+    pub fn stop_propagation(mut self) -> Self {
+        self.interactions.stop_propagation = true;
         self
     }
     pub fn on_mouse_down(mut self, msg: M) -> Self {
-        self.interactions.on_mouse_down = Some(msg);
+        self.interactions.on_mouse_down = Some(Handler::new(msg));
         self
     }
 }
@@ -4,33 +4,39 @@ use crate::draw::{
     DrawCtx, check_interactions, draw_element, draw_shadow, draw_shape, make_child_clip,
     with_opacity,
 };
+use crate::element::{Handler, Mapper};
 use crate::layout::{align_to_items, align_to_justify, build_taffy_node_pub, style_to_taffy};
+use crate::widgets::scroll::{Scroll, draw_scrollbars};
 use crate::{
-    Align, Color, Edges, Element, Fonts, Interactions, Layout, Margin, Overflow, Style, Val,
+    Align, Color, Edges, Element, Fonts, Interactions, Layout, Margin, Overflow, Style, Val, Widget,
 };
 
 // Row
 
 pub struct Row<M: Clone + 'static> {
     pub id: Option<String>,
+    pub key: Option<String>,
     pub layout: Layout,
     pub style: Style,
     pub interactions: Interactions<M>,
     pub children: Vec<Element<M>>,
     pub(crate) w: f32,
     pub(crate) h: f32,
+    pub(crate) scroll: Scroll,
 }
 
 impl<M: Clone + 'static> Row<M> {
     pub fn new(children: Vec<Element<M>>) -> Self {
         Self {
             id: None,
+            key: None,
             layout: Layout::default(),
             style: Style::default(),
             interactions: Interactions::default(),
             children,
             w: 0.0,
             h: 0.0,
+            scroll: Scroll::default(),
         }
     }
 
@@ -39,9 +45,30 @@ impl<M: Clone + 'static> Row<M> {
         self
     }
 
-    pub fn draw(&mut self, ctx: &mut DrawCtx<M>) {
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    pub(crate) fn map<B: Clone + 'static>(self, f: &Mapper<M, B>) -> Row<B> {
+        Row {
+            id: self.id,
+            key: self.key,
+            layout: self.layout,
+            style: self.style,
+            interactions: self.interactions.map(f),
+            children: self.children.into_iter().map(|c| c.map_with(f)).collect(),
+            w: self.w,
+            h: self.h,
+            scroll: self.scroll,
+        }
+    }
+}
+
+impl<M: Clone + 'static> Widget<M> for Row<M> {
+    fn draw(&mut self, ctx: &mut DrawCtx<M>) {
         let (x, y, w, h) = (self.layout.x, self.layout.y, self.w, self.h);
-        draw_shadow(ctx.shadow, x, y, w, h, &self.style);
+        draw_shadow(ctx.list, x, y, w, h, &self.style);
         if let Some(bg) = self.style.background {
             let border = self
                 .style
@@ -49,7 +76,7 @@ impl<M: Clone + 'static> Row<M> {
                 .unwrap_or(Color::TRANSPARENT)
                 .to_array();
             draw_shape(
-                ctx.sr,
+                ctx.list,
                 x,
                 y,
                 w,
@@ -58,16 +85,16 @@ impl<M: Clone + 'static> Row<M> {
                 self.style.border_radius,
                 with_opacity(border, self.style.opacity),
                 self.style.border_thickness,
-                ctx.clip,
             );
         }
-        check_interactions(&self.interactions, x, y, w, h, ctx);
         let child_clip = make_child_clip(x, y, w, h, self.layout.overflow, ctx.clip);
+        let clips = matches!(self.layout.overflow, Overflow::Hidden | Overflow::Scroll);
+        if clips {
+            ctx.list.push_clip([x, y, x + w, y + h]);
+        }
         for child in &mut self.children {
             let mut child_ctx = DrawCtx {
-                sr: ctx.sr,
-                shadow: ctx.shadow,
-                tr: ctx.tr,
+                list: ctx.list,
                 fonts: ctx.fonts,
                 state: ctx.state,
                 mouse: ctx.mouse,
@@ -78,9 +105,18 @@ impl<M: Clone + 'static> Row<M> {
             };
             draw_element(child, &mut child_ctx);
         }
+        if clips {
+            ctx.list.pop_clip();
+        }
+        if let (Overflow::Scroll, Some(id)) = (self.layout.overflow, &self.id) {
+            let hovered = self.interactions.hovered;
+            draw_scrollbars(ctx, id, [x, y, w, h], self.scroll, hovered);
+        }
+        // after the children so handlers run innermost first, the order events bubble in
+        check_interactions(&self.interactions, ctx);
     }
 
-    pub fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
+    fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
         let child_nodes: Vec<NodeId> = self
             .children
             .iter()
@@ -92,13 +128,31 @@ impl<M: Clone + 'static> Row<M> {
         taffy.new_with_children(ts, &child_nodes).unwrap()
     }
 
-    pub fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
+    fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
         self.layout.x = x;
         self.layout.y = y;
         self.w = w;
         self.h = h;
     }
 
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        [self.layout.x, self.layout.y, self.w, self.h]
+    }
+
+    fn interactions(&self) -> Option<&Interactions<M>> {
+        Some(&self.interactions)
+    }
+
+    fn interactions_mut(&mut self) -> Option<&mut Interactions<M>> {
+        Some(&mut self.interactions)
+    }
+}
+
+impl<M: Clone + 'static> Row<M> {
     // layout builder methods
     pub fn width(mut self, v: Val) -> Self {
         self.layout.width = v;
@@ -205,15 +259,19 @@ impl<M: Clone + 'static> Row<M> {
 
     // interaction builder methods
     pub fn on_click(mut self, msg: M) -> Self {
-        self.interactions.on_click = Some(msg);
+        self.interactions.on_click = Some(Handler::new(msg));
         self
     }
     pub fn on_hover(mut self, msg: M) -> Self {
-        self.interactions.on_hover = Some(msg);
+        self.interactions.on_hover = Some(Handler::new(msg));
+        self
+    }
+    pub fn stop_propagation(mut self) -> Self {
+        self.interactions.stop_propagation = true;
         self
     }
     pub fn on_mouse_down(mut self, msg: M) -> Self {
-        self.interactions.on_mouse_down = Some(msg);
+        self.interactions.on_mouse_down = Some(Handler::new(msg));
         self
     }
 }
@@ -222,24 +280,28 @@ impl<M: Clone + 'static> Row<M> {
 
 pub struct Column<M: Clone + 'static> {
     pub id: Option<String>,
+    pub key: Option<String>,
     pub layout: Layout,
     pub style: Style,
     pub interactions: Interactions<M>,
     pub children: Vec<Element<M>>,
     pub(crate) w: f32,
     pub(crate) h: f32,
+    pub(crate) scroll: Scroll,
 }
 
 impl<M: Clone + 'static> Column<M> {
     pub fn new(children: Vec<Element<M>>) -> Self {
         Self {
             id: None,
+            key: None,
             layout: Layout::default(),
             style: Style::default(),
             interactions: Interactions::default(),
             children,
             w: 0.0,
             h: 0.0,
+            scroll: Scroll::default(),
         }
     }
 
@@ -248,9 +310,30 @@ impl<M: Clone + 'static> Column<M> {
         self
     }
 
-    pub fn draw(&mut self, ctx: &mut DrawCtx<M>) {
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    pub(crate) fn map<B: Clone + 'static>(self, f: &Mapper<M, B>) -> Column<B> {
+        Column {
+            id: self.id,
+            key: self.key,
+            layout: self.layout,
+            style: self.style,
+            interactions: self.interactions.map(f),
+            children: self.children.into_iter().map(|c| c.map_with(f)).collect(),
+            w: self.w,
+            h: self.h,
+            scroll: self.scroll,
+        }
+    }
+}
+
+impl<M: Clone + 'static> Widget<M> for Column<M> {
+    fn draw(&mut self, ctx: &mut DrawCtx<M>) {
         let (x, y, w, h) = (self.layout.x, self.layout.y, self.w, self.h);
-        draw_shadow(ctx.shadow, x, y, w, h, &self.style);
+        draw_shadow(ctx.list, x, y, w, h, &self.style);
         if let Some(bg) = self.style.background {
             let border = self
                 .style
@@ -258,7 +341,7 @@ impl<M: Clone + 'static> Column<M> {
                 .unwrap_or(Color::TRANSPARENT)
                 .to_array();
             draw_shape(
-                ctx.sr,
+                ctx.list,
                 x,
                 y,
                 w,
@@ -267,16 +350,16 @@ impl<M: Clone + 'static> Column<M> {
                 self.style.border_radius,
                 with_opacity(border, self.style.opacity),
                 self.style.border_thickness,
-                ctx.clip,
             );
         }
-        check_interactions(&self.interactions, x, y, w, h, ctx);
         let child_clip = make_child_clip(x, y, w, h, self.layout.overflow, ctx.clip);
+        let clips = matches!(self.layout.overflow, Overflow::Hidden | Overflow::Scroll);
+        if clips {
+            ctx.list.push_clip([x, y, x + w, y + h]);
+        }
         for child in &mut self.children {
             let mut child_ctx = DrawCtx {
-                sr: ctx.sr,
-                shadow: ctx.shadow,
-                tr: ctx.tr,
+                list: ctx.list,
                 fonts: ctx.fonts,
                 state: ctx.state,
                 mouse: ctx.mouse,
@@ -287,9 +370,18 @@ impl<M: Clone + 'static> Column<M> {
             };
             draw_element(child, &mut child_ctx);
         }
+        if clips {
+            ctx.list.pop_clip();
+        }
+        if let (Overflow::Scroll, Some(id)) = (self.layout.overflow, &self.id) {
+            let hovered = self.interactions.hovered;
+            draw_scrollbars(ctx, id, [x, y, w, h], self.scroll, hovered);
+        }
+        // after the children so handlers run innermost first, the order events bubble in
+        check_interactions(&self.interactions, ctx);
     }
 
-    pub fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
+    fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
         let child_nodes: Vec<NodeId> = self
             .children
             .iter()
@@ -301,13 +393,31 @@ impl<M: Clone + 'static> Column<M> {
         taffy.new_with_children(ts, &child_nodes).unwrap()
     }
 
-    pub fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
+    fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
         self.layout.x = x;
         self.layout.y = y;
         self.w = w;
         self.h = h;
     }
 
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        [self.layout.x, self.layout.y, self.w, self.h]
+    }
+
+    fn interactions(&self) -> Option<&Interactions<M>> {
+        Some(&self.interactions)
+    }
+
+    fn interactions_mut(&mut self) -> Option<&mut Interactions<M>> {
+        Some(&mut self.interactions)
+    }
+}
+
+impl<M: Clone + 'static> Column<M> {
     // layout builder methods
     pub fn width(mut self, v: Val) -> Self {
         self.layout.width = v;
@@ -414,15 +524,19 @@ impl<M: Clone + 'static> Column<M> {
 
     // interaction builder methods
     pub fn on_click(mut self, msg: M) -> Self {
-        self.interactions.on_click = Some(msg);
+        self.interactions.on_click = Some(Handler::new(msg));
         self
     }
     pub fn on_hover(mut self, msg: M) -> Self {
-        self.interactions.on_hover = Some(msg);
+        self.interactions.on_hover = Some(Handler::new(msg));
+        self
+    }
+    pub fn stop_propagation(mut self) -> Self {
+        self.interactions.stop_propagation = true;
         self
     }
     pub fn on_mouse_down(mut self, msg: M) -> Self {
-        self.interactions.on_mouse_down = Some(msg);
+        self.interactions.on_mouse_down = Some(Handler::new(msg));
         self
     }
 }
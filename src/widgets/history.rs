@@ -0,0 +1,64 @@
+// History
+// undo / redo for the text widgets, kept in the state store next to their own state
+// under the same id. each step is a snapshot of the value, cursor and selection from
+// before an edit. a run of typing (or of deleting) coalesces into one step as long as
+// each edit starts where the previous one left the cursor. the input's value is a
+// String, the editor's a rope, whose snapshots share their text instead of copying it
+
+// steps kept per widget, the oldest fall off first
+const LIMIT: usize = 200;
+
+#[derive(Clone)]
+pub(crate) struct Snapshot<T = String> {
+    pub(crate) value: T,
+    pub(crate) cursor: usize,
+    pub(crate) anchor: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum EditKind {
+    Insert,
+    Delete,
+    // cut, paste, replacing a selection: always a step of its own
+    Other,
+}
+
+#[derive(Default)]
+pub(crate) struct History<T = String> {
+    undo: Vec<Snapshot<T>>,
+    redo: Vec<Snapshot<T>>,
+    // what the last recorded edit was and where it left the cursor, for coalescing
+    last: Option<(EditKind, usize)>,
+}
+
+impl<T> History<T> {
+    // called after an edit with the state from before it
+    pub(crate) fn record(&mut self, before: Snapshot<T>, kind: EditKind, cursor_after: usize) {
+        let continues = kind != EditKind::Other
+            && before.anchor.is_none_or(|a| a == before.cursor)
+            && self.last == Some((kind, before.cursor));
+        if !continues {
+            self.undo.push(before);
+            if self.undo.len() > LIMIT {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+        self.last = Some((kind, cursor_after));
+    }
+
+    // the snapshot to go back to, with `current` kept for redo
+    pub(crate) fn undo(&mut self, current: Snapshot<T>) -> Option<Snapshot<T>> {
+        let snapshot = self.undo.pop()?;
+        self.redo.push(current);
+        self.last = None;
+        Some(snapshot)
+    }
+
+    pub(crate) fn redo(&mut self, current: Snapshot<T>) -> Option<Snapshot<T>> {
+        let snapshot = self.redo.pop()?;
+        self.undo.push(current);
+        self.last = None;
+        Some(snapshot)
+    }
+}
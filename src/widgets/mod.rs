@@ -1,6 +1,10 @@
 pub mod button;
 pub mod containers;
+pub mod history;
+pub mod overlay;
 pub mod rect;
+pub mod scroll;
 pub mod text;
 pub mod text_editor;
 pub mod text_input;
+pub(crate) mod text_util;
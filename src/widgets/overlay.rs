@@ -0,0 +1,171 @@
+use taffy::prelude::*;
+
+use crate::draw::{DrawCtx, draw_element};
+use crate::element::{Element, Mapper};
+
+// Overlay
+// content that sits above the whole tree: dropdowns, tooltips, modals.
+// takes no space where it's declared, is laid out on its own against the window,
+// drawn after all normal content without any parent clip, and hit-tested first
+
+#[derive(Clone, PartialEq)]
+pub enum Anchor {
+    // laid out at the window origin with the window as available space
+    Window,
+    // placed next to the element with this id
+    Element(String),
+}
+
+// which side of the anchor element the overlay goes on
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Placement {
+    #[default]
+    Below,
+    Above,
+    Left,
+    Right,
+    // top left corners line up, covering the anchor
+    Over,
+}
+
+pub struct Overlay<M: Clone + 'static> {
+    pub child: Box<Element<M>>,
+    pub anchor: Anchor,
+    pub placement: Placement,
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub z_index: i32,
+    // resolved rect of the child, written by the overlay layout pass
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) w: f32,
+    pub(crate) h: f32,
+    // false when the anchor element wasn't found, nothing is drawn then
+    pub(crate) placed: bool,
+}
+
+impl<M: Clone + 'static> Overlay<M> {
+    pub fn new(child: Element<M>) -> Self {
+        Self {
+            child: Box::new(child),
+            anchor: Anchor::Window,
+            placement: Placement::Below,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            z_index: 0,
+            x: 0.0,
+            y: 0.0,
+            w: 0.0,
+            h: 0.0,
+            placed: false,
+        }
+    }
+
+    // the same overlay with its actions passed through f, see Element::map
+    pub(crate) fn map<B: Clone + 'static>(self, f: &Mapper<M, B>) -> Overlay<B> {
+        Overlay {
+            child: Box::new(self.child.map_with(f)),
+            anchor: self.anchor,
+            placement: self.placement,
+            offset_x: self.offset_x,
+            offset_y: self.offset_y,
+            z_index: self.z_index,
+            x: self.x,
+            y: self.y,
+            w: self.w,
+            h: self.h,
+            placed: self.placed,
+        }
+    }
+
+    pub fn anchor(mut self, id: &str) -> Self {
+        self.anchor = Anchor::Element(id.to_string());
+        self
+    }
+
+    pub fn placement(mut self, placement: Placement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    pub fn offset(mut self, x: f32, y: f32) -> Self {
+        self.offset_x = x;
+        self.offset_y = y;
+        self
+    }
+
+    pub fn z_index(mut self, z: i32) -> Self {
+        self.z_index = z;
+        self
+    }
+
+    // where it's declared it's an empty, sizeless node
+    pub fn layout_node(&self, taffy: &mut TaffyTree<()>) -> NodeId {
+        taffy
+            .new_leaf(taffy::Style {
+                display: Display::None,
+                ..Default::default()
+            })
+            .unwrap()
+    }
+
+    // top left corner for a child of size w x h, given the anchor's rect.
+    // flips to the opposite side when the preferred one runs off the window,
+    // then clamps so as much as possible stays on screen
+    pub(crate) fn place(
+        &self,
+        anchor: Option<[f32; 4]>,
+        w: f32,
+        h: f32,
+        window_w: f32,
+        window_h: f32,
+    ) -> (f32, f32) {
+        let Some([ax, ay, aw, ah]) = anchor else {
+            return (self.offset_x, self.offset_y);
+        };
+        let (ox, oy) = (self.offset_x, self.offset_y);
+        let (x, y) = match self.placement {
+            Placement::Below => {
+                let y = ay + ah + oy;
+                if y + h > window_h && ay - h - oy >= 0.0 {
+                    (ax + ox, ay - h - oy)
+                } else {
+                    (ax + ox, y)
+                }
+            }
+            Placement::Above => {
+                let y = ay - h - oy;
+                if y < 0.0 && ay + ah + oy + h <= window_h {
+                    (ax + ox, ay + ah + oy)
+                } else {
+                    (ax + ox, y)
+                }
+            }
+            Placement::Right => {
+                let x = ax + aw + ox;
+                if x + w > window_w && ax - w - ox >= 0.0 {
+                    (ax - w - ox, ay + oy)
+                } else {
+                    (x, ay + oy)
+                }
+            }
+            Placement::Left => {
+                let x = ax - w - ox;
+                if x < 0.0 && ax + aw + ox + w <= window_w {
+                    (ax + aw + ox, ay + oy)
+                } else {
+                    (x, ay + oy)
+                }
+            }
+            Placement::Over => (ax + ox, ay + oy),
+        };
+        (x.min(window_w - w).max(0.0), y.min(window_h - h).max(0.0))
+    }
+
+    pub fn draw(&mut self, ctx: &mut DrawCtx<M>) {
+        if !self.placed {
+            return;
+        }
+        draw_element(&mut self.child, ctx);
+    }
+}
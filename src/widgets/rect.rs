@@ -1,11 +1,13 @@
 use taffy::prelude::*;
 
 use crate::draw::{DrawCtx, check_interactions, draw_shadow, draw_shape, is_outside, with_opacity};
-use crate::layout::{margin_to_rect_lpa, style_to_taffy};
-use crate::{Color, Edges, Fonts, Interactions, Layout, Margin, Overflow, Style, Val};
+use crate::element::{Handler, Mapper};
+use crate::layout::style_to_taffy;
+use crate::{Color, Edges, Fonts, Interactions, Layout, Margin, Overflow, Style, Val, Widget};
 
 pub struct Rect<M: Clone + 'static> {
     pub id: Option<String>,
+    pub key: Option<String>,
     pub color: Color,
     pub layout: Layout,
     pub style: Style,
@@ -18,6 +20,7 @@ impl<M: Clone + 'static> Rect<M> {
     pub fn new(color: Color) -> Self {
         Self {
             id: None,
+            key: None,
             color,
             layout: Layout::default(),
             style: Style::default(),
@@ -32,19 +35,39 @@ impl<M: Clone + 'static> Rect<M> {
         self
     }
 
-    pub fn draw(&mut self, ctx: &mut DrawCtx<M>) {
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    pub(crate) fn map<B: Clone + 'static>(self, f: &Mapper<M, B>) -> Rect<B> {
+        Rect {
+            id: self.id,
+            key: self.key,
+            color: self.color,
+            layout: self.layout,
+            style: self.style,
+            interactions: self.interactions.map(f),
+            w: self.w,
+            h: self.h,
+        }
+    }
+}
+
+impl<M: Clone + 'static> Widget<M> for Rect<M> {
+    fn draw(&mut self, ctx: &mut DrawCtx<M>) {
         let (x, y, w, h) = (self.layout.x, self.layout.y, self.w, self.h);
         if is_outside(x, y, w, h, ctx.clip) {
             return;
         }
-        draw_shadow(ctx.shadow, x, y, w, h, &self.style);
+        draw_shadow(ctx.list, x, y, w, h, &self.style);
         let border = self
             .style
             .border_color
             .unwrap_or(Color::TRANSPARENT)
             .to_array();
         draw_shape(
-            ctx.sr,
+            ctx.list,
             x,
             y,
             w,
@@ -53,25 +76,42 @@ impl<M: Clone + 'static> Rect<M> {
             self.style.border_radius,
             with_opacity(border, self.style.opacity),
             self.style.border_thickness,
-            ctx.clip,
         );
-        check_interactions(&self.interactions, x, y, w, h, ctx);
+        check_interactions(&self.interactions, ctx);
     }
 
-    pub fn layout_node(&self, taffy: &mut TaffyTree<()>, _fonts: &mut Fonts) -> NodeId {
+    fn layout_node(&self, taffy: &mut TaffyTree<()>, _fonts: &mut Fonts) -> NodeId {
         let mut ts = style_to_taffy(&self.layout, FlexDirection::Row);
         ts.justify_content = None;
         ts.align_items = None;
         taffy.new_leaf(ts).unwrap()
     }
 
-    pub fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
+    fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
         self.layout.x = x;
         self.layout.y = y;
         self.w = w;
         self.h = h;
     }
 
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        [self.layout.x, self.layout.y, self.w, self.h]
+    }
+
+    fn interactions(&self) -> Option<&Interactions<M>> {
+        Some(&self.interactions)
+    }
+
+    fn interactions_mut(&mut self) -> Option<&mut Interactions<M>> {
+        Some(&mut self.interactions)
+    }
+}
+
+impl<M: Clone + 'static> Rect<M> {
     // layout builder methods
     pub fn width(mut self, v: Val) -> Self {
         self.layout.width = v;
@@ -163,15 +203,19 @@ impl<M: Clone + 'static> Rect<M> {
     }
 
     pub fn on_click(mut self, msg: M) -> Self {
-        self.interactions.on_click = Some(msg);
+        self.interactions.on_click = Some(Handler::new(msg));
         self
     }
     pub fn on_hover(mut self, msg: M) -> Self {
-        self.interactions.on_hover = Some(msg);
+        self.interactions.on_hover = Some(Handler::new(msg));
+        self
+    }
+    pub fn stop_propagation(mut self) -> Self {
+        self.interactions.stop_propagation = true;
         self
     }
     pub fn on_mouse_down(mut self, msg: M) -> Self {
-        self.interactions.on_mouse_down = Some(msg);
+        self.interactions.on_mouse_down = Some(Handler::new(msg));
         self
     }
 }
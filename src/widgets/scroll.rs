@@ -0,0 +1,275 @@
+use crate::draw::{DrawCtx, draw_shape};
+use crate::state::StateStore;
+use crate::{Color, Element};
+
+// Scroll
+// shared by Row and Column with overflow_scroll(). the offset lives in the StateStore
+// under the container's id, the layout pass clamps it to what taffy says overflows
+// and moves the children up/left by it. wheel deltas go to the innermost hovered
+// container that can still move that way, and each axis that overflows gets a
+// draggable scrollbar along the far edge
+
+// how far one wheel notch moves, for mice that report lines instead of pixels
+pub(crate) const LINE_HEIGHT: f32 = 40.0;
+
+// thickness of the strip the scrollbar lives in, the thumb is drawn inset in it
+pub(crate) const BAR: f32 = 8.0;
+const MIN_THUMB: f32 = 20.0;
+
+#[derive(Default)]
+pub struct ScrollState {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    // dragging a thumb: which axis (0 = x, 1 = y) and where on the thumb it was grabbed
+    drag: Option<(usize, f32)>,
+}
+
+// the resolved scroll of one container, written by the layout pass
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Scroll {
+    pub(crate) offset: [f32; 2],
+    pub(crate) max: [f32; 2],
+}
+
+impl Scroll {
+    // track and thumb along one axis as (track_start, track_len, thumb_start, thumb_len),
+    // None when that axis doesn't overflow
+    fn thumb(&self, axis: usize, rect: [f32; 4]) -> Option<(f32, f32, f32, f32)> {
+        let max = self.max[axis];
+        if max <= 0.0 {
+            return None;
+        }
+        let [x, y, w, h] = rect;
+        // leave the corner free when both bars are showing
+        let other = if self.max[1 - axis] > 0.0 { BAR } else { 0.0 };
+        let (start, len) = if axis == 0 {
+            (x, w - other)
+        } else {
+            (y, h - other)
+        };
+        let visible = if axis == 0 { w } else { h };
+        let thumb = (len * visible / (visible + max)).max(MIN_THUMB).min(len);
+        let pos = start + (len - thumb) * self.offset[axis] / max;
+        Some((start, len, pos, thumb))
+    }
+
+    // the strip a scrollbar takes up along the far edge, if that axis overflows
+    fn bar_rect(&self, axis: usize, rect: [f32; 4]) -> Option<[f32; 4]> {
+        let (start, len, _, _) = self.thumb(axis, rect)?;
+        let [x, y, w, h] = rect;
+        Some(if axis == 0 {
+            [start, y + h - BAR, len, BAR]
+        } else {
+            [x + w - BAR, start, BAR, len]
+        })
+    }
+
+    // is the point on one of the scrollbars, they sit above the children
+    pub(crate) fn on_bar(&self, rect: [f32; 4], px: f32, py: f32) -> bool {
+        (0..2).any(|axis| {
+            self.bar_rect(axis, rect)
+                .is_some_and(|[x, y, w, h]| px >= x && px <= x + w && py >= y && py <= y + h)
+        })
+    }
+}
+
+// clamps the stored offset to what overflows and returns it. called from layout
+pub(crate) fn resolve(state: &mut StateStore, id: &str, max: [f32; 2]) -> Scroll {
+    let s = state.get_or_default_mut::<ScrollState>(id);
+    s.offset_x = s.offset_x.clamp(0.0, max[0]);
+    s.offset_y = s.offset_y.clamp(0.0, max[1]);
+    Scroll {
+        offset: [s.offset_x, s.offset_y],
+        max,
+    }
+}
+
+// thumbs, plus dragging them. hovered is the container's own hover state
+pub(crate) fn draw_scrollbars<M: Clone + 'static>(
+    ctx: &mut DrawCtx<M>,
+    id: &str,
+    rect: [f32; 4],
+    scroll: Scroll,
+    hovered: bool,
+) {
+    let (mx, my) = (ctx.mouse.x, ctx.mouse.y);
+    let mut offset = scroll.offset;
+
+    for axis in 0..2 {
+        let Some((start, len, pos, thumb)) = scroll.thumb(axis, rect) else {
+            continue;
+        };
+        let along = if axis == 0 { mx } else { my };
+        let s = ctx.state.get_or_default_mut::<ScrollState>(id);
+
+        if ctx.mouse.left_just_pressed && hovered {
+            let [bx, by, bw, bh] = scroll.bar_rect(axis, rect).unwrap();
+            if mx >= bx && mx <= bx + bw && my >= by && my <= by + bh {
+                // grabbing the track outside the thumb centers the thumb there first
+                let grab = if along >= pos && along <= pos + thumb {
+                    along - pos
+                } else {
+                    thumb / 2.0
+                };
+                s.drag = Some((axis, grab));
+            }
+        }
+        if !ctx.mouse.left_pressed {
+            s.drag = None;
+        }
+
+        let active = matches!(s.drag, Some((a, _)) if a == axis);
+        if let Some((_, grab)) = s.drag.filter(|_| active) {
+            let t = ((along - grab - start) / (len - thumb).max(1.0)).clamp(0.0, 1.0);
+            offset[axis] = t * scroll.max[axis];
+            if axis == 0 {
+                s.offset_x = offset[0];
+            } else {
+                s.offset_y = offset[1];
+            }
+        }
+
+        let pos = start + (len - thumb) * offset[axis] / scroll.max[axis];
+        let [x, y, w, h] = rect;
+        let (tx, ty, tw, th) = if axis == 0 {
+            (pos, y + h - BAR + 2.0, thumb, BAR - 4.0)
+        } else {
+            (x + w - BAR + 2.0, pos, BAR - 4.0, thumb)
+        };
+        let color = if active {
+            Color::new(0.75, 0.75, 0.8, 0.8)
+        } else if hovered {
+            Color::new(0.65, 0.65, 0.7, 0.6)
+        } else {
+            Color::new(0.55, 0.55, 0.6, 0.4)
+        };
+        draw_shape(
+            ctx.list,
+            tx,
+            ty,
+            tw,
+            th,
+            color.to_array(),
+            (BAR - 4.0) / 2.0,
+            Color::TRANSPARENT.to_array(),
+            0.0,
+        );
+    }
+}
+
+// hands a wheel delta to the innermost hovered scroll container that can move that
+// way. positive deltas scroll towards the start, like winit reports them.
+// returns true if anything moved
+pub(crate) fn wheel<M: Clone + 'static>(
+    el: &mut Element<M>,
+    state: &mut StateStore,
+    dx: f32,
+    dy: f32,
+) -> bool {
+    let (children, id, scroll, hovered) = match el {
+        Element::Row(r) => (&mut r.children, &r.id, r.scroll, r.interactions.hovered),
+        Element::Column(c) => (&mut c.children, &c.id, c.scroll, c.interactions.hovered),
+        Element::Overlay(o) => return wheel(&mut o.child, state, dx, dy),
+        _ => return false,
+    };
+    // overlays can sit anywhere in the tree, so every child is visited, not just hovered ones
+    for child in children.iter_mut() {
+        if wheel(child, state, dx, dy) {
+            return true;
+        }
+    }
+    let (Some(id), true) = (id, hovered) else {
+        return false;
+    };
+    let x = (scroll.offset[0] - dx).clamp(0.0, scroll.max[0]);
+    let y = (scroll.offset[1] - dy).clamp(0.0, scroll.max[1]);
+    if x == scroll.offset[0] && y == scroll.offset[1] {
+        return false;
+    }
+    let s = state.get_or_default_mut::<ScrollState>(id);
+    s.offset_x = x;
+    s.offset_y = y;
+    true
+}
+
+pub(crate) fn scroll_to(state: &mut StateStore, id: &str, x: f32, y: f32) {
+    let s = state.get_or_default_mut::<ScrollState>(id);
+    s.offset_x = x;
+    s.offset_y = y;
+}
+
+enum Found {
+    No,
+    // the element's rect, still looking for a scroll container to bring it into
+    At([f32; 4]),
+    Done,
+}
+
+// moves the nearest scroll container around the element with this id just enough
+// to show it whole (or its top left, if it's bigger than the container).
+// returns true if anything moved
+pub(crate) fn scroll_into_view<M: Clone + 'static>(
+    el: &Element<M>,
+    state: &mut StateStore,
+    target: &str,
+) -> bool {
+    matches!(find(el, state, target), Found::Done)
+}
+
+fn find<M: Clone + 'static>(el: &Element<M>, state: &mut StateStore, target: &str) -> Found {
+    let (id, children, rect, scroll) = match el {
+        Element::Row(r) => (
+            &r.id,
+            &r.children,
+            [r.layout.x, r.layout.y, r.w, r.h],
+            r.scroll,
+        ),
+        Element::Column(c) => (
+            &c.id,
+            &c.children,
+            [c.layout.x, c.layout.y, c.w, c.h],
+            c.scroll,
+        ),
+        Element::Overlay(o) => return find(&o.child, state, target),
+        other => {
+            return match crate::layout::element_rect(other) {
+                Some((Some(id), r)) if id == target => Found::At(r),
+                _ => Found::No,
+            };
+        }
+    };
+    if id.as_deref() == Some(target) {
+        return Found::At(rect);
+    }
+
+    let mut found = Found::No;
+    for child in children {
+        found = find(child, state, target);
+        if !matches!(found, Found::No) {
+            break;
+        }
+    }
+    let Found::At([ex, ey, ew, eh]) = found else {
+        return found;
+    };
+    let Some(id) = id.as_deref().filter(|_| scroll.max != [0.0, 0.0]) else {
+        return found;
+    };
+
+    // how far the element sticks out past each edge, in content coordinates
+    let [x, y, w, h] = rect;
+    let fit = |offset: f32, start: f32, len: f32, el_start: f32, el_len: f32, max: f32| {
+        let mut o = offset;
+        if el_start + el_len > start + len {
+            o += el_start + el_len - (start + len);
+        }
+        if el_start < start {
+            o -= start - el_start;
+        }
+        o.clamp(0.0, max)
+    };
+    let s = state.get_or_default_mut::<ScrollState>(id);
+    s.offset_x = fit(scroll.offset[0], x, w, ex, ew, scroll.max[0]);
+    s.offset_y = fit(scroll.offset[1], y, h, ey, eh, scroll.max[1]);
+    Found::Done
+}
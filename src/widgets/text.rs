@@ -1,11 +1,13 @@
 use taffy::prelude::*;
 
 use crate::draw::{DrawCtx, is_outside};
+use crate::element::Mapper;
 use crate::layout::{align_to_self, margin_to_rect_lpa};
-use crate::{Align, Color, Fonts, Interactions, Layout, Margin, Style, TextAlign, Val};
+use crate::{Align, Color, Fonts, Interactions, Layout, Margin, Style, TextAlign, Val, Widget};
 
 pub struct Text<M: Clone + 'static> {
     pub id: Option<String>,
+    pub key: Option<String>,
     pub content: String,
     pub color: Color,
     pub font: Option<String>,
@@ -17,12 +19,14 @@ pub struct Text<M: Clone + 'static> {
     pub style: Style,
     pub interactions: Interactions<M>,
     pub(crate) w: f32,
+    pub(crate) h: f32,
 }
 
 impl<M: Clone + 'static> Text<M> {
     pub fn new(content: &str, color: Color) -> Self {
         Self {
             id: None,
+            key: None,
             content: content.to_string(),
             color,
             font: None,
@@ -34,6 +38,7 @@ impl<M: Clone + 'static> Text<M> {
             style: Style::default(),
             interactions: Interactions::default(),
             w: 0.0,
+            h: 0.0,
         }
     }
 
@@ -42,7 +47,33 @@ impl<M: Clone + 'static> Text<M> {
         self
     }
 
-    pub fn draw(&mut self, ctx: &mut DrawCtx<M>) {
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    pub(crate) fn map<B: Clone + 'static>(self, f: &Mapper<M, B>) -> Text<B> {
+        Text {
+            id: self.id,
+            key: self.key,
+            content: self.content,
+            color: self.color,
+            font: self.font,
+            font_size: self.font_size,
+            font_weight: self.font_weight,
+            italic: self.italic,
+            text_align: self.text_align,
+            layout: self.layout,
+            style: self.style,
+            interactions: self.interactions.map(f),
+            w: self.w,
+            h: self.h,
+        }
+    }
+}
+
+impl<M: Clone + 'static> Widget<M> for Text<M> {
+    fn draw(&mut self, ctx: &mut DrawCtx<M>) {
         if is_outside(self.layout.x, self.layout.y, self.w, 999.0, ctx.clip) {
             return;
         }
@@ -51,18 +82,9 @@ impl<M: Clone + 'static> Text<M> {
         let size = self.font_size.unwrap_or(ctx.fonts.get(font_id).size);
 
         let x2 = self.layout.x + self.w;
-        let text_clip = Some(match ctx.clip {
-            Some([cx, cy, cx2, cy2]) => [
-                self.layout.x.max(cx),
-                self.layout.y.max(cy),
-                x2.min(cx2),
-                (self.layout.y + 9999.0).min(cy2),
-            ],
-            None => [self.layout.x, self.layout.y, x2, self.layout.y + 9999.0],
-        });
-
-        ctx.tr.draw(
-            &mut ctx.fonts.font_system,
+        ctx.list
+            .push_clip([self.layout.x, self.layout.y, x2, self.layout.y + 9999.0]);
+        ctx.list.text(
             family,
             size,
             self.font_weight,
@@ -72,17 +94,16 @@ impl<M: Clone + 'static> Text<M> {
             self.layout.x,
             self.layout.y,
             99999.0,
-            text_clip,
             self.color,
         );
+        ctx.list.pop_clip();
     }
 
-    pub fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
+    fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
         let font_id = fonts.resolve(self.font.as_deref()).unwrap();
-        let (w, h) = match self.font_size {
-            Some(size) => fonts.measure_sized(&self.content, font_id, size, self.font_weight),
-            None => fonts.measure(&self.content, font_id),
-        };
+        // measured as it's drawn, at the font's own size unless it has one
+        let size = self.font_size.unwrap_or(fonts.get(font_id).size);
+        let (w, h) = fonts.measure_sized(&self.content, font_id, size, self.font_weight);
         taffy
             .new_leaf(taffy::Style {
                 size: taffy::geometry::Size {
@@ -106,12 +127,31 @@ impl<M: Clone + 'static> Text<M> {
             .unwrap()
     }
 
-    pub fn apply_layout(&mut self, x: f32, y: f32, w: f32, _h: f32) {
+    fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
         self.layout.x = x;
         self.layout.y = y;
         self.w = w;
+        self.h = h;
+    }
+
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
     }
 
+    fn bounds(&self) -> [f32; 4] {
+        [self.layout.x, self.layout.y, self.w, self.h]
+    }
+
+    fn interactions(&self) -> Option<&Interactions<M>> {
+        Some(&self.interactions)
+    }
+
+    fn interactions_mut(&mut self) -> Option<&mut Interactions<M>> {
+        Some(&mut self.interactions)
+    }
+}
+
+impl<M: Clone + 'static> Text<M> {
     // layout builder methods
     pub fn width(mut self, v: Val) -> Self {
         self.layout.width = v;
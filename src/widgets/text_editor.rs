@@ -1,10 +1,28 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+use ropey::Rope;
 use taffy::prelude::*;
 
+use crate::clipboard::Clipboard;
 use crate::draw::{DrawCtx, check_interactions, is_outside, with_opacity};
+use crate::element::{Callback, Handler, Mapper, callback, map_handler};
 use crate::events::{Event, Key};
+use crate::focus::{self, FocusKind};
+use crate::ime;
 use crate::layout::{align_to_self, margin_to_rect_lpa, val_to_dimension};
 use crate::state::StateStore;
-use crate::{Align, Color, Edges, Fonts, Interactions, Layout, Margin, Style, TextAlign, Val};
+use crate::widgets::history::{EditKind, History, Snapshot};
+use crate::widgets::text_util::{
+    Chars, move_cursor, next_char, next_word, prev_char, prev_word, selection_range, span,
+    word_end, word_start,
+};
+use crate::{
+    Align, Color, Edges, Fonts, Interactions, Layout, Margin, SpanStyle, Style, TextAlign, Val,
+    Widget,
+};
 
 // ─── persisted state ──────────────────────────────────────────────────────────
 
@@ -15,25 +33,269 @@ pub struct TextEditorState {
     pub scroll_offset: f32,
     pub selection_anchor: Option<usize>,
     pub dragging: bool,
-    pub cached_value: String,
+    // the text being edited: the app's value as of the last draw plus any edits since
+    pub buffer: Rope,
+    // the widget's revision of its value as of the last draw, see TextEditor::revision
+    pub(crate) value_revision: Option<u64>,
+    // how many rows fit in the editor, for PageUp / PageDown
+    pub(crate) page_lines: usize,
+    // where the buffer's lines break into rows
+    pub(crate) rows: Rows,
+    // highlight spans of the lines last in view, by their text
+    pub(crate) highlights: HashMap<String, Spans>,
+    // the widget's indent unit as of the last draw, None when Tab isn't for indenting
+    pub(crate) indent: Option<String>,
+    // the widget's search and its matches, and find and replace tasks waiting for the
+    // next draw to run against them
+    pub(crate) search: Option<Searching>,
+    pub(crate) search_ops: Vec<SearchOp>,
+}
+
+impl TextEditorState {
+    // an edit replaced the bytes start..end of old, the buffer before it, with text.
+    // the lines it touched are searched again now and wrapped again on the next draw
+    fn edited(&mut self, old: &Rope, start: usize, end: usize, text: &str) {
+        self.rows.edited(old, start, end, text);
+        if let Some(search) = &mut self.search {
+            search.edited(old, &self.buffer, start, end, text);
+        }
+    }
+}
+
+type Spans = Vec<(Range<usize>, SpanStyle)>;
+type Highlighter = Box<dyn Fn(&str) -> Spans>;
+
+pub(crate) enum TextEditorCallback<M> {
+    Change(Callback<String, M>),
+    Edit(Callback<TextEdit, M>),
+}
+
+// one change to a text editor's value, passed to on_edit: the bytes in range of the
+// value before it were replaced by text
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    // makes the same change to a copy of the value
+    pub fn apply(&self, value: &mut String) {
+        value.replace_range(self.range.clone(), &self.text);
+    }
 }
 
-pub(crate) struct TextEditorCallback<M>(pub Box<dyn Fn(String) -> M>);
+// what TextEditor::search looks for
+#[derive(Clone, Debug, PartialEq)]
+pub enum Search {
+    // the text as it's written
+    Text(String),
+    // the text in any case
+    IgnoreCase(String),
+    // a regular expression, ^ and $ match at line ends and a match doesn't span lines.
+    // replacements can use its groups as $1 or $name
+    Regex(String),
+}
+
+// how a search is going, passed to on_matches: how many matches there are and which
+// of them is selected, if one is
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Matches {
+    pub count: usize,
+    pub current: Option<usize>,
+}
+
+// find and replace tasks, see Task::find_next
+#[derive(Clone, Debug)]
+pub(crate) enum SearchOp {
+    Next,
+    Previous,
+    Replace(String),
+    ReplaceAll(String),
+}
+
+// a search, compiled, and what it found in the buffer it last looked at. it's run a
+// line at a time, and after an edit only over the lines the edit touched. an invalid
+// regex finds nothing
+pub(crate) struct Searching {
+    query: Search,
+    regex: Option<Regex>,
+    // per line, its matches as byte ranges into the line, in order and not overlapping.
+    // empty matches are left out
+    lines: Vec<Vec<Range<usize>>>,
+    count: usize,
+    // what on_matches was last told
+    reported: Option<Matches>,
+}
+
+impl Searching {
+    fn new(query: Search) -> Self {
+        let regex = match &query {
+            Search::Text(text) => Regex::new(&regex::escape(text)).ok(),
+            Search::IgnoreCase(text) => RegexBuilder::new(&regex::escape(text))
+                .case_insensitive(true)
+                .build()
+                .ok(),
+            Search::Regex(pattern) => RegexBuilder::new(pattern).multi_line(true).build().ok(),
+        };
+        Self {
+            query,
+            regex,
+            lines: Vec::new(),
+            count: 0,
+            reported: None,
+        }
+    }
+
+    // searches the whole buffer, if it hasn't been yet or was replaced rather than edited
+    fn find(&mut self, buf: &Rope) {
+        if self.lines.len() == buf.len_lines() {
+            return;
+        }
+        self.lines = (0..buf.len_lines())
+            .map(|line| self.search_line(buf, line))
+            .collect();
+        self.count = self.lines.iter().map(Vec::len).sum();
+    }
+
+    fn search_line(&self, buf: &Rope, line: usize) -> Vec<Range<usize>> {
+        let Some(regex) = &self.regex else {
+            return Vec::new();
+        };
+        let (start, end) = line_range(buf, line);
+        let text = Cow::from(buf.byte_slice(start..end));
+        regex
+            .find_iter(&text)
+            .map(|m| m.range())
+            .filter(|m| !m.is_empty())
+            .collect()
+    }
+
+    // an edit replaced the bytes start..end of old with text, making buf: the lines it
+    // touched are searched again
+    fn edited(&mut self, old: &Rope, buf: &Rope, start: usize, end: usize, text: &str) {
+        // not searched yet, find does the whole buffer
+        if self.lines.len() != old.len_lines() {
+            return;
+        }
+        let first = old.byte_to_line(start);
+        let touched = first..=old.byte_to_line(end);
+        let found: Vec<_> = (first..=first + text.matches('\n').count())
+            .map(|line| self.search_line(buf, line))
+            .collect();
+        self.count += found.iter().map(Vec::len).sum::<usize>();
+        let removed = self.lines.splice(touched, found);
+        self.count -= removed.map(|m| m.len()).sum::<usize>();
+    }
+
+    // a line's matches as byte ranges into the buffer
+    fn on_line(&self, buf: &Rope, line: usize) -> impl DoubleEndedIterator<Item = Range<usize>> {
+        let found = self.lines.get(line).map_or(&[][..], Vec::as_slice);
+        let start = if found.is_empty() {
+            0
+        } else {
+            buf.line_to_byte(line)
+        };
+        found.iter().map(move |m| start + m.start..start + m.end)
+    }
+
+    // the matches that overlap from..=to
+    fn between(&self, buf: &Rope, from: usize, to: usize) -> Vec<Range<usize>> {
+        (buf.byte_to_line(from)..=buf.byte_to_line(to))
+            .flat_map(|line| self.on_line(buf, line))
+            .filter(|m| m.end > from && m.start <= to)
+            .collect()
+    }
+
+    fn all(&self, buf: &Rope) -> Vec<Range<usize>> {
+        (0..self.lines.len())
+            .flat_map(|line| self.on_line(buf, line))
+            .collect()
+    }
+
+    fn contains(&self, buf: &Rope, m: Range<usize>) -> bool {
+        self.on_line(buf, buf.byte_to_line(m.start))
+            .any(|found| found == m)
+    }
+
+    // the first match starting at or after from, or the first of all
+    fn next(&self, buf: &Rope, from: usize) -> Option<Range<usize>> {
+        (buf.byte_to_line(from)..self.lines.len())
+            .flat_map(|line| self.on_line(buf, line))
+            .find(|m| m.start >= from)
+            .or_else(|| self.all(buf).into_iter().next())
+    }
+
+    // the last match ending at or before to, or the last of all
+    fn previous(&self, buf: &Rope, to: usize) -> Option<Range<usize>> {
+        let last = self.lines.len();
+        (0..=buf.byte_to_line(to))
+            .rev()
+            .flat_map(|line| self.on_line(buf, line).rev())
+            .find(|m| m.end <= to)
+            .or_else(|| {
+                (0..last)
+                    .rev()
+                    .flat_map(|line| self.on_line(buf, line).rev())
+                    .next()
+            })
+    }
+
+    // what a match is replaced with, with a regex's groups filled in
+    fn replacement(&self, buf: &Rope, m: Range<usize>, with: &str) -> String {
+        match (&self.query, &self.regex) {
+            (Search::Regex(_), Some(regex)) => {
+                let (start, end) = line_range(buf, buf.byte_to_line(m.start));
+                let line = Cow::from(buf.byte_slice(start..end));
+                let mut out = String::new();
+                if let Some(caps) = regex.captures_at(&line, m.start - start) {
+                    caps.expand(with, &mut out);
+                }
+                out
+            }
+            _ => with.to_string(),
+        }
+    }
+
+    fn matches(&self, buf: &Rope, cursor: usize, anchor: Option<usize>) -> Matches {
+        let (start, end) = selection_range(cursor, anchor);
+        let line = buf.byte_to_line(start);
+        let current = self.on_line(buf, line).position(|m| m == (start..end));
+        Matches {
+            count: self.count,
+            current: current.map(|i| {
+                let before = self.lines.iter().take(line);
+                before.map(Vec::len).sum::<usize>() + i
+            }),
+        }
+    }
+}
 
 // ─── widget ───────────────────────────────────────────────────────────────────
 
 pub struct TextEditor<M: Clone + 'static> {
     pub id: Option<String>,
+    pub key: Option<String>,
     pub placeholder: String,
     pub placeholder_color: Option<Color>,
+    pub tab_index: i32,
     pub font: Option<String>,
     pub font_size: Option<f32>,
     pub font_weight: u16,
     pub value: Option<String>,
+    pub revision: Option<u64>,
+    pub wrap: bool,
+    pub line_numbers: bool,
+    pub current_line: Option<Color>,
+    pub indent: Option<String>,
     pub layout: Layout,
     pub style: Style,
     pub interactions: Interactions<M>,
-    pub on_change: Option<Box<dyn Fn(String) -> M>>,
+    pub on_change: Option<Callback<String, M>>,
+    pub on_edit: Option<Callback<TextEdit, M>>,
+    pub highlight: Option<Highlighter>,
+    pub search: Option<Search>,
+    pub on_matches: Option<Callback<Matches, M>>,
     pub(crate) x: f32,
     pub(crate) y: f32,
     pub(crate) w: f32,
@@ -44,16 +306,27 @@ impl<M: Clone + 'static> TextEditor<M> {
     pub fn new() -> Self {
         Self {
             id: None,
+            key: None,
             placeholder: String::new(),
             placeholder_color: None,
+            tab_index: 0,
             font: None,
             font_size: None,
             font_weight: 400,
             value: None,
+            revision: None,
+            wrap: false,
+            line_numbers: false,
+            current_line: None,
+            indent: None,
             layout: Layout::default(),
             style: Style::default(),
             interactions: Interactions::default(),
             on_change: None,
+            on_edit: None,
+            highlight: None,
+            search: None,
+            on_matches: None,
             x: 0.0,
             y: 0.0,
             w: 0.0,
@@ -66,13 +339,129 @@ impl<M: Clone + 'static> TextEditor<M> {
         self
     }
 
-    fn require_id(&self) -> &str {
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    pub(crate) fn map<B: Clone + 'static>(self, f: &Mapper<M, B>) -> TextEditor<B> {
+        TextEditor {
+            id: self.id,
+            key: self.key,
+            placeholder: self.placeholder,
+            placeholder_color: self.placeholder_color,
+            tab_index: self.tab_index,
+            font: self.font,
+            font_size: self.font_size,
+            font_weight: self.font_weight,
+            value: self.value,
+            revision: self.revision,
+            wrap: self.wrap,
+            line_numbers: self.line_numbers,
+            current_line: self.current_line,
+            indent: self.indent,
+            layout: self.layout,
+            style: self.style,
+            interactions: self.interactions.map(f),
+            on_change: map_handler(self.on_change, f),
+            on_edit: map_handler(self.on_edit, f),
+            highlight: self.highlight,
+            search: self.search,
+            on_matches: map_handler(self.on_matches, f),
+            x: self.x,
+            y: self.y,
+            w: self.w,
+            h: self.h,
+        }
+    }
+
+    // highlight spans for the rows in view, each relative to its row. highlight is
+    // called a line at a time and only for lines whose text it hasn't seen last frame,
+    // so an edit re-highlights just the lines it touched. no state is passed from one
+    // line to the next, see TextEditor::highlight
+    fn highlight_rows(
+        &self,
+        state: &mut StateStore,
+        id: &str,
+        buf: &Rope,
+        rows: &Rows,
+        visible: Range<usize>,
+    ) -> RowSpans {
+        let Some(highlight) = &self.highlight else {
+            return RowSpans::default();
+        };
+        let cache = &mut state.get_or_default_mut::<TextEditorState>(id).highlights;
+        let mut seen = HashMap::new();
+        let mut out = Vec::new();
+        for li in visible.clone() {
+            let row = rows.row(buf, li);
+            let (start, end) = line_range(buf, buf.byte_to_line(row.start));
+            let line = String::from(buf.byte_slice(start..end));
+            let spans = seen
+                .entry(line)
+                .or_insert_with_key(|line| cache.remove(line).unwrap_or_else(|| highlight(line)));
+            // the part of each span inside the row
+            let (from, to) = (row.start - start, row.end - start);
+            out.push(
+                spans
+                    .iter()
+                    .filter(|(r, _)| r.start < to && r.end > from)
+                    .map(|(r, style)| (r.start.max(from) - from..r.end.min(to) - from, *style))
+                    .collect(),
+            );
+        }
+        // only what's in view is kept, however long the text
+        *cache = seen;
+        RowSpans {
+            first: visible.start,
+            rows: out,
+        }
+    }
+
+    // brings the search up to date with the query and the buffer, runs the find and
+    // replace tasks waiting for it and tells on_matches if that changed anything.
+    // returns which match is selected
+    fn run_search(&mut self, ctx: &mut DrawCtx<M>, id: &str) -> Option<usize> {
+        let s = ctx.state.get_or_default_mut::<TextEditorState>(id);
+        let Some(query) = self.search.take() else {
+            s.search = None;
+            s.search_ops.clear();
+            return None;
+        };
+        if s.search
+            .as_ref()
+            .is_none_or(|searching| searching.query != query)
+        {
+            s.search = Some(Searching::new(query));
+        }
+        for op in std::mem::take(&mut s.search_ops) {
+            if let Some(edit) = search_op(ctx.state, id, op) {
+                ctx.actions.extend(call_callback(ctx.state, id, edit));
+            }
+        }
+
+        let s = ctx.state.get_or_default_mut::<TextEditorState>(id);
+        let searching = s.search.as_mut().unwrap();
+        searching.find(&s.buffer);
+        let matches = searching.matches(&s.buffer, s.cursor, s.selection_anchor);
+        if searching.reported != Some(matches) {
+            searching.reported = Some(matches);
+            if let Some(f) = &self.on_matches {
+                ctx.actions.extend(f(matches));
+            }
+        }
+        matches.current
+    }
+
+    pub(crate) fn require_id(&self) -> &str {
         self.id
             .as_deref()
-            .expect("TextEditor requires an id — use .id(\"my_editor\")")
+            .expect("a TextEditor is given an id before layout, see key.rs")
     }
+}
 
-    pub fn draw(&mut self, ctx: &mut DrawCtx<M>) {
+impl<M: Clone + 'static> Widget<M> for TextEditor<M> {
+    fn draw(&mut self, ctx: &mut DrawCtx<M>) {
         let id = self.require_id().to_string();
         let id = id.as_str();
         let (x, y, w, h) = (self.x, self.y, self.w, self.h);
@@ -80,20 +469,45 @@ impl<M: Clone + 'static> TextEditor<M> {
             return;
         }
 
-        let value_str = self.value.as_deref().unwrap_or("");
-
-        // cache the current value into state so app.rs can read it on key events
-        ctx.state
-            .get_or_default_mut::<TextEditorState>(id)
-            .cached_value = value_str.to_string();
+        // the app's value replaces the buffer whenever they differ, with a revision it's
+        // only compared once that moves. without one the editor keeps its own text,
+        // which the app can follow through on_edit
+        let s = ctx.state.get_or_default_mut::<TextEditorState>(id);
+        if let Some(value) = &self.value {
+            let moved = self.revision.is_none() || s.value_revision != self.revision;
+            if moved && s.buffer != value.as_str() {
+                s.buffer = Rope::from_str(value);
+                s.rows = Rows::default();
+                if let Some(search) = &mut s.search {
+                    search.lines.clear();
+                }
+            }
+            s.value_revision = self.revision;
+        }
+        s.indent = self.indent.clone();
+        // clamp cursor and selection to valid byte boundaries
+        s.cursor = clamp_to_char_boundary(&s.buffer, s.cursor);
+        s.selection_anchor = s
+            .selection_anchor
+            .map(|a| clamp_to_char_boundary(&s.buffer, a));
 
         // register callback for this frame
-        if let Some(cb) = self.on_change.take() {
-            ctx.state.set_callback(id, TextEditorCallback(cb));
+        if let Some(cb) = self.on_edit.take() {
+            ctx.state.set_callback(id, TextEditorCallback::Edit(cb));
+        } else if let Some(cb) = self.on_change.take() {
+            ctx.state.set_callback(id, TextEditorCallback::Change(cb));
         }
 
-        let hovered =
-            ctx.mouse.x >= x && ctx.mouse.x <= x + w && ctx.mouse.y >= y && ctx.mouse.y <= y + h;
+        // find and replace can edit the buffer, so before it's copied
+        let current_match = self.run_search(ctx, id);
+        // a cheap copy, the rope shares its text
+        let buf = ctx
+            .state
+            .get_or_default::<TextEditorState>(id)
+            .buffer
+            .clone();
+
+        let hovered = self.interactions.hovered;
 
         if hovered {
             *ctx.cursor = Some(crate::draw::Cursor::Text);
@@ -107,11 +521,6 @@ impl<M: Clone + 'static> TextEditor<M> {
             ctx.mouse.left_pressed,
         );
         let focused = ctx.state.get_or_default::<TextEditorState>(id).focused;
-        // clamp cursor to valid byte boundary
-        ctx.state.get_or_default_mut::<TextEditorState>(id).cursor = clamp_to_char_boundary(
-            value_str,
-            ctx.state.get_or_default::<TextEditorState>(id).cursor,
-        );
 
         let font_id = self
             .font
@@ -122,11 +531,23 @@ impl<M: Clone + 'static> TextEditor<M> {
         let size = self.font_size.unwrap_or(ctx.fonts.get(font_id).size);
         let line_height = size * 1.4;
 
-        let pad_l = if self.layout.padding.left > 0.0 {
-            self.layout.padding.left
+        // the gutter goes left of the padding, with 8px either side of the numbers
+        let gutter_w = if self.line_numbers {
+            let digits = buf.len_lines().to_string().len();
+            let zeros = "0".repeat(digits);
+            ctx.fonts
+                .measure_sized(&zeros, font_id, size, self.font_weight)
+                .0
+                + 16.0
         } else {
-            8.0
+            0.0
         };
+        let pad_l = gutter_w
+            + if self.layout.padding.left > 0.0 {
+                self.layout.padding.left
+            } else {
+                8.0
+            };
         let pad_r = if self.layout.padding.right > 0.0 {
             self.layout.padding.right
         } else {
@@ -150,24 +571,49 @@ impl<M: Clone + 'static> TextEditor<M> {
         let text_origin_x = ((x + pad_l) * sc).floor() / sc;
         let text_origin_y_base = y + pad_t; // before scroll
 
-        let lines: Vec<&str> = value_str.split('\n').collect();
-
-        // update vertical scroll so cursor stays visible
-        update_scroll(ctx.state, id, value_str, &lines, line_height, text_area_h);
-        let scroll = ctx
-            .state
-            .get_or_default::<TextEditorState>(id)
-            .scroll_offset;
+        ctx.state
+            .get_or_default_mut::<TextEditorState>(id)
+            .page_lines = (text_area_h / line_height).floor() as usize;
+
+        // taken out of the state while drawing, put back at the end
+        let mut rows =
+            std::mem::take(&mut ctx.state.get_or_default_mut::<TextEditorState>(id).rows);
+        let wrap = self
+            .wrap
+            .then_some((text_area_w, font_id, size, self.font_weight));
+        rows.set_wrap(&buf, wrap);
+        let cursor_line = buf.byte_to_line(ctx.state.get_or_default::<TextEditorState>(id).cursor);
+        rows.wrap(&buf, cursor_line..cursor_line + 1, ctx.fonts);
+
+        // only the rows in view are measured and drawn, and their lines wrapped as they
+        // come into view. that can move the cursor's row, so the scroll keeping it
+        // visible is worked out again until nothing new was wrapped
+        let shown_count = (text_area_h / line_height).ceil() as usize + 1;
+        let (scroll, visible) = loop {
+            update_scroll(ctx.state, id, &buf, &rows, line_height, text_area_h);
+            let scroll = ctx
+                .state
+                .get_or_default::<TextEditorState>(id)
+                .scroll_offset;
+            let first = (((scroll * sc).floor() / sc) / line_height).floor() as usize;
+            let visible = first..(first + shown_count).min(rows.len(&buf));
+            if !rows.wrap(&buf, rows.lines(&buf, visible.clone()), ctx.fonts) {
+                break (scroll, visible);
+            }
+        };
         let scroll_snapped = (scroll * sc).floor() / sc;
+        let spans = self.highlight_rows(ctx.state, id, &buf, &rows, visible.clone());
 
         // handle mouse (may mutate cursor/selection)
         handle_mouse(
             ctx,
             id,
-            value_str,
-            &lines,
+            &buf,
+            &rows,
+            &spans,
             font_id,
             size,
+            self.font_weight,
             line_height,
             text_origin_x,
             text_origin_y_base,
@@ -181,13 +627,119 @@ impl<M: Clone + 'static> TextEditor<M> {
             .state
             .get_or_default::<TextEditorState>(id)
             .selection_anchor;
-        let has_selection = selection_anchor.map_or(false, |a| a != cursor_pos);
+        let has_selection = selection_anchor.is_some_and(|a| a != cursor_pos);
+
+        // an IME composition in progress shows inline at the cursor, see ime.rs. it's
+        // drawn from a copy of the buffer and rows with the preedit spliced in
+        let preedit = if focused {
+            ime::preedit(ctx.state).map(|(text, caret)| (text.to_string(), caret))
+        } else {
+            None
+        };
+        let mut shown = buf.clone();
+        let mut composed_rows = None;
+        if let Some((text, _)) = &preedit {
+            shown.insert(shown.byte_to_char(cursor_pos), text);
+            let mut r = rows.clone();
+            r.edited(&buf, cursor_pos, cursor_pos, text);
+            r.wrap(&shown, r.lines(&shown, visible.clone()), ctx.fonts);
+            composed_rows = Some(r);
+        }
+        let shown_rows = composed_rows.as_ref().unwrap_or(&rows);
+        let composed_spans = composed_rows
+            .as_ref()
+            .map(|r| self.highlight_rows(ctx.state, id, &shown, r, visible.clone()));
+        let shown_spans = composed_spans.as_ref().unwrap_or(&spans);
+        let caret = match &preedit {
+            Some((text, caret)) => cursor_pos + caret.unwrap_or(text.len()),
+            None => cursor_pos,
+        };
 
         // ── draw background ──────────────────────────────────────────────────
-        draw_background(ctx, x, y, w, h, &self.style, focused, hovered);
+        draw_background(ctx, x, y, w, h, &self.style, focused);
+        if focus::ring_visible(ctx.state, id) {
+            focus::draw_ring(ctx.list, x, y, w, h, self.style.border_radius);
+        }
+        let cursor_line = focused.then(|| shown.byte_to_line(caret));
+        if let (Some(line), Some(color)) = (cursor_line, self.current_line) {
+            draw_current_line(
+                ctx,
+                x,
+                w,
+                text_origin_y_base,
+                scroll_snapped,
+                line_height,
+                sc,
+                text_clip,
+                &shown,
+                shown_rows,
+                line,
+                color,
+                self.style.opacity,
+            );
+        }
+        if self.line_numbers {
+            draw_gutter(
+                ctx,
+                x,
+                gutter_w,
+                text_origin_y_base,
+                scroll_snapped,
+                line_height,
+                sc,
+                text_clip,
+                family.clone(),
+                font_id,
+                size,
+                self.font_weight,
+                &shown,
+                shown_rows,
+                visible.clone(),
+                cursor_line,
+                self.style.text_color,
+                self.style.opacity,
+            );
+        }
+
+        // ── draw matches ─────────────────────────────────────────────────────
+        if preedit.is_none() && !visible.is_empty() {
+            let from = rows.row(&buf, visible.start).start;
+            let to = rows.row(&buf, visible.end - 1).end;
+            let matches = match &ctx.state.get_or_default::<TextEditorState>(id).search {
+                Some(search) => search.between(&buf, from, to),
+                None => Vec::new(),
+            };
+            for m in matches {
+                draw_selection(
+                    ctx,
+                    x,
+                    w,
+                    pad_l,
+                    pad_r,
+                    text_origin_x,
+                    text_origin_y_base,
+                    scroll_snapped,
+                    line_height,
+                    sc,
+                    text_clip,
+                    &buf,
+                    &rows,
+                    &spans,
+                    visible.clone(),
+                    font_id,
+                    size,
+                    self.font_weight,
+                    m,
+                    with_opacity([0.9, 0.7, 0.2, 0.3], self.style.opacity),
+                );
+            }
+        }
 
         // ── draw selection ───────────────────────────────────────────────────
-        if focused && has_selection {
+        // the selected match shows without focus, find bars usually have it
+        let selected = focused || current_match.is_some();
+        if selected && has_selection && preedit.is_none() {
+            let (sel_start, sel_end) = selection_range(cursor_pos, selection_anchor);
             draw_selection(
                 ctx,
                 x,
@@ -200,24 +752,24 @@ impl<M: Clone + 'static> TextEditor<M> {
                 line_height,
                 sc,
                 text_clip,
-                value_str,
-                &lines,
+                &buf,
+                &rows,
+                &spans,
+                visible.clone(),
                 font_id,
                 size,
                 self.font_weight,
-                cursor_pos,
-                selection_anchor,
-                self.style.opacity,
+                sel_start..sel_end,
+                with_opacity([0.3, 0.5, 0.9, 0.4], self.style.opacity),
             );
         }
 
         // ── draw text / placeholder ──────────────────────────────────────────
+        if let Some(clip) = text_clip {
+            ctx.list.push_clip(clip);
+        }
         draw_text(
             ctx,
-            x,
-            w,
-            pad_l,
-            pad_r,
             text_origin_x,
             text_origin_y_base,
             scroll_snapped,
@@ -227,16 +779,69 @@ impl<M: Clone + 'static> TextEditor<M> {
             family.clone(),
             size,
             self.font_weight,
-            value_str,
-            &lines,
+            &shown,
+            shown_rows,
+            shown_spans,
+            visible,
             &self.placeholder,
             self.placeholder_color,
             self.style.text_color,
             self.style.opacity,
         );
+        // the preedit is underlined, it isn't part of the value yet
+        if let Some((text, _)) = &preedit {
+            let (li, start) = shown_rows.find(&shown, cursor_pos);
+            let (end_li, end) = shown_rows.find(&shown, cursor_pos + text.len());
+            let line = row_text(&shown, shown_rows.row(&shown, li));
+            let end = if end_li == li { end } else { line.len() };
+            let (weight, line_spans) = (self.font_weight, shown_spans.of(li));
+            let (x0, _) =
+                ctx.fonts
+                    .measure_spans(&line[..start], font_id, size, weight, line_spans);
+            let (x1, _) = ctx
+                .fonts
+                .measure_spans(&line[..end], font_id, size, weight, line_spans);
+            let line_y = text_origin_y_base + li as f32 * line_height - scroll_snapped;
+            let col = self
+                .style
+                .text_color
+                .unwrap_or(Color::new(0.92, 0.92, 0.95, 1.0));
+            ctx.list.rect(
+                text_origin_x + x0,
+                line_y + line_height - 1.0,
+                x1 - x0,
+                1.0,
+                with_opacity(col.to_array(), self.style.opacity),
+                [0.0; 4],
+                0.0,
+            );
+        }
+        if text_clip.is_some() {
+            ctx.list.pop_clip();
+        }
 
         // ── draw cursor ──────────────────────────────────────────────────────
-        if focused && !has_selection {
+        if focused {
+            let (li, col) = shown_rows.find(&shown, caret);
+            let line = row_text(&shown, shown_rows.row(&shown, li));
+            let (caret_x, _) = ctx.fonts.measure_spans(
+                &line[..col],
+                font_id,
+                size,
+                self.font_weight,
+                shown_spans.of(li),
+            );
+            let caret_y = text_origin_y_base + li as f32 * line_height - scroll_snapped;
+            ime::set_caret(
+                ctx.state,
+                [text_origin_x + caret_x, caret_y, 2.0, line_height],
+            );
+        }
+        let show_caret = match &preedit {
+            Some((_, caret)) => caret.is_some(),
+            None => !has_selection,
+        };
+        if focused && show_caret {
             draw_cursor(
                 ctx,
                 x,
@@ -249,21 +854,23 @@ impl<M: Clone + 'static> TextEditor<M> {
                 line_height,
                 sc,
                 text_clip,
-                value_str,
-                &lines,
+                &shown,
+                shown_rows,
+                shown_spans,
                 font_id,
                 size,
                 self.font_weight,
-                cursor_pos,
+                caret,
                 self.style.text_color,
                 self.style.opacity,
             );
         }
 
-        check_interactions(&self.interactions, x, y, w, h, ctx);
+        ctx.state.get_or_default_mut::<TextEditorState>(id).rows = rows;
+        check_interactions(&self.interactions, ctx);
     }
 
-    pub fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
+    fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
         let font_id = self
             .font
             .as_deref()
@@ -299,13 +906,31 @@ impl<M: Clone + 'static> TextEditor<M> {
             .unwrap()
     }
 
-    pub fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
+    fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
         self.x = x;
         self.y = y;
         self.w = w;
         self.h = h;
     }
 
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        [self.x, self.y, self.w, self.h]
+    }
+
+    fn interactions(&self) -> Option<&Interactions<M>> {
+        Some(&self.interactions)
+    }
+
+    fn interactions_mut(&mut self) -> Option<&mut Interactions<M>> {
+        Some(&mut self.interactions)
+    }
+}
+
+impl<M: Clone + 'static> TextEditor<M> {
     // ── layout builder ────────────────────────────────────────────────────────
     pub fn width(mut self, v: Val) -> Self {
         self.layout.width = v;
@@ -360,6 +985,13 @@ impl<M: Clone + 'static> TextEditor<M> {
         self.value = Some(v.to_string());
         self
     }
+    // a number the app moves whenever it changes value on its own, edits it was handed
+    // by on_change or on_edit don't count. with one value is only compared with the
+    // editor's text when the number moves, not every frame
+    pub fn revision(mut self, revision: u64) -> Self {
+        self.revision = Some(revision);
+        self
+    }
     pub fn placeholder(mut self, text: &str) -> Self {
         self.placeholder = text.to_string();
         self
@@ -380,18 +1012,80 @@ impl<M: Clone + 'static> TextEditor<M> {
         self.font_weight = weight;
         self
     }
+    // soft wrap long lines at the editor's width instead of running past it
+    pub fn wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+    // a gutter down the left with each line's number, as wide as the last one needs
+    pub fn line_numbers(mut self, show: bool) -> Self {
+        self.line_numbers = show;
+        self
+    }
+    // background for the line the cursor is on while focused
+    pub fn current_line(mut self, color: Color) -> Self {
+        self.current_line = Some(color);
+        self
+    }
+    // what one level of indentation is, e.g. four spaces or "\t". with it Tab and
+    // Shift+Tab indent and outdent the selected lines instead of moving focus (that's
+    // Ctrl+Tab then), and Enter starts the new line at the same indent as the last
+    pub fn indent(mut self, unit: &str) -> Self {
+        self.indent = Some(unit.to_string());
+        self
+    }
     pub fn on_change(mut self, f: impl Fn(String) -> M + 'static) -> Self {
-        self.on_change = Some(Box::new(f));
+        self.on_change = Some(callback(f));
+        self
+    }
+    // each change as the range it replaced and the text that replaced it, instead of
+    // the whole value like on_change. takes over from on_change if both are set
+    pub fn on_edit(mut self, f: impl Fn(TextEdit) -> M + 'static) -> Self {
+        self.on_edit = Some(callback(f));
+        self
+    }
+    // styled spans for a line of text, given without its '\n', e.g. syntax highlighting.
+    // a line's spans are reused for as long as its text doesn't change.
+    // every line is highlighted on its own, nothing carries over from the line before,
+    // so a construct that spans lines (a block comment, a multi-line string) is only
+    // styled as far as each of its lines can tell by itself
+    pub fn highlight(
+        mut self,
+        f: impl Fn(&str) -> Vec<(Range<usize>, SpanStyle)> + 'static,
+    ) -> Self {
+        self.highlight = Some(Box::new(f));
+        self
+    }
+
+    // highlights every match of the query, Task::find_next and the rest move between
+    // them and replace them
+    pub fn search(mut self, query: Search) -> Self {
+        self.search = Some(query);
+        self
+    }
+    // called whenever the number of matches or which one is selected changes
+    pub fn on_matches(mut self, f: impl Fn(Matches) -> M + 'static) -> Self {
+        self.on_matches = Some(callback(f));
         self
     }
 
     // ── interaction builder ───────────────────────────────────────────────────
+    // order Tab visits it in, see focus.rs. negative leaves it out
+    pub fn tab_index(mut self, index: i32) -> Self {
+        self.tab_index = index;
+        self
+    }
+
     pub fn on_click(mut self, msg: M) -> Self {
-        self.interactions.on_click = Some(msg);
+        self.interactions.on_click = Some(Handler::new(msg));
         self
     }
     pub fn on_hover(mut self, msg: M) -> Self {
-        self.interactions.on_hover = Some(msg);
+        self.interactions.on_hover = Some(Handler::new(msg));
+        self
+    }
+    pub fn stop_propagation(mut self) -> Self {
+        self.interactions.stop_propagation = true;
         self
     }
 }
@@ -406,8 +1100,8 @@ fn update_focus_and_drag(
     pressed: bool,
 ) {
     if just_pressed {
+        focus::press(state, id, FocusKind::TextEditor, hovered);
         let s = state.get_or_default_mut::<TextEditorState>(id);
-        s.focused = hovered;
         s.selection_anchor = None;
         s.dragging = hovered;
     }
@@ -424,7 +1118,6 @@ fn draw_background<M: Clone + 'static>(
     h: f32,
     style: &Style,
     focused: bool,
-    hovered: bool,
 ) {
     let bg = style
         .background
@@ -440,7 +1133,7 @@ fn draw_background<M: Clone + 'static>(
         (col, 1.5)
     };
 
-    ctx.sr.draw_rounded_rect(
+    ctx.list.rounded_rect(
         x,
         y,
         w,
@@ -456,44 +1149,206 @@ fn draw_background<M: Clone + 'static>(
     );
 }
 
-/// Returns (line_index, byte_offset_within_line) for a given overall byte offset.
-fn offset_to_line_col(lines: &[&str], offset: usize) -> (usize, usize) {
-    let mut remaining = offset;
-    for (i, line) in lines.iter().enumerate() {
-        let len = line.len();
-        if remaining <= len || i == lines.len() - 1 {
-            return (i, remaining.min(len));
+// ─── rows ─────────────────────────────────────────────────────────────────────
+
+// one visual row: a whole line, or with wrap on, as much of one as fits the width.
+// a byte range of the buffer, the '\n' ending a line isn't in any row
+#[derive(Clone, Copy)]
+struct Row {
+    start: usize,
+    end: usize,
+    // the line carries on in the next row, which starts at end
+    wrapped: bool,
+}
+
+// the buffer's lines as rows. without wrap a row is just a line, found through the
+// rope's own line index. with wrap each line keeps where it breaks, worked out the
+// first time it comes into view and again only once it's edited. a line that hasn't
+// been in view yet counts as one row, so the rows further down are where they'd be
+// with it unwrapped until it's shown
+#[derive(Clone, Default)]
+pub(crate) struct Rows {
+    // what the lines were wrapped with: width, font, size and weight. None without wrap
+    wrap: Option<(f32, crate::FontId, f32, u16)>,
+    // per line, the byte offsets into it where it wraps. None until it's wrapped,
+    // meanwhile it counts as one row
+    breaks: Vec<Option<Vec<usize>>>,
+    // per line, the index of its first row
+    first: Vec<usize>,
+}
+
+impl Rows {
+    fn len(&self, buf: &Rope) -> usize {
+        match self.first.last() {
+            Some(&first) => first + self.breaks_of(self.first.len() - 1).len() + 1,
+            None => buf.len_lines(),
         }
-        remaining -= len + 1; // +1 for the '\n'
     }
-    (
-        lines.len().saturating_sub(1),
-        lines.last().map(|l| l.len()).unwrap_or(0),
-    )
-}
 
-/// Convert (line_index, col_byte_offset) back to overall byte offset.
-fn line_col_to_offset(lines: &[&str], line: usize, col: usize) -> usize {
-    let mut off = 0;
-    for (i, l) in lines.iter().enumerate() {
-        if i == line {
-            return off + col.min(l.len());
+    fn breaks_of(&self, line: usize) -> &[usize] {
+        self.breaks
+            .get(line)
+            .and_then(|b| b.as_deref())
+            .unwrap_or(&[])
+    }
+
+    fn row(&self, buf: &Rope, row: usize) -> Row {
+        let row = row.min(self.len(buf) - 1);
+        let (line, k) = match self.wrap {
+            Some(_) => {
+                let line = self.first.partition_point(|&f| f <= row) - 1;
+                (line, row - self.first[line])
+            }
+            None => (row, 0),
+        };
+        let (start, end) = line_range(buf, line);
+        let breaks = self.breaks_of(line);
+        Row {
+            start: if k == 0 { start } else { start + breaks[k - 1] },
+            end: breaks.get(k).map_or(end, |b| start + b),
+            wrapped: k < breaks.len(),
+        }
+    }
+
+    /// Returns (row_index, byte_offset_within_row) for a given overall byte offset.
+    fn find(&self, buf: &Rope, offset: usize) -> (usize, usize) {
+        let offset = offset.min(buf.len_bytes());
+        let line = buf.byte_to_line(offset);
+        let start = buf.line_to_byte(line);
+        // an offset where a line wraps belongs to the start of the next row
+        let breaks = self.breaks_of(line);
+        let k = breaks.partition_point(|&b| start + b <= offset);
+        let row_start = if k == 0 { start } else { start + breaks[k - 1] };
+        let row = match self.wrap {
+            Some(_) => self.first[line] + k,
+            None => line,
+        };
+        (row, offset - row_start)
+    }
+
+    /// Convert (row_index, col_byte_offset) back to overall byte offset.
+    fn offset(&self, buf: &Rope, row: usize, col: usize) -> usize {
+        let row = self.row(buf, row);
+        // the end of a wrapped row is the next row's start, so stop before its last char
+        let end = if row.wrapped {
+            prev_char(buf, row.end).max(row.start)
+        } else {
+            row.end
+        };
+        clamp_to_char_boundary(buf, row.start + col.min(end - row.start))
+    }
+
+    // starts over when wrap was turned on or off or the width or font changed, every
+    // line is unwrapped again then
+    fn set_wrap(&mut self, buf: &Rope, wrap: Option<(f32, crate::FontId, f32, u16)>) {
+        if wrap == self.wrap {
+            return;
+        }
+        self.wrap = wrap;
+        self.breaks = match wrap {
+            Some(_) => vec![None; buf.len_lines()],
+            None => Vec::new(),
+        };
+        self.index();
+    }
+
+    // wraps the lines in range that haven't been, returns whether there were any
+    fn wrap(&mut self, buf: &Rope, lines: Range<usize>, fonts: &mut Fonts) -> bool {
+        let Some((width, font_id, size, weight)) = self.wrap else {
+            return false;
+        };
+        let end = lines.end.min(self.breaks.len());
+        let mut wrapped = false;
+        for line in lines.start.min(end)..end {
+            if self.breaks[line].is_none() {
+                let (start, end) = line_range(buf, line);
+                let text = Cow::from(buf.byte_slice(start..end));
+                self.breaks[line] = Some(fonts.wrap(&text, font_id, size, weight, width));
+                wrapped = true;
+            }
+        }
+        if wrapped {
+            self.index();
+        }
+        wrapped
+    }
+
+    // the lines the rows in range are part of
+    fn lines(&self, buf: &Rope, rows: Range<usize>) -> Range<usize> {
+        if rows.is_empty() {
+            return 0..0;
+        }
+        let first = buf.byte_to_line(self.row(buf, rows.start).start);
+        let last = buf.byte_to_line(self.row(buf, rows.end - 1).start);
+        first..last + 1
+    }
+
+    // an edit replaced the bytes start..end of old with text: the lines it touched
+    // need wrapping again
+    fn edited(&mut self, old: &Rope, start: usize, end: usize, text: &str) {
+        if self.wrap.is_none() {
+            return;
+        }
+        let lines = text.matches('\n').count() + 1;
+        let touched = old.byte_to_line(start)..=old.byte_to_line(end);
+        self.breaks
+            .splice(touched, std::iter::repeat_n(None, lines));
+        self.index();
+    }
+
+    fn index(&mut self) {
+        self.first.clear();
+        if self.wrap.is_none() {
+            return;
+        }
+        let mut row = 0;
+        for breaks in &self.breaks {
+            self.first.push(row);
+            row += breaks.as_ref().map_or(0, Vec::len) + 1;
         }
-        off += l.len() + 1; // +1 for '\n'
     }
-    off
+}
+
+// highlight spans of the rows in view, each relative to its row
+#[derive(Default)]
+struct RowSpans {
+    first: usize,
+    rows: Vec<Spans>,
+}
+
+impl RowSpans {
+    fn of(&self, row: usize) -> &[(Range<usize>, SpanStyle)] {
+        row.checked_sub(self.first)
+            .and_then(|i| self.rows.get(i))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+// a line's byte range, without its '\n'
+fn line_range(buf: &Rope, line: usize) -> (usize, usize) {
+    let start = buf.line_to_byte(line);
+    let end = if line + 1 < buf.len_lines() {
+        buf.line_to_byte(line + 1) - 1
+    } else {
+        buf.len_bytes()
+    };
+    (start, end)
+}
+
+fn row_text(buf: &Rope, row: Row) -> Cow<'_, str> {
+    buf.byte_slice(row.start..row.end).into()
 }
 
 fn update_scroll(
     state: &mut StateStore,
     id: &str,
-    value: &str,
-    lines: &[&str],
+    buf: &Rope,
+    rows: &Rows,
     line_height: f32,
     text_area_h: f32,
 ) {
     let cursor_pos = state.get_or_default::<TextEditorState>(id).cursor;
-    let (line_idx, _) = offset_to_line_col(lines, cursor_pos);
+    let (line_idx, _) = rows.find(buf, cursor_pos);
     let cursor_top = line_idx as f32 * line_height;
     let cursor_bot = cursor_top + line_height;
 
@@ -515,10 +1370,12 @@ fn update_scroll(
 fn handle_mouse<M: Clone + 'static>(
     ctx: &mut DrawCtx<M>,
     id: &str,
-    value: &str,
-    lines: &[&str],
+    buf: &Rope,
+    rows: &Rows,
+    spans: &RowSpans,
     font_id: crate::FontId,
     size: f32,
+    weight: u16,
     line_height: f32,
     text_origin_x: f32,
     text_origin_y_base: f32,
@@ -531,9 +1388,12 @@ fn handle_mouse<M: Clone + 'static>(
         let hit = hit_test(
             ctx.mouse.x,
             ctx.mouse.y,
-            lines,
+            buf,
+            rows,
+            spans,
             font_id,
             size,
+            weight,
             line_height,
             text_origin_x,
             text_origin_y_base,
@@ -544,8 +1404,8 @@ fn handle_mouse<M: Clone + 'static>(
         match ctx.mouse.left_click_count {
             2 => {
                 // select word
-                let ws = word_start(value, hit);
-                let we = word_end(value, hit);
+                let ws = word_start(buf, hit);
+                let we = word_end(buf, hit);
                 if ws < we {
                     state.selection_anchor = Some(ws);
                     state.cursor = we;
@@ -555,10 +1415,8 @@ fn handle_mouse<M: Clone + 'static>(
                 }
             }
             3 => {
-                // select whole line
-                let (li, _) = offset_to_line_col(lines, hit);
-                let line_start = line_col_to_offset(lines, li, 0);
-                let line_end = line_col_to_offset(lines, li, lines[li].len());
+                // select the whole line, not just the row when it wraps
+                let (line_start, line_end) = line_range(buf, buf.byte_to_line(hit));
                 state.selection_anchor = Some(line_start);
                 state.cursor = line_end;
             }
@@ -571,9 +1429,12 @@ fn handle_mouse<M: Clone + 'static>(
         let hit = hit_test(
             ctx.mouse.x,
             ctx.mouse.y,
-            lines,
+            buf,
+            rows,
+            spans,
             font_id,
             size,
+            weight,
             line_height,
             text_origin_x,
             text_origin_y_base,
@@ -595,25 +1456,36 @@ fn handle_mouse<M: Clone + 'static>(
 fn hit_test(
     mouse_x: f32,
     mouse_y: f32,
-    lines: &[&str],
+    buf: &Rope,
+    rows: &Rows,
+    spans: &RowSpans,
     font_id: crate::FontId,
     size: f32,
+    weight: u16,
     line_height: f32,
     text_origin_x: f32,
     text_origin_y_base: f32,
     scroll: f32,
     fonts: &mut Fonts,
 ) -> usize {
-    // which line?
+    // which row?
     let rel_y = (mouse_y - text_origin_y_base + scroll).max(0.0);
-    let line_idx = ((rel_y / line_height) as usize).min(lines.len().saturating_sub(1));
-    let line = lines[line_idx];
+    let row_idx = ((rel_y / line_height) as usize).min(rows.len(buf) - 1);
+    let line = row_text(buf, rows.row(buf, row_idx));
 
-    // which character within the line?
+    // which character within the row?
     let click_x = (mouse_x - text_origin_x).max(0.0);
-    let col = hit_test_line(line, click_x, fonts, font_id, size, 400);
+    let col = hit_test_line(
+        &line,
+        click_x,
+        fonts,
+        font_id,
+        size,
+        weight,
+        spans.of(row_idx),
+    );
 
-    line_col_to_offset(lines, line_idx, col)
+    rows.offset(buf, row_idx, col)
 }
 
 fn hit_test_line(
@@ -623,6 +1495,7 @@ fn hit_test_line(
     font_id: crate::FontId,
     size: f32,
     weight: u16,
+    spans: &[(Range<usize>, SpanStyle)],
 ) -> usize {
     if line.is_empty() {
         return 0;
@@ -636,15 +1509,16 @@ fn hit_test_line(
     let mut hi = boundaries.len() - 1;
     while hi - lo > 1 {
         let mid = (lo + hi) / 2;
-        let (measured, _) = fonts.measure_sized(&line[..boundaries[mid]], font_id, size, weight);
+        let (measured, _) =
+            fonts.measure_spans(&line[..boundaries[mid]], font_id, size, weight, spans);
         if measured <= click_x {
             lo = mid;
         } else {
             hi = mid;
         }
     }
-    let (lo_w, _) = fonts.measure_sized(&line[..boundaries[lo]], font_id, size, weight);
-    let (hi_w, _) = fonts.measure_sized(&line[..boundaries[hi]], font_id, size, weight);
+    let (lo_w, _) = fonts.measure_spans(&line[..boundaries[lo]], font_id, size, weight, spans);
+    let (hi_w, _) = fonts.measure_spans(&line[..boundaries[hi]], font_id, size, weight, spans);
     if (lo_w - click_x).abs() <= (hi_w - click_x).abs() {
         boundaries[lo]
     } else {
@@ -665,34 +1539,25 @@ fn draw_selection<M: Clone + 'static>(
     line_height: f32,
     sc: f32,
     clip: Option<[f32; 4]>,
-    value: &str,
-    lines: &[&str],
+    buf: &Rope,
+    rows: &Rows,
+    spans: &RowSpans,
+    visible: Range<usize>,
     font_id: crate::FontId,
     size: f32,
     weight: u16,
-    cursor_pos: usize,
-    selection_anchor: Option<usize>,
-    opacity: f32,
+    range: Range<usize>,
+    sel_col: [f32; 4],
 ) {
-    let Some(anchor) = selection_anchor else {
-        return;
-    };
-    if anchor == cursor_pos {
-        return;
-    }
-
-    let sel_start = anchor.min(cursor_pos);
-    let sel_end = anchor.max(cursor_pos);
+    let (sel_start, sel_end) = (range.start, range.end);
+    let (start_line, start_col) = rows.find(buf, sel_start);
+    let (end_line, end_col) = rows.find(buf, sel_end);
 
-    let (start_line, start_col) = offset_to_line_col(lines, sel_start);
-    let (end_line, end_col) = offset_to_line_col(lines, sel_end);
-
-    let sel_col = with_opacity([0.3, 0.5, 0.9, 0.4], opacity);
     let right_edge = x + w - pad_r;
     let left_edge = x + pad_l;
 
-    for li in start_line..=end_line {
-        let line = lines[li];
+    for li in start_line.max(visible.start)..(end_line + 1).min(visible.end) {
+        let line = row_text(buf, rows.row(buf, li));
         // Snap y to pixel, compute height as distance to next snapped y
         // so adjacent lines tile perfectly with no gap or overlap.
         let line_y =
@@ -705,12 +1570,12 @@ fn draw_selection<M: Clone + 'static>(
         let col_start = if li == start_line { start_col } else { 0 };
         let col_end = if li == end_line { end_col } else { line.len() };
 
-        let (sx_rel, _) = ctx
-            .fonts
-            .measure_sized(&line[..col_start], font_id, size, weight);
-        let (ex_rel, _) = ctx
-            .fonts
-            .measure_sized(&line[..col_end], font_id, size, weight);
+        let (sx_rel, _) =
+            ctx.fonts
+                .measure_spans(&line[..col_start], font_id, size, weight, spans.of(li));
+        let (ex_rel, _) =
+            ctx.fonts
+                .measure_spans(&line[..col_end], font_id, size, weight, spans.of(li));
 
         let sx = (text_origin_x + sx_rel).max(left_edge);
         let ex = (text_origin_x + ex_rel).min(right_edge);
@@ -732,19 +1597,124 @@ fn draw_selection<M: Clone + 'static>(
         };
 
         if ex > sx && rh > 0.0 {
-            ctx.sr
-                .draw_rect(sx, ry, ex - sx, rh, sel_col, [0.0; 4], 0.0);
+            ctx.list.rect(sx, ry, ex - sx, rh, sel_col, [0.0; 4], 0.0);
         }
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-fn draw_text<M: Clone + 'static>(
+fn draw_current_line<M: Clone + 'static>(
     ctx: &mut DrawCtx<M>,
     x: f32,
     w: f32,
-    pad_l: f32,
-    pad_r: f32,
+    text_origin_y_base: f32,
+    scroll_snapped: f32,
+    line_height: f32,
+    sc: f32,
+    clip: Option<[f32; 4]>,
+    buf: &Rope,
+    rows: &Rows,
+    line: usize,
+    color: Color,
+    opacity: f32,
+) {
+    // every row of the line when it wraps
+    let (start, end) = line_range(buf, line);
+    let (first, _) = rows.find(buf, start);
+    let (last, _) = rows.find(buf, end);
+    let top =
+        ((text_origin_y_base + first as f32 * line_height - scroll_snapped) * sc).floor() / sc;
+    let bot =
+        ((text_origin_y_base + (last + 1) as f32 * line_height - scroll_snapped) * sc).floor() / sc;
+    let (top, bot) = match clip {
+        Some([_, cy, _, cy2]) => (top.max(cy), bot.min(cy2)),
+        None => (top, bot),
+    };
+    if bot > top {
+        ctx.list.rect(
+            x,
+            top,
+            w,
+            bot - top,
+            with_opacity(color.to_array(), opacity),
+            [0.0; 4],
+            0.0,
+        );
+    }
+}
+
+// line numbers right aligned in the gutter, on the first row of each line. the
+// cursor's line is numbered in the text color, the rest dimmer
+#[allow(clippy::too_many_arguments)]
+fn draw_gutter<M: Clone + 'static>(
+    ctx: &mut DrawCtx<M>,
+    x: f32,
+    gutter_w: f32,
+    text_origin_y_base: f32,
+    scroll_snapped: f32,
+    line_height: f32,
+    sc: f32,
+    clip: Option<[f32; 4]>,
+    family: String,
+    font_id: crate::FontId,
+    size: f32,
+    weight: u16,
+    buf: &Rope,
+    rows: &Rows,
+    visible: Range<usize>,
+    cursor_line: Option<usize>,
+    text_color: Option<Color>,
+    opacity: f32,
+) {
+    let Some([_, cy, _, cy2]) = clip else {
+        return;
+    };
+    let dim = Color::new(0.45, 0.45, 0.5, 1.0);
+    let bright = text_color.unwrap_or(Color::new(0.92, 0.92, 0.95, 1.0));
+    ctx.list.rect(
+        x + gutter_w - 1.0,
+        cy,
+        1.0,
+        cy2 - cy,
+        with_opacity(Color::new(0.3, 0.3, 0.35, 1.0).to_array(), opacity),
+        [0.0; 4],
+        0.0,
+    );
+    ctx.list.push_clip([x, cy, x + gutter_w, cy2]);
+    for li in visible {
+        let start = rows.row(buf, li).start;
+        let line = buf.byte_to_line(start);
+        if buf.line_to_byte(line) != start {
+            continue;
+        }
+        let number = (line + 1).to_string();
+        let (nw, _) = ctx.fonts.measure_sized(&number, font_id, size, weight);
+        let ty =
+            ((text_origin_y_base + li as f32 * line_height - scroll_snapped) * sc).floor() / sc;
+        let col = if cursor_line == Some(line) {
+            bright
+        } else {
+            dim
+        };
+        ctx.list.text(
+            family.clone(),
+            size,
+            weight,
+            false,
+            TextAlign::Left,
+            &number,
+            x + gutter_w - 8.0 - nw,
+            ty,
+            99999.0,
+            with_opacity(col.to_array(), opacity).into(),
+        );
+    }
+    ctx.list.pop_clip();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_text<M: Clone + 'static>(
+    ctx: &mut DrawCtx<M>,
     text_origin_x: f32,
     text_origin_y_base: f32,
     scroll_snapped: f32,
@@ -754,18 +1724,19 @@ fn draw_text<M: Clone + 'static>(
     family: String,
     size: f32,
     weight: u16,
-    value: &str,
-    lines: &[&str],
+    buf: &Rope,
+    rows: &Rows,
+    spans: &RowSpans,
+    visible: Range<usize>,
     placeholder: &str,
     placeholder_color: Option<Color>,
     text_color: Option<Color>,
     opacity: f32,
 ) {
-    if value.is_empty() {
+    if buf.len_bytes() == 0 {
         let col = placeholder_color.unwrap_or(Color::new(0.45, 0.45, 0.5, 1.0));
         let ty = (text_origin_y_base * sc).floor() / sc;
-        ctx.tr.draw(
-            &mut ctx.fonts.font_system,
+        ctx.list.text(
             family,
             size,
             weight,
@@ -775,7 +1746,6 @@ fn draw_text<M: Clone + 'static>(
             text_origin_x,
             ty,
             99999.0,
-            clip,
             with_opacity(col.to_array(), opacity).into(),
         );
         return;
@@ -784,28 +1754,37 @@ fn draw_text<M: Clone + 'static>(
     let col = text_color.unwrap_or(Color::new(0.92, 0.92, 0.95, 1.0));
     let color_arr: [f32; 4] = with_opacity(col.to_array(), opacity);
 
-    for (li, line) in lines.iter().enumerate() {
+    for li in visible {
         let ty_raw = text_origin_y_base + li as f32 * line_height - scroll_snapped;
         let ty = (ty_raw * sc).floor() / sc;
         // skip lines completely outside clip
-        if let Some([_, cy, _, cy2]) = clip {
-            if ty + line_height < cy || ty > cy2 {
-                continue;
-            }
+        if let Some([_, cy, _, cy2]) = clip
+            && (ty + line_height < cy || ty > cy2)
+        {
+            continue;
         }
-        ctx.tr.draw(
-            &mut ctx.fonts.font_system,
+        let spans = spans
+            .of(li)
+            .iter()
+            .map(|(r, style)| {
+                let color = style
+                    .color
+                    .map(|c| with_opacity(c.to_array(), opacity).into());
+                (r.clone(), SpanStyle { color, ..*style })
+            })
+            .collect();
+        ctx.list.rich_text(
             family.clone(),
             size,
             weight,
             false,
             TextAlign::Left,
-            line,
+            &row_text(buf, rows.row(buf, li)),
             text_origin_x,
             ty,
             99999.0,
-            clip,
             color_arr.into(),
+            spans,
         );
     }
 }
@@ -823,8 +1802,9 @@ fn draw_cursor<M: Clone + 'static>(
     line_height: f32,
     sc: f32,
     clip: Option<[f32; 4]>,
-    value: &str,
-    lines: &[&str],
+    buf: &Rope,
+    rows: &Rows,
+    spans: &RowSpans,
     font_id: crate::FontId,
     size: f32,
     weight: u16,
@@ -832,9 +1812,11 @@ fn draw_cursor<M: Clone + 'static>(
     text_color: Option<Color>,
     opacity: f32,
 ) {
-    let (line_idx, col) = offset_to_line_col(lines, cursor_pos);
-    let line = lines[line_idx];
-    let (cursor_x_rel, _) = ctx.fonts.measure_sized(&line[..col], font_id, size, weight);
+    let (line_idx, col) = rows.find(buf, cursor_pos);
+    let line = row_text(buf, rows.row(buf, line_idx));
+    let (cursor_x_rel, _) =
+        ctx.fonts
+            .measure_spans(&line[..col], font_id, size, weight, spans.of(line_idx));
 
     let ty_raw = text_origin_y_base + line_idx as f32 * line_height - scroll_snapped;
     let cursor_x = ((text_origin_x + cursor_x_rel) * sc).floor() / sc;
@@ -847,14 +1829,14 @@ fn draw_cursor<M: Clone + 'static>(
     if cursor_x < left_edge || cursor_x > right_edge {
         return;
     }
-    if let Some([_, cy, _, cy2]) = clip {
-        if cursor_y + cursor_h < cy || cursor_y > cy2 {
-            return;
-        }
+    if let Some([_, cy, _, cy2]) = clip
+        && (cursor_y + cursor_h < cy || cursor_y > cy2)
+    {
+        return;
     }
 
     let col_val = text_color.unwrap_or(Color::new(0.7, 0.75, 1.0, 1.0));
-    ctx.sr.draw_rect(
+    ctx.list.rect(
         cursor_x,
         cursor_y,
         2.0,
@@ -867,184 +1849,463 @@ fn draw_cursor<M: Clone + 'static>(
 
 // ─── text helpers ─────────────────────────────────────────────────────────────
 
-fn clamp_to_char_boundary(s: &str, mut pos: usize) -> usize {
-    pos = pos.min(s.len());
-    while pos > 0 && !s.is_char_boundary(pos) {
-        pos -= 1;
+fn clamp_to_char_boundary(buf: &Rope, pos: usize) -> usize {
+    let pos = pos.min(buf.len_bytes());
+    buf.char_to_byte(buf.byte_to_char(pos))
+}
+
+// replaces the bytes start..end of the buffer with text
+fn splice(buf: &mut Rope, start: usize, end: usize, text: &str) -> TextEdit {
+    let from = buf.byte_to_char(start);
+    buf.remove(from..buf.byte_to_char(end));
+    buf.insert(from, text);
+    TextEdit {
+        range: start..end,
+        text: text.to_string(),
     }
-    pos
 }
 
-fn word_start(value: &str, pos: usize) -> usize {
-    let mut i = pos.min(value.len());
-    while i > 0 {
-        if let Some((j, c)) = value[..i].char_indices().next_back() {
-            if c.is_alphanumeric() || c == '_' {
-                i = j;
+// Tab and Shift+Tab on a selection: unit goes on the front of each of its lines that
+// isn't empty, or comes off each that starts with it (or with less whitespace than
+// it). a selection ending at the start of a line leaves that line out. the cursor and
+// anchor stay with their text, one at the start of a line stays there
+fn indent_lines(
+    buf: &mut Rope,
+    cursor: &mut usize,
+    anchor: &mut Option<usize>,
+    unit: &str,
+    outdent: bool,
+) -> Option<TextEdit> {
+    let (start, end) = selection_range(*cursor, *anchor);
+    let first = buf.byte_to_line(start);
+    let mut last = buf.byte_to_line(end);
+    if last > first && buf.line_to_byte(last) == end {
+        last -= 1;
+    }
+    let from = buf.line_to_byte(first);
+    let to = line_range(buf, last).1;
+
+    let mut text = String::new();
+    // per line: where it started, where it starts now and how far its text moved
+    let mut moved = Vec::new();
+    for line in first..=last {
+        let (line_start, line_end) = line_range(buf, line);
+        let old = Cow::from(buf.byte_slice(line_start..line_end));
+        let shift = if !outdent {
+            if old.is_empty() {
+                0
             } else {
-                break;
+                unit.len() as isize
             }
+        } else if old.starts_with(unit) {
+            -(unit.len() as isize)
         } else {
-            break;
+            -(span(old.chars(), |c| c == ' ' || c == '\t').min(unit.len()) as isize)
+        };
+        moved.push((line_start, from + text.len(), shift));
+        if shift > 0 {
+            text.push_str(unit);
+        }
+        text.push_str(&old[(-shift).max(0) as usize..]);
+        if line < last {
+            text.push('\n');
         }
     }
-    i
-}
+    if buf.byte_slice(from..to) == text.as_str() {
+        return None;
+    }
 
-fn word_end(value: &str, pos: usize) -> usize {
-    let mut i = pos;
-    for (j, c) in value[pos..].char_indices() {
-        if c.is_alphanumeric() || c == '_' {
-            i = pos + j + c.len_utf8();
-        } else {
-            break;
+    let place = |pos: usize| match moved
+        .iter()
+        .rev()
+        .find(|(line_start, ..)| *line_start <= pos)
+    {
+        Some(&(line_start, new_start, shift)) if pos <= to => {
+            let col = (pos - line_start) as isize;
+            if col == 0 && shift > 0 {
+                new_start
+            } else {
+                new_start + (col + shift).max(0) as usize
+            }
         }
-    }
-    i
+        _ => pos - to + from + text.len(),
+    };
+    *anchor = anchor.map(&place);
+    *cursor = place(*cursor);
+    Some(splice(buf, from, to, &text))
 }
 
-fn selection_range(cursor: usize, anchor: Option<usize>) -> (usize, usize) {
-    match anchor {
-        Some(a) => (a.min(cursor), a.max(cursor)),
-        None => (cursor, cursor),
+// the smallest edit that turns old into new, for undo and redo which swap in a whole
+// snapshot. everything outside it is the same in both
+fn diff(old: &Rope, new: &Rope) -> TextEdit {
+    let prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old
+        .bytes_at(old.len_bytes())
+        .reversed()
+        .zip(new.bytes_at(new.len_bytes()).reversed())
+        .take(old.len_bytes().min(new.len_bytes()) - prefix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    // widened to whole chars, the shared bytes around it split them the same way
+    let start = clamp_to_char_boundary(old, prefix);
+    let mut end = old.len_bytes() - suffix;
+    if clamp_to_char_boundary(old, end) != end {
+        end = next_char(old, end);
+    }
+    let new_end = new.len_bytes() - (old.len_bytes() - end);
+    TextEdit {
+        range: start..end,
+        text: new.byte_slice(start..new_end).to_string(),
     }
 }
 
 // ─── state helpers (called from app.rs) ───────────────────────────────────────
 
+// a find and replace task, run when the editor next draws with its search up to date
+pub(crate) fn queue_search(state: &mut StateStore, id: &str, op: SearchOp) {
+    state
+        .get_or_default_mut::<TextEditorState>(id)
+        .search_ops
+        .push(op);
+}
+
+// a find and replace task against the editor's search. finding selects the match, which
+// scrolls it into view like the cursor. replacing swaps out the selected match and
+// selects the next, with no match selected it only finds one. returns what it changed
+fn search_op(state: &mut StateStore, id: &str, op: SearchOp) -> Option<TextEdit> {
+    let s = state.get_or_default_mut::<TextEditorState>(id);
+    let searching = s.search.as_mut()?;
+    searching.find(&s.buffer);
+    let (start, end) = selection_range(s.cursor, s.selection_anchor);
+    let before = Snapshot {
+        value: s.buffer.clone(),
+        cursor: s.cursor,
+        anchor: s.selection_anchor,
+    };
+    let previous = matches!(op, SearchOp::Previous);
+    let replace_all = matches!(op, SearchOp::ReplaceAll(_));
+    let edit = match op {
+        SearchOp::Next | SearchOp::Previous => None,
+        SearchOp::Replace(with) => {
+            if searching.contains(&s.buffer, start..end) {
+                let text = searching.replacement(&s.buffer, start..end, &with);
+                s.cursor = start + text.len();
+                s.selection_anchor = None;
+                Some(splice(&mut s.buffer, start, end, &text))
+            } else {
+                None
+            }
+        }
+        // one edit from the first match to the last, so one undo step
+        SearchOp::ReplaceAll(with) => {
+            let all = searching.all(&s.buffer);
+            let first = all.first()?.start;
+            let last = all.last()?.end;
+            let mut out = String::new();
+            let mut at = first;
+            for m in &all {
+                out.push_str(&Cow::from(s.buffer.byte_slice(at..m.start)));
+                out.push_str(&searching.replacement(&s.buffer, m.clone(), &with));
+                at = m.end;
+            }
+            s.cursor = first + out.len();
+            s.selection_anchor = None;
+            Some(splice(&mut s.buffer, first, last, &out))
+        }
+    };
+
+    let from = match &edit {
+        Some(edit) => {
+            s.edited(&before.value, edit.range.start, edit.range.end, &edit.text);
+            edit.range.start + edit.text.len()
+        }
+        None => end,
+    };
+    let searching = s.search.as_ref()?;
+    let found = if previous {
+        searching.previous(&s.buffer, start)
+    } else {
+        searching.next(&s.buffer, from)
+    };
+    // replace all leaves the cursor after what it replaced
+    if let Some(m) = found.filter(|_| !replace_all) {
+        s.selection_anchor = Some(m.start);
+        s.cursor = m.end;
+    }
+    let cursor = s.cursor;
+    if edit.is_some() {
+        state
+            .get_or_default_mut::<History<Rope>>(id)
+            .record(before, EditKind::Other, cursor);
+    }
+    edit
+}
+
+// the editor's callback for an edit: on_edit gets the edit, on_change the whole value
 pub(crate) fn call_callback<M: Clone + 'static>(
     state: &StateStore,
     id: &str,
-    value: String,
+    edit: TextEdit,
 ) -> Option<M> {
-    let cb = state.get_callback::<TextEditorCallback<M>>(id)?;
-    Some((cb.0)(value))
+    match state.get_callback::<TextEditorCallback<M>>(id)? {
+        TextEditorCallback::Change(cb) => {
+            let value = state.get::<TextEditorState>(id)?.buffer.to_string();
+            cb(value)
+        }
+        TextEditorCallback::Edit(cb) => cb(edit),
+    }
 }
 
-pub(crate) fn find_focused(state: &StateStore) -> Option<String> {
-    state.find::<TextEditorState, _>(|s| s.focused)
+// an editor with an indent unit keeps Tab for itself, see TextEditor::indent
+pub(crate) fn takes_tab(state: &StateStore, id: &str) -> bool {
+    state
+        .get::<TextEditorState>(id)
+        .is_some_and(|s| s.indent.is_some())
 }
 
-pub fn handle_key(state: &mut StateStore, id: &str, event: &Event, text: &str) -> Option<String> {
+pub fn handle_key(
+    state: &mut StateStore,
+    id: &str,
+    event: &Event,
+    text: &str,
+    clipboard: &mut dyn Clipboard,
+) -> Option<TextEdit> {
     let focused = state.get_or_default::<TextEditorState>(id).focused;
     if !focused {
         return None;
     }
 
-    let mut value = state
-        .get_or_default::<TextEditorState>(id)
-        .cached_value
-        .clone();
-    let mut cursor =
-        clamp_to_char_boundary(&value, state.get_or_default::<TextEditorState>(id).cursor);
-    let mut selection_anchor = state.get_or_default::<TextEditorState>(id).selection_anchor;
-    let mut changed = false;
-    let has_selection = selection_anchor.map_or(false, |a| a != cursor);
-
-    let lines: Vec<&str> = value.split('\n').collect();
+    let s = state.get_or_default_mut::<TextEditorState>(id);
+    // a cheap copy, the rope shares its text
+    let mut value = s.buffer.clone();
+    let mut cursor = clamp_to_char_boundary(&value, s.cursor);
+    let mut selection_anchor = s.selection_anchor;
+    let page_lines = s.page_lines;
+    let indent = s.indent.clone();
+    // rows as last drawn, put back at the end
+    let rows = std::mem::take(&mut s.rows);
+    let has_selection = selection_anchor.is_some_and(|a| a != cursor);
+    let before = Snapshot {
+        value: value.clone(),
+        cursor,
+        anchor: selection_anchor,
+    };
+    let mut kind = EditKind::Other;
+    // what this key changed, the editor only ever makes one change at a time
+    let mut edit = None;
+    let mut restored = false;
 
     match event {
         Event::KeyPressed {
             key: Key::Backspace,
-            ..
+            modifiers,
         } => {
+            kind = EditKind::Delete;
             if has_selection {
                 let (start, end) = selection_range(cursor, selection_anchor);
-                value.drain(start..end);
+                edit = Some(splice(&mut value, start, end, ""));
                 cursor = start;
                 selection_anchor = None;
-                changed = true;
             } else if cursor > 0 {
-                if let Some((i, _)) = value[..cursor].char_indices().next_back() {
-                    value.remove(i);
-                    cursor = i;
-                    changed = true;
-                }
+                // Ctrl+Backspace takes the whole word before the cursor
+                let start = if modifiers.ctrl {
+                    prev_word(&value, cursor)
+                } else {
+                    prev_char(&value, cursor)
+                };
+                edit = Some(splice(&mut value, start, cursor, ""));
+                cursor = start;
             }
         }
         Event::KeyPressed {
-            key: Key::Delete, ..
+            key: Key::Delete,
+            modifiers,
         } => {
+            kind = EditKind::Delete;
             if has_selection {
                 let (start, end) = selection_range(cursor, selection_anchor);
-                value.drain(start..end);
+                edit = Some(splice(&mut value, start, end, ""));
                 cursor = start;
                 selection_anchor = None;
-                changed = true;
-            } else if cursor < value.len() {
-                value.remove(cursor);
-                changed = true;
+            } else if cursor < value.len_bytes() {
+                let end = if modifiers.ctrl {
+                    next_word(&value, cursor)
+                } else {
+                    next_char(&value, cursor)
+                };
+                edit = Some(splice(&mut value, cursor, end, ""));
             }
         }
-        Event::KeyPressed { key: Key::Left, .. } => {
-            if has_selection {
-                cursor = selection_range(cursor, selection_anchor).0;
-                selection_anchor = None;
-            } else if cursor > 0 {
-                if let Some((i, _)) = value[..cursor].char_indices().next_back() {
-                    cursor = i;
+        // arrows collapse a selection to the side they point at, shift extends it
+        // instead, ctrl moves by words
+        Event::KeyPressed {
+            key: Key::Left,
+            modifiers,
+        } => {
+            let to = if has_selection && !modifiers.shift {
+                selection_range(cursor, selection_anchor).0
+            } else if modifiers.ctrl {
+                prev_word(&value, cursor)
+            } else {
+                prev_char(&value, cursor)
+            };
+            move_cursor(&mut cursor, &mut selection_anchor, to, modifiers.shift);
+        }
+        Event::KeyPressed {
+            key: Key::Right,
+            modifiers,
+        } => {
+            let to = if has_selection && !modifiers.shift {
+                selection_range(cursor, selection_anchor).1
+            } else if modifiers.ctrl {
+                next_word(&value, cursor)
+            } else {
+                next_char(&value, cursor)
+            };
+            move_cursor(&mut cursor, &mut selection_anchor, to, modifiers.shift);
+        }
+        // Up and Down keep the column, PageUp and PageDown move by a screenful, both by
+        // visual row. past the first or last row they go to the start or end of the text
+        Event::KeyPressed {
+            key: key @ (Key::Up | Key::Down | Key::PageUp | Key::PageDown),
+            modifiers,
+        } => {
+            let (li, col) = rows.find(&value, cursor);
+            let last = rows.len(&value) - 1;
+            let by = match key {
+                Key::Up | Key::Down => 1,
+                _ => page_lines.max(1),
+            };
+            let to = if matches!(key, Key::Up | Key::PageUp) {
+                match li {
+                    0 => 0,
+                    _ => rows.offset(&value, li.saturating_sub(by), col),
                 }
-            }
+            } else if li == last {
+                value.len_bytes()
+            } else {
+                rows.offset(&value, li + by, col)
+            };
+            move_cursor(&mut cursor, &mut selection_anchor, to, modifiers.shift);
         }
+        // Home and End go to the ends of the row, with ctrl to the ends of the text
         Event::KeyPressed {
-            key: Key::Right, ..
+            key: key @ (Key::Home | Key::End),
+            modifiers,
         } => {
+            let (li, _) = rows.find(&value, cursor);
+            let to = match (key, modifiers.ctrl) {
+                (Key::Home, true) => 0,
+                (Key::Home, false) => rows.row(&value, li).start,
+                (_, true) => value.len_bytes(),
+                (_, false) => rows.offset(&value, li, usize::MAX),
+            };
+            move_cursor(&mut cursor, &mut selection_anchor, to, modifiers.shift);
+        }
+        Event::KeyPressed {
+            key: Key::A,
+            modifiers,
+        } if modifiers.ctrl && !modifiers.alt => {
+            selection_anchor = Some(0);
+            cursor = value.len_bytes();
+        }
+        // taken without a selection too, rather than left to the arms below
+        #[allow(clippy::collapsible_match)]
+        Event::KeyPressed {
+            key: key @ (Key::C | Key::X),
+            modifiers,
+        } if modifiers.ctrl && !modifiers.alt => {
             if has_selection {
-                cursor = selection_range(cursor, selection_anchor).1;
-                selection_anchor = None;
-            } else if cursor < value.len() {
-                cursor = value[cursor..]
-                    .char_indices()
-                    .nth(1)
-                    .map(|(i, _)| cursor + i)
-                    .unwrap_or(value.len());
+                let (start, end) = selection_range(cursor, selection_anchor);
+                clipboard.write(&value.byte_slice(start..end).to_string());
+                if *key == Key::X {
+                    edit = Some(splice(&mut value, start, end, ""));
+                    cursor = start;
+                    selection_anchor = None;
+                }
             }
         }
-        Event::KeyPressed { key: Key::Up, .. } => {
-            selection_anchor = None;
-            let (li, col) = offset_to_line_col(&lines, cursor);
-            if li > 0 {
-                let prev_line = lines[li - 1];
-                let col_clamped = col.min(prev_line.len());
-                cursor = line_col_to_offset(&lines, li - 1, col_clamped);
+        Event::KeyPressed {
+            key: Key::V,
+            modifiers,
+        } if modifiers.ctrl && !modifiers.alt => {
+            if let Some(paste) = clipboard.read() {
+                let paste = paste.replace("\r\n", "\n").replace('\r', "\n");
+                let (start, end) = selection_range(cursor, selection_anchor);
+                edit = Some(splice(&mut value, start, end, &paste));
+                cursor = start + paste.len();
+                selection_anchor = None;
             }
         }
-        Event::KeyPressed { key: Key::Down, .. } => {
-            selection_anchor = None;
-            let (li, col) = offset_to_line_col(&lines, cursor);
-            if li < lines.len() - 1 {
-                let next_line = lines[li + 1];
-                let col_clamped = col.min(next_line.len());
-                cursor = line_col_to_offset(&lines, li + 1, col_clamped);
+        Event::KeyPressed {
+            key: key @ (Key::Z | Key::Y),
+            modifiers,
+        } if modifiers.ctrl && !modifiers.alt => {
+            // Ctrl+Z undoes, Ctrl+Shift+Z and Ctrl+Y redo
+            let history = state.get_or_default_mut::<History<Rope>>(id);
+            let snapshot = if *key == Key::Y || modifiers.shift {
+                history.redo(before.clone())
+            } else {
+                history.undo(before.clone())
+            };
+            if let Some(snapshot) = snapshot {
+                edit = Some(diff(&value, &snapshot.value));
+                value = snapshot.value;
+                cursor = snapshot.cursor;
+                selection_anchor = snapshot.anchor;
+                restored = true;
             }
         }
-        Event::KeyPressed { key: Key::Home, .. } => {
-            let (li, _) = offset_to_line_col(&lines, cursor);
-            cursor = line_col_to_offset(&lines, li, 0);
-            selection_anchor = None;
-        }
-        Event::KeyPressed { key: Key::End, .. } => {
-            let (li, _) = offset_to_line_col(&lines, cursor);
-            cursor = line_col_to_offset(&lines, li, lines[li].len());
-            selection_anchor = None;
+        // with an indent unit, Tab indents the selected lines or without a selection
+        // types the unit, Shift+Tab outdents the lines the selection or cursor is on
+        Event::KeyPressed {
+            key: Key::Tab,
+            modifiers,
+        } if !modifiers.ctrl => {
+            if let Some(unit) = &indent {
+                if has_selection || modifiers.shift {
+                    edit = indent_lines(
+                        &mut value,
+                        &mut cursor,
+                        &mut selection_anchor,
+                        unit,
+                        modifiers.shift,
+                    );
+                } else {
+                    kind = EditKind::Insert;
+                    edit = Some(splice(&mut value, cursor, cursor, unit));
+                    cursor += unit.len();
+                }
+            }
         }
         Event::KeyPressed { .. } => {
+            kind = EditKind::Insert;
             // Enter inserts newline; other printable text is inserted normally
             let insert = if text == "\r" || text == "\r\n" {
                 "\n"
             } else {
                 text
             };
-            if !insert.is_empty() {
-                if has_selection {
-                    let (start, end) = selection_range(cursor, selection_anchor);
-                    value.drain(start..end);
-                    cursor = start;
-                    selection_anchor = None;
+            let (start, end) = selection_range(cursor, selection_anchor);
+            // with an indent unit the new line starts with the whitespace the one it
+            // was split from starts with, as far as the cursor
+            let insert = match &indent {
+                Some(_) if insert == "\n" => {
+                    let line_start = value.line_to_byte(value.byte_to_line(start));
+                    let ws = span(value.chars_after(line_start), |c| c == ' ' || c == '\t');
+                    let ws = value.byte_slice(line_start..line_start + ws.min(start - line_start));
+                    Cow::from(format!("\n{ws}"))
                 }
-                value.insert_str(cursor, insert);
-                cursor += insert.len();
-                changed = true;
+                _ => Cow::from(insert),
+            };
+            if !insert.is_empty() {
+                edit = Some(splice(&mut value, start, end, &insert));
+                cursor = start + insert.len();
+                selection_anchor = None;
             }
         }
         _ => {}
@@ -1053,10 +2314,15 @@ pub fn handle_key(state: &mut StateStore, id: &str, event: &Event, text: &str) -
     let s = state.get_or_default_mut::<TextEditorState>(id);
     s.cursor = cursor;
     s.selection_anchor = selection_anchor;
-    if changed {
-        s.cached_value = value.clone();
-        Some(value)
-    } else {
-        None
+    s.rows = rows;
+    if let Some(edit) = &edit {
+        s.buffer = value;
+        s.edited(&before.value, edit.range.start, edit.range.end, &edit.text);
+        if !restored {
+            state
+                .get_or_default_mut::<History<Rope>>(id)
+                .record(before, kind, cursor);
+        }
     }
+    edit
 }
@@ -1,10 +1,22 @@
+use std::borrow::Cow;
+
 use taffy::prelude::*;
 
+use crate::clipboard::Clipboard;
 use crate::draw::{DrawCtx, check_interactions, is_outside, with_opacity};
+use crate::element::{Callback, Handler, Mapper, callback, map_handler};
 use crate::events::{Event, Key};
+use crate::focus::{self, FocusKind};
+use crate::ime;
 use crate::layout::{align_to_self, margin_to_rect_lpa, val_to_dimension};
 use crate::state::StateStore;
-use crate::{Align, Color, Edges, Fonts, Interactions, Layout, Margin, Style, TextAlign, Val};
+use crate::widgets::history::{EditKind, History, Snapshot};
+use crate::widgets::text_util::{
+    move_cursor, next_char, next_word, prev_char, prev_word, selection_range, word_end, word_start,
+};
+use crate::{
+    Align, Color, Edges, Fonts, Interactions, Layout, Margin, Style, TextAlign, Val, Widget,
+};
 
 // persisted state between frames
 #[derive(Default)]
@@ -15,15 +27,77 @@ pub struct TextInputState {
     pub selection_anchor: Option<usize>,
     pub dragging: bool,
     pub cached_value: String,
+    // the validator's message for the value, None while it's valid
+    pub error: Option<String>,
+    // the widget's modes as of the last draw, for handle_key
+    pub(crate) password: bool,
+    pub(crate) max_length: Option<usize>,
+    pub(crate) numeric: Option<Numeric>,
+}
+
+// what a validator makes of a value: fine, kept but flagged with a message, or turned
+// away so the key that would have made it does nothing
+#[derive(Clone, Debug, PartialEq)]
+pub enum Validation {
+    Valid,
+    Invalid(String),
+    Reject,
+}
+
+// numeric mode's range and what Up and Down add or take away
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Numeric {
+    min: f64,
+    max: f64,
+    step: f64,
+}
+
+impl Numeric {
+    // typing is let through as long as it's on its way to a number, the range is only
+    // kept to when stepping
+    fn accepts(&self, text: &str) -> bool {
+        let digits = match text.strip_prefix('-') {
+            Some(rest) if self.min < 0.0 => rest,
+            _ => text,
+        };
+        digits.chars().all(|c| c.is_ascii_digit() || c == '.') && digits.matches('.').count() <= 1
+    }
+
+    // the value a step up or down from text, with as many decimals as the step has
+    fn step(&self, text: &str, up: bool) -> String {
+        let current = text.parse::<f64>().unwrap_or(0.0);
+        let next = if up {
+            current + self.step
+        } else {
+            current - self.step
+        };
+        let next = next.clamp(self.min, self.max);
+        let decimals = self.step.to_string().split('.').nth(1).map_or(0, str::len);
+        format!("{next:.decimals$}")
+    }
 }
 
+// the validator, registered per frame like the callbacks
+pub(crate) struct TextInputValidator(Option<Validator>);
+
+type Validator = Box<dyn Fn(&str) -> Validation>;
+
 // callback stored per-frame in the callbacks map
-pub(crate) struct TextInputCallback<M>(pub Box<dyn Fn(String) -> M>);
+pub(crate) struct TextInputCallback<M>(pub Callback<String, M>);
+
+// the rest of the input's callbacks, registered alongside it
+pub(crate) struct TextInputEvents<M> {
+    on_submit: Option<Callback<String, M>>,
+    on_focus: Option<Handler<M>>,
+    on_blur: Option<Handler<M>>,
+}
 
 pub struct TextInput<M: Clone + 'static> {
     pub id: Option<String>,
+    pub key: Option<String>,
     pub placeholder: String,
     pub placeholder_color: Option<Color>,
+    pub tab_index: i32,
     pub font: Option<String>,
     pub font_size: Option<f32>,
     pub font_weight: u16,
@@ -31,7 +105,17 @@ pub struct TextInput<M: Clone + 'static> {
     pub layout: Layout,
     pub style: Style,
     pub interactions: Interactions<M>,
-    pub on_change: Option<Box<dyn Fn(String) -> M>>,
+    pub on_change: Option<Callback<String, M>>,
+    pub on_submit: Option<Callback<String, M>>,
+    pub on_focus: Option<Handler<M>>,
+    pub on_blur: Option<Handler<M>>,
+    pub password: bool,
+    pub revealed: bool,
+    pub max_length: Option<usize>,
+    pub numeric: Option<(f64, f64, f64)>,
+    pub validate: Option<Validator>,
+    pub error_color: Option<Color>,
+    pub on_error: Option<Callback<Option<String>, M>>,
     pub(crate) x: f32,
     pub(crate) y: f32,
     pub(crate) w: f32,
@@ -42,8 +126,10 @@ impl<M: Clone + 'static> TextInput<M> {
     pub fn new() -> Self {
         Self {
             id: None,
+            key: None,
             placeholder: String::new(),
             placeholder_color: None,
+            tab_index: 0,
             font: None,
             font_size: None,
             font_weight: 400,
@@ -52,6 +138,16 @@ impl<M: Clone + 'static> TextInput<M> {
             style: Style::default(),
             interactions: Interactions::default(),
             on_change: None,
+            on_submit: None,
+            on_focus: None,
+            on_blur: None,
+            password: false,
+            revealed: false,
+            max_length: None,
+            numeric: None,
+            validate: None,
+            error_color: None,
+            on_error: None,
             x: 0.0,
             y: 0.0,
             w: 0.0,
@@ -64,13 +160,52 @@ impl<M: Clone + 'static> TextInput<M> {
         self
     }
 
-    fn require_id(&self) -> &str {
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    pub(crate) fn map<B: Clone + 'static>(self, f: &Mapper<M, B>) -> TextInput<B> {
+        TextInput {
+            id: self.id,
+            key: self.key,
+            placeholder: self.placeholder,
+            placeholder_color: self.placeholder_color,
+            tab_index: self.tab_index,
+            font: self.font,
+            font_size: self.font_size,
+            font_weight: self.font_weight,
+            value: self.value,
+            layout: self.layout,
+            style: self.style,
+            interactions: self.interactions.map(f),
+            on_change: map_handler(self.on_change, f),
+            on_submit: map_handler(self.on_submit, f),
+            on_focus: self.on_focus.map(|h| h.map(f)),
+            on_blur: self.on_blur.map(|h| h.map(f)),
+            password: self.password,
+            revealed: self.revealed,
+            max_length: self.max_length,
+            numeric: self.numeric,
+            validate: self.validate,
+            error_color: self.error_color,
+            on_error: map_handler(self.on_error, f),
+            x: self.x,
+            y: self.y,
+            w: self.w,
+            h: self.h,
+        }
+    }
+
+    pub(crate) fn require_id(&self) -> &str {
         self.id
             .as_deref()
-            .expect("TextInput requires an id — use .id(\"my_input\")")
+            .expect("a TextInput is given an id before layout, see key.rs")
     }
+}
 
-    pub fn draw(&mut self, ctx: &mut DrawCtx<M>) {
+impl<M: Clone + 'static> Widget<M> for TextInput<M> {
+    fn draw(&mut self, ctx: &mut DrawCtx<M>) {
         let id = self.require_id().to_string();
         let id = id.as_str();
         let (x, y, w, h) = (self.x, self.y, self.w, self.h);
@@ -89,9 +224,39 @@ impl<M: Clone + 'static> TextInput<M> {
         if let Some(cb) = self.on_change.take() {
             ctx.state.set_callback(id, TextInputCallback(cb));
         }
+        ctx.state.set_callback(
+            id,
+            TextInputEvents {
+                on_submit: self.on_submit.take(),
+                on_focus: self.on_focus.clone(),
+                on_blur: self.on_blur.clone(),
+            },
+        );
+
+        // the modes handle_key needs, and what the validator makes of the value
+        let error = self.validate.as_ref().and_then(|f| match f(value_str) {
+            Validation::Invalid(message) => Some(message),
+            _ => None,
+        });
+        ctx.state
+            .set_callback(id, TextInputValidator(self.validate.take()));
+        let s = ctx.state.get_or_default_mut::<TextInputState>(id);
+        s.password = self.password;
+        s.max_length = self.max_length;
+        s.numeric = self
+            .numeric
+            .map(|(min, max, step)| Numeric { min, max, step });
+        if s.error != error {
+            s.error = error.clone();
+            if let Some(f) = &self.on_error {
+                ctx.actions.extend(f(error.clone()));
+            }
+        }
+        let error_color = error
+            .is_some()
+            .then(|| self.error_color.unwrap_or(Color::new(0.9, 0.35, 0.35, 1.0)));
 
-        let hovered =
-            ctx.mouse.x >= x && ctx.mouse.x <= x + w && ctx.mouse.y >= y && ctx.mouse.y <= y + h;
+        let hovered = self.interactions.hovered;
 
         if hovered {
             *ctx.cursor = Some(crate::draw::Cursor::Text);
@@ -111,7 +276,10 @@ impl<M: Clone + 'static> TextInput<M> {
             .cursor
             .min(value_str.len());
 
-        draw_background(ctx, x, y, w, h, &self.style, focused, hovered);
+        draw_background(ctx, x, y, w, h, &self.style, focused, error_color);
+        if focus::ring_visible(ctx.state, id) {
+            focus::draw_ring(ctx.list, x, y, w, h, self.style.border_radius);
+        }
 
         let font_id = self
             .font
@@ -143,10 +311,37 @@ impl<M: Clone + 'static> TextInput<M> {
         let sc = ctx.scale_factor;
         let text_origin_x = ((x + pad_l) * sc).floor() / sc;
 
+        // an IME composition in progress shows inline at the cursor, see ime.rs
+        let cursor_pos = ctx.state.get_or_default::<TextInputState>(id).cursor;
+        let composed = if focused {
+            ime::compose(ctx.state, value_str, cursor_pos)
+        } else {
+            None
+        };
+        let shown = composed.as_ref().map_or(value_str, |c| c.text.as_str());
+        let caret = composed
+            .as_ref()
+            .map_or(cursor_pos, |c| c.caret.unwrap_or(c.end));
+        // a password is drawn as dots, everything measured is measured on those
+        let masked = self.password && !self.revealed;
+        let drawn = if masked {
+            Cow::Owned(mask(shown))
+        } else {
+            Cow::Borrowed(shown)
+        };
+        let at = |text: &str, pos: usize| {
+            if masked {
+                masked_offset(text, pos)
+            } else {
+                pos
+            }
+        };
+
         update_scroll(
             ctx.state,
             id,
-            value_str,
+            &drawn,
+            at(shown, caret),
             font_id,
             size,
             self.font_weight,
@@ -160,6 +355,7 @@ impl<M: Clone + 'static> TextInput<M> {
             ctx,
             id,
             value_str,
+            masked,
             font_id,
             size,
             self.font_weight,
@@ -170,15 +366,20 @@ impl<M: Clone + 'static> TextInput<M> {
 
         // re-read after mouse handling may have mutated state this frame
         let cursor_pos = ctx.state.get_or_default::<TextInputState>(id).cursor;
+        let caret = if composed.is_some() {
+            caret
+        } else {
+            cursor_pos
+        };
         let (cursor_x_abs, _) =
             ctx.fonts
-                .measure_sized(&value_str[..cursor_pos], font_id, size, self.font_weight);
+                .measure_sized(&drawn[..at(shown, caret)], font_id, size, self.font_weight);
         let cursor_x_snapped = (cursor_x_abs * sc).floor() / sc;
         let selection_anchor = ctx
             .state
             .get_or_default::<TextInputState>(id)
             .selection_anchor;
-        let has_selection = selection_anchor.map_or(false, |a| a != cursor_pos);
+        let has_selection = selection_anchor.is_some_and(|a| a != cursor_pos);
 
         draw_selection(
             ctx,
@@ -191,38 +392,69 @@ impl<M: Clone + 'static> TextInput<M> {
             ty,
             th,
             sc,
-            value_str,
+            &drawn,
             font_id,
             size,
             self.font_weight,
-            cursor_pos,
-            selection_anchor,
-            focused,
+            at(shown, cursor_pos),
+            selection_anchor.map(|a| at(shown, a)),
+            focused && composed.is_none(),
             self.style.opacity,
         );
 
+        if let Some(clip) = text_clip {
+            ctx.list.push_clip(clip);
+        }
         draw_text(
             ctx,
-            x,
-            w,
-            pad_l,
-            pad_r,
             text_origin_x,
             scroll_snapped,
             ty,
-            sc,
-            text_clip,
             family,
             size,
             self.font_weight,
-            value_str,
+            &drawn,
             &self.placeholder,
             self.placeholder_color,
             self.style.text_color,
             self.style.opacity,
         );
+        // the preedit is underlined, it isn't part of the value yet
+        if let Some(c) = &composed {
+            let weight = self.font_weight;
+            let (x0, _) =
+                ctx.fonts
+                    .measure_sized(&drawn[..at(shown, c.start)], font_id, size, weight);
+            let (x1, _) =
+                ctx.fonts
+                    .measure_sized(&drawn[..at(shown, c.end)], font_id, size, weight);
+            let col = self
+                .style
+                .text_color
+                .unwrap_or(Color::new(0.92, 0.92, 0.95, 1.0));
+            ctx.list.rect(
+                text_origin_x + x0 - scroll_snapped,
+                ty + th,
+                x1 - x0,
+                1.0,
+                with_opacity(col.to_array(), self.style.opacity),
+                [0.0; 4],
+                0.0,
+            );
+        }
+        if text_clip.is_some() {
+            ctx.list.pop_clip();
+        }
 
-        if focused && !has_selection {
+        if focused {
+            let caret_x = text_origin_x + cursor_x_snapped - scroll_snapped;
+            ime::set_caret(ctx.state, [caret_x, ty, 1.0, th]);
+        }
+        let show_caret = match &composed {
+            Some(c) => c.caret.is_some(),
+            None => !has_selection,
+        };
+        if focused && show_caret {
             draw_cursor(
                 ctx,
                 x,
@@ -240,10 +472,10 @@ impl<M: Clone + 'static> TextInput<M> {
             );
         }
 
-        check_interactions(&self.interactions, x, y, w, h, ctx);
+        check_interactions(&self.interactions, ctx);
     }
 
-    pub fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
+    fn layout_node(&self, taffy: &mut TaffyTree<()>, fonts: &mut Fonts) -> NodeId {
         let font_id = self
             .font
             .as_deref()
@@ -277,13 +509,31 @@ impl<M: Clone + 'static> TextInput<M> {
             .unwrap()
     }
 
-    pub fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
+    fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
         self.x = x;
         self.y = y;
         self.w = w;
         self.h = h;
     }
 
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        [self.x, self.y, self.w, self.h]
+    }
+
+    fn interactions(&self) -> Option<&Interactions<M>> {
+        Some(&self.interactions)
+    }
+
+    fn interactions_mut(&mut self) -> Option<&mut Interactions<M>> {
+        Some(&mut self.interactions)
+    }
+}
+
+impl<M: Clone + 'static> TextInput<M> {
     // layout builder methods
     pub fn width(mut self, v: Val) -> Self {
         self.layout.width = v;
@@ -359,17 +609,77 @@ impl<M: Clone + 'static> TextInput<M> {
         self
     }
     pub fn on_change(mut self, f: impl Fn(String) -> M + 'static) -> Self {
-        self.on_change = Some(Box::new(f));
+        self.on_change = Some(callback(f));
+        self
+    }
+    // Enter while focused, with the value
+    pub fn on_submit(mut self, f: impl Fn(String) -> M + 'static) -> Self {
+        self.on_submit = Some(callback(f));
+        self
+    }
+    pub fn on_focus(mut self, msg: M) -> Self {
+        self.on_focus = Some(Handler::new(msg));
+        self
+    }
+    // losing focus to a click elsewhere, Tab, Escape or Task::blur
+    pub fn on_blur(mut self, msg: M) -> Self {
+        self.on_blur = Some(Handler::new(msg));
+        self
+    }
+
+    // draws a dot for each char unless revealed, and never copies or cuts
+    pub fn password(mut self, password: bool) -> Self {
+        self.password = password;
+        self
+    }
+    // shows a password input's text, still without copy or cut, e.g. for an eye toggle
+    pub fn revealed(mut self, revealed: bool) -> Self {
+        self.revealed = revealed;
+        self
+    }
+    // at most this many chars, typing past it does nothing and pastes are cut short
+    pub fn max_length(mut self, chars: usize) -> Self {
+        self.max_length = Some(chars);
+        self
+    }
+    // only takes a number, which Up and Down step by step within min..=max
+    pub fn numeric(mut self, min: f64, max: f64, step: f64) -> Self {
+        self.numeric = Some((min, max, step));
+        self
+    }
+    // checked against every value: Reject turns away the edit that made it, Invalid
+    // keeps it but gives the input an error border and passes the message to on_error
+    pub fn validate(mut self, f: impl Fn(&str) -> Validation + 'static) -> Self {
+        self.validate = Some(Box::new(f));
+        self
+    }
+    pub fn error_color(mut self, color: Color) -> Self {
+        self.error_color = Some(color);
+        self
+    }
+    // called when the validator's message changes, None once the value is valid again
+    pub fn on_error(mut self, f: impl Fn(Option<String>) -> M + 'static) -> Self {
+        self.on_error = Some(callback(f));
         self
     }
 
     // interaction builder methods
+    // order Tab visits it in, see focus.rs. negative leaves it out
+    pub fn tab_index(mut self, index: i32) -> Self {
+        self.tab_index = index;
+        self
+    }
+
     pub fn on_click(mut self, msg: M) -> Self {
-        self.interactions.on_click = Some(msg);
+        self.interactions.on_click = Some(Handler::new(msg));
         self
     }
     pub fn on_hover(mut self, msg: M) -> Self {
-        self.interactions.on_hover = Some(msg);
+        self.interactions.on_hover = Some(Handler::new(msg));
+        self
+    }
+    pub fn stop_propagation(mut self) -> Self {
+        self.interactions.stop_propagation = true;
         self
     }
 }
@@ -384,8 +694,8 @@ fn update_focus_and_drag(
     pressed: bool,
 ) {
     if just_pressed {
+        focus::press(state, id, FocusKind::TextInput, hovered);
         let s = state.get_or_default_mut::<TextInputState>(id);
-        s.focused = hovered;
         s.selection_anchor = None;
         s.dragging = hovered;
     }
@@ -394,6 +704,7 @@ fn update_focus_and_drag(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_background<M: Clone + 'static>(
     ctx: &mut DrawCtx<M>,
     x: f32,
@@ -402,22 +713,15 @@ fn draw_background<M: Clone + 'static>(
     h: f32,
     style: &Style,
     focused: bool,
-    hovered: bool,
+    error_color: Option<Color>,
 ) {
     let bg = style
         .background
         .unwrap_or(Color::new(0.13, 0.13, 0.17, 1.0));
-    let bg = if focused {
-        bg
-        // bg.lighten(0.05)
-    } else if hovered {
-        bg
-        // bg.lighten(0.03)
-    } else {
-        bg
-    };
 
-    let (border_col, border_w) = if let Some(col) = style.border_color {
+    let (border_col, border_w) = if let Some(col) = error_color {
+        (col, style.border_thickness.max(1.5))
+    } else if let Some(col) = style.border_color {
         (col, style.border_thickness)
     } else {
         let col = if focused {
@@ -427,17 +731,8 @@ fn draw_background<M: Clone + 'static>(
         };
         (col, 1.5)
     };
-    let border_col = if focused {
-        border_col
-        // border_col.lighten(0.05)
-    } else if hovered {
-        border_col
-        // border_col.lighten(0.03)
-    } else {
-        border_col
-    };
 
-    ctx.sr.draw_rounded_rect(
+    ctx.list.rounded_rect(
         x,
         y,
         w,
@@ -453,18 +748,19 @@ fn draw_background<M: Clone + 'static>(
     );
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_scroll(
     state: &mut StateStore,
     id: &str,
     value: &str,
+    caret: usize,
     font_id: crate::FontId,
     size: f32,
     weight: u16,
     text_area_w: f32,
     fonts: &mut Fonts,
 ) {
-    let cursor_pos = state.get_or_default::<TextInputState>(id).cursor;
-    let (cursor_x, _) = fonts.measure_sized(&value[..cursor_pos], font_id, size, weight);
+    let (cursor_x, _) = fonts.measure_sized(&value[..caret], font_id, size, weight);
     let s = state.get_or_default_mut::<TextInputState>(id);
     if cursor_x - s.scroll_offset > text_area_w - 2.0 {
         s.scroll_offset = cursor_x - text_area_w + 2.0;
@@ -477,10 +773,12 @@ fn update_scroll(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_mouse<M: Clone + 'static>(
     ctx: &mut DrawCtx<M>,
     id: &str,
     value: &str,
+    masked: bool,
     font_id: crate::FontId,
     size: f32,
     weight: u16,
@@ -489,13 +787,24 @@ fn handle_mouse<M: Clone + 'static>(
     hovered: bool,
 ) {
     let dragging = ctx.state.get_or_default::<TextInputState>(id).dragging;
+    // hit testing a password is done on its dots
+    let hit_test = |fonts: &mut Fonts, click_x: f32| {
+        if masked {
+            let dots = mask(value);
+            let hit = hit_test_cursor(&dots, click_x, fonts, font_id, size, weight);
+            unmasked_offset(value, hit)
+        } else {
+            hit_test_cursor(value, click_x, fonts, font_id, size, weight)
+        }
+    };
 
     if ctx.mouse.left_just_pressed && hovered {
         let click_x = ctx.mouse.x - text_origin_x + scroll;
-        let hit = hit_test_cursor(value, click_x, ctx.fonts, font_id, size, weight);
+        let hit = hit_test(ctx.fonts, click_x);
         let state = ctx.state.get_or_default_mut::<TextInputState>(id);
         match ctx.mouse.left_click_count {
-            2 => {
+            // a password's words aren't given away, it's all selected
+            2 if !masked => {
                 let ws = word_start(value, hit);
                 let we = word_end(value, hit);
                 if ws < we {
@@ -506,7 +815,7 @@ fn handle_mouse<M: Clone + 'static>(
                     state.selection_anchor = None;
                 }
             }
-            3 => {
+            2 | 3 => {
                 state.selection_anchor = Some(0);
                 state.cursor = value.len();
             }
@@ -517,7 +826,7 @@ fn handle_mouse<M: Clone + 'static>(
         }
     } else if dragging && ctx.mouse.left_pressed {
         let click_x = ctx.mouse.x - text_origin_x + scroll;
-        let hit = hit_test_cursor(value, click_x, ctx.fonts, font_id, size, weight);
+        let hit = hit_test(ctx.fonts, click_x);
         let state = ctx.state.get_or_default_mut::<TextInputState>(id);
         if hit != state.cursor {
             if state.selection_anchor.is_none() {
@@ -567,7 +876,7 @@ fn draw_selection<M: Clone + 'static>(
     let sx = (text_origin_x + sel_start_x - scroll_snapped).max(x + pad_l);
     let ex = (text_origin_x + sel_end_x - scroll_snapped).min(x + w - pad_r);
     if ex > sx {
-        ctx.sr.draw_rect(
+        ctx.list.rect(
             sx,
             (ty * sc).floor() / sc,
             ex - sx,
@@ -582,15 +891,9 @@ fn draw_selection<M: Clone + 'static>(
 #[allow(clippy::too_many_arguments)]
 fn draw_text<M: Clone + 'static>(
     ctx: &mut DrawCtx<M>,
-    x: f32,
-    w: f32,
-    pad_l: f32,
-    pad_r: f32,
     text_origin_x: f32,
     scroll_snapped: f32,
     ty: f32,
-    _sc: f32,
-    text_clip: Option<[f32; 4]>,
     family: String,
     size: f32,
     weight: u16,
@@ -602,8 +905,7 @@ fn draw_text<M: Clone + 'static>(
 ) {
     if value.is_empty() {
         let col = placeholder_color.unwrap_or(Color::new(0.45, 0.45, 0.5, 1.0));
-        ctx.tr.draw(
-            &mut ctx.fonts.font_system,
+        ctx.list.text(
             family,
             size,
             weight,
@@ -613,13 +915,11 @@ fn draw_text<M: Clone + 'static>(
             text_origin_x,
             ty,
             99999.0,
-            text_clip,
             with_opacity(col.to_array(), opacity).into(),
         );
     } else {
         let col = text_color.unwrap_or(Color::new(0.92, 0.92, 0.95, 1.0));
-        ctx.tr.draw(
-            &mut ctx.fonts.font_system,
+        ctx.list.text(
             family,
             size,
             weight,
@@ -629,12 +929,12 @@ fn draw_text<M: Clone + 'static>(
             text_origin_x - scroll_snapped,
             ty,
             99999.0,
-            text_clip,
             with_opacity(col.to_array(), opacity).into(),
         );
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_cursor<M: Clone + 'static>(
     ctx: &mut DrawCtx<M>,
     x: f32,
@@ -655,7 +955,7 @@ fn draw_cursor<M: Clone + 'static>(
     let cursor_y = (ty * sc).floor() / sc;
     let cursor_h = (th * sc).ceil() / sc;
     if cursor_x >= x + pad_l && cursor_x <= x + w - pad_r {
-        ctx.sr.draw_rect(
+        ctx.list.rect(
             cursor_x,
             cursor_y,
             1.0,
@@ -669,32 +969,22 @@ fn draw_cursor<M: Clone + 'static>(
 
 // text helpers
 
-fn word_start(value: &str, pos: usize) -> usize {
-    let mut i = pos.min(value.len());
-    while i > 0 {
-        if let Some((j, c)) = value[..i].char_indices().next_back() {
-            if c.is_alphanumeric() || c == '_' {
-                i = j;
-            } else {
-                break;
-            }
-        } else {
-            break;
-        }
-    }
-    i
+// what a password is drawn as, a dot per char
+const DOT: char = '•';
+
+fn mask(text: &str) -> String {
+    text.chars().map(|_| DOT).collect()
 }
 
-fn word_end(value: &str, pos: usize) -> usize {
-    let mut i = pos;
-    for (j, c) in value[pos..].char_indices() {
-        if c.is_alphanumeric() || c == '_' {
-            i = pos + j + c.len_utf8();
-        } else {
-            break;
-        }
-    }
-    i
+// a byte offset into text as one into its dots, and back
+fn masked_offset(text: &str, pos: usize) -> usize {
+    text[..pos].chars().count() * DOT.len_utf8()
+}
+
+fn unmasked_offset(text: &str, pos: usize) -> usize {
+    text.char_indices()
+        .nth(pos / DOT.len_utf8())
+        .map_or(text.len(), |(i, _)| i)
 }
 
 fn hit_test_cursor(
@@ -733,11 +1023,16 @@ fn hit_test_cursor(
     }
 }
 
-fn selection_range(cursor: usize, anchor: Option<usize>) -> (usize, usize) {
-    match anchor {
-        Some(a) => (a.min(cursor), a.max(cursor)),
-        None => (cursor, cursor),
-    }
+// as much of text as fits in max_length chars once it replaces start..end of value
+fn fit<'a>(text: &'a str, value: &str, start: usize, end: usize, max: Option<usize>) -> &'a str {
+    let Some(max) = max else {
+        return text;
+    };
+    let kept = value[..start].chars().count() + value[end..].chars().count();
+    let room = max.saturating_sub(kept);
+    text.char_indices()
+        .nth(room)
+        .map_or(text, |(i, _)| &text[..i])
 }
 
 // state helpers used by app.rs for keyboard handling
@@ -748,14 +1043,41 @@ pub(crate) fn call_callback<M: Clone + 'static>(
     value: String,
 ) -> Option<M> {
     let cb = state.get_callback::<TextInputCallback<M>>(id)?;
-    Some((cb.0)(value))
+    (cb.0)(value)
+}
+
+// Enter in the input: on_submit with its value
+pub(crate) fn call_submit<M: Clone + 'static>(state: &StateStore, id: &str) -> Option<M> {
+    let cb = state
+        .get_callback::<TextInputEvents<M>>(id)?
+        .on_submit
+        .as_ref()?;
+    let value = state.get::<TextInputState>(id)?.cached_value.clone();
+    cb(value)
 }
 
-pub(crate) fn find_focused(state: &StateStore) -> Option<String> {
-    state.find::<TextInputState, _>(|s| s.focused)
+// the input gained or lost focus
+pub(crate) fn call_focus_callback<M: Clone + 'static>(
+    state: &StateStore,
+    id: &str,
+    focused: bool,
+) -> Option<M> {
+    let events = state.get_callback::<TextInputEvents<M>>(id)?;
+    let handler = if focused {
+        &events.on_focus
+    } else {
+        &events.on_blur
+    };
+    handler.as_ref()?.fire()
 }
 
-pub fn handle_key(state: &mut StateStore, id: &str, event: &Event, text: &str) -> Option<String> {
+pub fn handle_key(
+    state: &mut StateStore,
+    id: &str,
+    event: &Event,
+    text: &str,
+    clipboard: &mut dyn Clipboard,
+) -> Option<String> {
     let focused = state.get_or_default::<TextInputState>(id).focused;
     if !focused {
         return None;
@@ -770,14 +1092,24 @@ pub fn handle_key(state: &mut StateStore, id: &str, event: &Event, text: &str) -
         .cursor
         .min(value.len());
     let mut selection_anchor = state.get_or_default::<TextInputState>(id).selection_anchor;
+    let s = state.get_or_default::<TextInputState>(id);
+    let (password, max_length, numeric) = (s.password, s.max_length, s.numeric);
     let mut changed = false;
-    let has_selection = selection_anchor.map_or(false, |a| a != cursor);
+    let has_selection = selection_anchor.is_some_and(|a| a != cursor);
+    let before = Snapshot {
+        value: value.clone(),
+        cursor,
+        anchor: selection_anchor,
+    };
+    let mut kind = EditKind::Other;
+    let mut restored = false;
 
     match event {
         Event::KeyPressed {
             key: Key::Backspace,
-            ..
+            modifiers,
         } => {
+            kind = EditKind::Delete;
             if has_selection {
                 let (start, end) = selection_range(cursor, selection_anchor);
                 value.drain(start..end);
@@ -785,16 +1117,22 @@ pub fn handle_key(state: &mut StateStore, id: &str, event: &Event, text: &str) -
                 selection_anchor = None;
                 changed = true;
             } else if cursor > 0 {
-                if let Some((i, _)) = value[..cursor].char_indices().next_back() {
-                    value.remove(i);
-                    cursor = i;
-                    changed = true;
-                }
+                // Ctrl+Backspace takes the whole word before the cursor
+                let start = if modifiers.ctrl {
+                    prev_word(value.as_str(), cursor)
+                } else {
+                    prev_char(value.as_str(), cursor)
+                };
+                value.drain(start..cursor);
+                cursor = start;
+                changed = true;
             }
         }
         Event::KeyPressed {
-            key: Key::Delete, ..
+            key: Key::Delete,
+            modifiers,
         } => {
+            kind = EditKind::Delete;
             if has_selection {
                 let (start, end) = selection_range(cursor, selection_anchor);
                 value.drain(start..end);
@@ -802,46 +1140,135 @@ pub fn handle_key(state: &mut StateStore, id: &str, event: &Event, text: &str) -
                 selection_anchor = None;
                 changed = true;
             } else if cursor < value.len() {
-                value.remove(cursor);
+                let end = if modifiers.ctrl {
+                    next_word(value.as_str(), cursor)
+                } else {
+                    next_char(value.as_str(), cursor)
+                };
+                value.drain(cursor..end);
                 changed = true;
             }
         }
-        Event::KeyPressed { key: Key::Left, .. } => {
-            if has_selection {
-                cursor = selection_range(cursor, selection_anchor).0;
-                selection_anchor = None;
-            } else if cursor > 0 {
-                if let Some((i, _)) = value[..cursor].char_indices().next_back() {
-                    cursor = i;
-                }
-            }
+        // arrows collapse a selection to the side they point at, shift extends it
+        // instead, ctrl moves by words
+        Event::KeyPressed {
+            key: Key::Left,
+            modifiers,
+        } => {
+            let to = if has_selection && !modifiers.shift {
+                selection_range(cursor, selection_anchor).0
+            } else if modifiers.ctrl {
+                prev_word(value.as_str(), cursor)
+            } else {
+                prev_char(value.as_str(), cursor)
+            };
+            move_cursor(&mut cursor, &mut selection_anchor, to, modifiers.shift);
         }
         Event::KeyPressed {
-            key: Key::Right, ..
+            key: Key::Right,
+            modifiers,
         } => {
-            if has_selection {
-                cursor = selection_range(cursor, selection_anchor).1;
-                selection_anchor = None;
-            } else if cursor < value.len() {
-                cursor = value[cursor..]
-                    .char_indices()
-                    .nth(1)
-                    .map(|(i, _)| cursor + i)
-                    .unwrap_or(value.len());
-            }
+            let to = if has_selection && !modifiers.shift {
+                selection_range(cursor, selection_anchor).1
+            } else if modifiers.ctrl {
+                next_word(value.as_str(), cursor)
+            } else {
+                next_char(value.as_str(), cursor)
+            };
+            move_cursor(&mut cursor, &mut selection_anchor, to, modifiers.shift);
         }
-        Event::KeyPressed { key: Key::Home, .. } => {
-            cursor = 0;
-            selection_anchor = None;
+        Event::KeyPressed {
+            key: Key::Home,
+            modifiers,
+        } => {
+            move_cursor(&mut cursor, &mut selection_anchor, 0, modifiers.shift);
+        }
+        Event::KeyPressed {
+            key: Key::End,
+            modifiers,
+        } => {
+            let to = value.len();
+            move_cursor(&mut cursor, &mut selection_anchor, to, modifiers.shift);
+        }
+        Event::KeyPressed {
+            key: Key::A,
+            modifiers,
+        } if modifiers.ctrl && !modifiers.alt => {
+            selection_anchor = Some(0);
+            cursor = value.len();
         }
-        Event::KeyPressed { key: Key::End, .. } => {
+        // Up and Down step a number
+        Event::KeyPressed {
+            key: key @ (Key::Up | Key::Down),
+            ..
+        } if numeric.is_some() => {
+            value = numeric.unwrap().step(&value, *key == Key::Up);
             cursor = value.len();
             selection_anchor = None;
+            changed = true;
+        }
+        // a password can't be copied out
+        // taken without a selection too, rather than left to the arms below
+        #[allow(clippy::collapsible_match)]
+        Event::KeyPressed {
+            key: key @ (Key::C | Key::X),
+            modifiers,
+        } if modifiers.ctrl && !modifiers.alt => {
+            if has_selection && !password {
+                let (start, end) = selection_range(cursor, selection_anchor);
+                clipboard.write(&value[start..end]);
+                if *key == Key::X {
+                    value.drain(start..end);
+                    cursor = start;
+                    selection_anchor = None;
+                    changed = true;
+                }
+            }
+        }
+        Event::KeyPressed {
+            key: Key::V,
+            modifiers,
+        } if modifiers.ctrl && !modifiers.alt => {
+            if let Some(paste) = clipboard.read() {
+                // single line, so line breaks become spaces
+                let paste = paste.replace("\r\n", " ").replace(['\r', '\n'], " ");
+                let (start, end) = selection_range(cursor, selection_anchor);
+                let paste = fit(&paste, &value, start, end, max_length);
+                if has_selection {
+                    value.drain(start..end);
+                    cursor = start;
+                    selection_anchor = None;
+                }
+                value.insert_str(cursor, paste);
+                cursor += paste.len();
+                changed = true;
+            }
+        }
+        Event::KeyPressed {
+            key: key @ (Key::Z | Key::Y),
+            modifiers,
+        } if modifiers.ctrl && !modifiers.alt => {
+            // Ctrl+Z undoes, Ctrl+Shift+Z and Ctrl+Y redo
+            let history = state.get_or_default_mut::<History>(id);
+            let snapshot = if *key == Key::Y || modifiers.shift {
+                history.redo(before.clone())
+            } else {
+                history.undo(before.clone())
+            };
+            if let Some(snapshot) = snapshot {
+                value = snapshot.value;
+                cursor = snapshot.cursor;
+                selection_anchor = snapshot.anchor;
+                changed = true;
+                restored = true;
+            }
         }
         Event::KeyPressed { .. } => {
+            kind = EditKind::Insert;
+            let (start, end) = selection_range(cursor, selection_anchor);
+            let text = fit(text, &value, start, end, max_length);
             if !text.is_empty() && text != "\r" && text != "\n" && text != "\r\n" {
                 if has_selection {
-                    let (start, end) = selection_range(cursor, selection_anchor);
                     value.drain(start..end);
                     cursor = start;
                     selection_anchor = None;
@@ -854,6 +1281,24 @@ pub fn handle_key(state: &mut StateStore, id: &str, event: &Event, text: &str) -
         _ => {}
     }
 
+    // an edit numeric mode or the validator won't have does nothing at all
+    if changed && !restored {
+        let accepted = numeric.is_none_or(|n| n.accepts(&value))
+            && state
+                .get_callback::<TextInputValidator>(id)
+                .and_then(|v| v.0.as_ref())
+                .is_none_or(|f| f(&value) != Validation::Reject);
+        if !accepted {
+            return None;
+        }
+    }
+
+    if changed && !restored {
+        state
+            .get_or_default_mut::<History>(id)
+            .record(before, kind, cursor);
+    }
+
     let s = state.get_or_default_mut::<TextInputState>(id);
     s.cursor = cursor;
     s.selection_anchor = selection_anchor;
@@ -0,0 +1,91 @@
+use ropey::Rope;
+
+// Text helpers
+// cursor movement and word boundaries for the text widgets, over either of the buffers
+// they keep their value in: the input's string or the editor's rope. positions are
+// byte offsets on char boundaries
+
+pub(crate) trait Chars {
+    // the chars before pos, nearest first
+    fn chars_before(&self, pos: usize) -> impl Iterator<Item = char> + '_;
+
+    fn chars_after(&self, pos: usize) -> impl Iterator<Item = char> + '_;
+}
+
+impl Chars for str {
+    fn chars_before(&self, pos: usize) -> impl Iterator<Item = char> + '_ {
+        self[..pos].chars().rev()
+    }
+
+    fn chars_after(&self, pos: usize) -> impl Iterator<Item = char> + '_ {
+        self[pos..].chars()
+    }
+}
+
+impl Chars for Rope {
+    fn chars_before(&self, pos: usize) -> impl Iterator<Item = char> + '_ {
+        self.chars_at(self.byte_to_char(pos)).reversed()
+    }
+
+    fn chars_after(&self, pos: usize) -> impl Iterator<Item = char> + '_ {
+        self.chars_at(self.byte_to_char(pos))
+    }
+}
+
+// bytes taken up by the chars the iterator yields while f holds
+pub(crate) fn span(chars: impl Iterator<Item = char>, f: impl Fn(char) -> bool) -> usize {
+    chars.take_while(|&c| f(c)).map(char::len_utf8).sum()
+}
+
+pub(crate) fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+pub(crate) fn word_start<T: Chars + ?Sized>(text: &T, pos: usize) -> usize {
+    pos - span(text.chars_before(pos), is_word_char)
+}
+
+pub(crate) fn word_end<T: Chars + ?Sized>(text: &T, pos: usize) -> usize {
+    pos + span(text.chars_after(pos), is_word_char)
+}
+
+pub(crate) fn prev_char<T: Chars + ?Sized>(text: &T, pos: usize) -> usize {
+    pos - text.chars_before(pos).next().map_or(0, char::len_utf8)
+}
+
+pub(crate) fn next_char<T: Chars + ?Sized>(text: &T, pos: usize) -> usize {
+    pos + text.chars_after(pos).next().map_or(0, char::len_utf8)
+}
+
+// Ctrl+Left: back over any spaces and punctuation, then to the start of that word
+pub(crate) fn prev_word<T: Chars + ?Sized>(text: &T, pos: usize) -> usize {
+    word_start(
+        text,
+        pos - span(text.chars_before(pos), |c| !is_word_char(c)),
+    )
+}
+
+// Ctrl+Right: over any spaces and punctuation, then to the end of that word
+pub(crate) fn next_word<T: Chars + ?Sized>(text: &T, pos: usize) -> usize {
+    word_end(
+        text,
+        pos + span(text.chars_after(pos), |c| !is_word_char(c)),
+    )
+}
+
+// moves the cursor, extending keeps the far end of the selection where it was
+pub(crate) fn move_cursor(cursor: &mut usize, anchor: &mut Option<usize>, to: usize, extend: bool) {
+    if extend {
+        anchor.get_or_insert(*cursor);
+    } else {
+        *anchor = None;
+    }
+    *cursor = to;
+}
+
+pub(crate) fn selection_range(cursor: usize, anchor: Option<usize>) -> (usize, usize) {
+    match anchor {
+        Some(a) => (a.min(cursor), a.max(cursor)),
+        None => (cursor, cursor),
+    }
+}
@@ -0,0 +1,262 @@
+// golden screenshot tests, rendered on the cpu so they run without a gpu.
+// run with BENTO_UPDATE_GOLDEN=1 to (re)write the pngs in tests/golden/, a test whose
+// png is missing fails until then
+
+use std::path::PathBuf;
+
+use bento::*;
+
+// per channel slack for float rounding differences between platforms
+const TOLERANCE: u8 = 2;
+
+const BACKGROUND: Color = Color::new(0.35, 0.35, 0.4, 1.0);
+
+fn check_golden(name: &str, image: &Image) {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let path = dir.join(format!("{name}.png"));
+
+    if std::env::var_os("BENTO_UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(&dir).unwrap();
+        image.save_png(&path).unwrap();
+        return;
+    }
+    assert!(
+        path.exists(),
+        "{name} has no golden at {}, run with BENTO_UPDATE_GOLDEN=1 to write it",
+        path.display()
+    );
+
+    let expected = Image::load_png(&path).unwrap();
+    match image.diff(&expected, TOLERANCE) {
+        Some(0) => {}
+        mismatch => {
+            let actual = std::env::temp_dir().join(format!("{name}.actual.png"));
+            image.save_png(&actual).unwrap();
+            panic!(
+                "{name} does not match {} ({} pixels differ), actual output written to {}",
+                path.display(),
+                match mismatch {
+                    Some(n) => n.to_string(),
+                    None => format!(
+                        "size {}x{} vs {}x{}",
+                        image.width(),
+                        image.height(),
+                        expected.width(),
+                        expected.height()
+                    ),
+                },
+                actual.display()
+            );
+        }
+    }
+}
+
+fn panel() -> Element<()> {
+    column(vec![
+        row(vec![
+            rect(Color::new(0.9, 0.3, 0.3, 1.0))
+                .width(px(40.0))
+                .height(px(40.0)),
+            rect(Color::new(0.3, 0.8, 0.4, 1.0))
+                .width(px(40.0))
+                .height(px(40.0))
+                .border_radius(12.0),
+            rect(Color::new(0.3, 0.4, 0.9, 1.0))
+                .width(px(40.0))
+                .height(px(40.0))
+                .border_radius(20.0)
+                .border(Color::WHITE, 3.0),
+        ])
+        .gap(12.0),
+        rect(Color::new(0.95, 0.95, 0.97, 1.0))
+            .width(px(136.0))
+            .height(px(30.0))
+            .border_radius(6.0)
+            .shadow(Color::new(0.0, 0.0, 0.0, 0.6), 0.0, 4.0, 8.0),
+        // the inner rect is twice as wide as its parent and must be cut at its edge
+        row(vec![
+            rect(Color::new(0.9, 0.7, 0.2, 1.0))
+                .width(px(120.0))
+                .height(px(20.0))
+                .border_radius(10.0)
+                .shrink(0.0),
+        ])
+        .width(px(60.0))
+        .overflow_hidden(),
+    ])
+    .gap(16.0)
+    .padding(Edges::all(16.0))
+}
+
+#[test]
+fn shapes() {
+    let mut fonts = Fonts::new();
+    let image = render_to_image(&mut panel(), &mut fonts, 200.0, 200.0, 1.0, BACKGROUND);
+    check_golden("shapes", &image);
+}
+
+#[test]
+fn shapes_hidpi() {
+    let mut fonts = Fonts::new();
+    let image = render_to_image(&mut panel(), &mut fonts, 200.0, 200.0, 2.0, BACKGROUND);
+    assert_eq!((image.width(), image.height()), (400, 400));
+    check_golden("shapes_hidpi", &image);
+}
+
+// text in a font that's checked in, so it renders the same whatever is installed
+#[test]
+fn labels() {
+    let mut fonts = Fonts::new();
+    fonts.load(include_bytes!("fonts/Inter-Regular.ttf").to_vec());
+    fonts.add("inter", "Inter", 14.0).default();
+    let mut tree: Element<()> = column(vec![
+        text("Golden text", Color::WHITE).font_size(24.0),
+        text("and at the default size", Color::WHITE),
+        button("Button").width(px(100.0)).height(px(32.0)),
+        text_input()
+            .placeholder("placeholder")
+            .width(px(160.0))
+            .height(px(32.0)),
+    ])
+    .gap(8.0)
+    .padding(Edges::all(12.0));
+    let image = render_to_image(&mut tree, &mut fonts, 200.0, 160.0, 1.0, BACKGROUND);
+    check_golden("labels", &image);
+}
+
+#[test]
+fn png_round_trip() {
+    let mut fonts = Fonts::new();
+    let image = render_to_image(&mut panel(), &mut fonts, 64.0, 48.0, 1.0, BACKGROUND);
+    let decoded = Image::decode_png(&image.encode_png()).unwrap();
+    assert_eq!(decoded, image);
+}
+
+// a later sibling has to cover earlier text, not just earlier shapes
+#[test]
+fn overlay_covers_text() {
+    let panel = Color::new(0.2, 0.6, 0.9, 1.0);
+    let mut tree: Element<()> = column(vec![
+        text("covered by the panel", Color::WHITE).font_size(24.0),
+        rect(panel).absolute().inset(Edges::all(0.0)),
+    ])
+    .width(px(200.0))
+    .height(px(40.0));
+
+    let mut fonts = Fonts::new();
+    let image = render_to_image(&mut tree, &mut fonts, 200.0, 40.0, 1.0, BACKGROUND);
+    // stay off the outermost pixels, the panel's own antialiasing lives there
+    let expected = image.pixel(100, 20);
+    for y in 1..39 {
+        for x in 1..199 {
+            assert_eq!(image.pixel(x, y), expected, "text shows through at {x},{y}");
+        }
+    }
+}
+
+// an anchored overlay escapes its parent's clip and paints over later siblings
+#[test]
+fn overlay_escapes_clip() {
+    let menu = Color::new(0.9, 0.3, 0.3, 1.0);
+    let mut tree: Element<()> = column(vec![
+        row(vec![
+            rect(Color::WHITE)
+                .width(px(40.0))
+                .height(px(20.0))
+                .id("trigger"),
+            overlay(rect(menu).width(px(80.0)).height(px(40.0))).anchor("trigger"),
+        ])
+        .height(px(20.0))
+        .overflow_hidden(),
+        rect(Color::new(0.2, 0.6, 0.9, 1.0))
+            .width(px(100.0))
+            .height(px(60.0)),
+    ]);
+
+    let mut fonts = Fonts::new();
+    let image = render_to_image(&mut tree, &mut fonts, 100.0, 100.0, 1.0, BACKGROUND);
+    // the menu hangs below the trigger, outside the clipped row and over the blue sibling
+    let expected = image.pixel(10, 30);
+    assert!(expected[0] > expected[2], "menu is not on top at 10,30");
+    for (x, y) in [(70, 22), (70, 50), (40, 58)] {
+        assert_eq!(image.pixel(x, y), expected, "menu missing at {x},{y}");
+    }
+    // past the menu the sibling shows as usual
+    assert_ne!(image.pixel(90, 50), expected);
+}
+
+// text is bounded by what it lays out, not the width it was given, so a shape beside a
+// label doesn't split the frame into another layer
+#[test]
+fn text_layers_by_extent() {
+    let mut fonts = Fonts::new();
+    fonts.load(include_bytes!("fonts/Inter-Regular.ttf").to_vec());
+    let label = |list: &mut DisplayList| {
+        list.text(
+            "Inter".into(),
+            14.0,
+            400,
+            false,
+            TextAlign::Left,
+            "label",
+            10.0,
+            10.0,
+            99999.0,
+            Color::WHITE,
+        )
+    };
+    let white = Color::WHITE.to_array();
+
+    let mut list = DisplayList::new();
+    label(&mut list);
+    list.rect(120.0, 10.0, 40.0, 20.0, white, white, 0.0);
+    assert_eq!(list.layers(&mut fonts).len(), 1);
+
+    // over the glyphs it still has to go on top
+    let mut list = DisplayList::new();
+    label(&mut list);
+    list.rect(20.0, 10.0, 40.0, 20.0, white, white, 0.0);
+    assert_eq!(list.layers(&mut fonts).len(), 2);
+}
+
+// widgets that keep state need an id, offscreen they get one from their path like in a window
+#[test]
+fn stateful_widgets_without_ids() {
+    let mut tree: Element<()> = column(vec![
+        button("ok").width(px(60.0)).height(px(30.0)),
+        text_input().width(px(100.0)).height(px(30.0)),
+        column(vec![rect(Color::WHITE).width(px(40.0)).height(px(80.0))])
+            .height(px(30.0))
+            .overflow_scroll(),
+    ]);
+
+    let mut fonts = Fonts::new();
+    let image = render_to_image(&mut tree, &mut fonts, 100.0, 100.0, 1.0, BACKGROUND);
+    // the button paints, the window shows past it
+    assert_ne!(image.pixel(2, 2), image.pixel(90, 2));
+    assert_eq!(image.pixel(20, 70), [255, 255, 255, 255]);
+}
+
+// a component is built by the layout pass, so it renders offscreen too
+struct Swatch;
+
+impl Component<()> for Swatch {
+    type State = ();
+    type Event = ();
+
+    fn view(&self, _: &()) -> Element<()> {
+        rect(Color::BLACK).width(px(20.0)).height(px(20.0))
+    }
+
+    fn update(&self, _: &mut (), _: ()) -> Option<()> {
+        None
+    }
+}
+
+#[test]
+fn component_offscreen() {
+    let mut tree: Element<()> = row(vec![component(Swatch)]);
+    let mut fonts = Fonts::new();
+    let image = render_to_image(&mut tree, &mut fonts, 40.0, 40.0, 1.0, Color::WHITE);
+    assert_eq!(image.pixel(10, 10), [0, 0, 0, 255]);
+}
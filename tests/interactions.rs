@@ -0,0 +1,2177 @@
+// pointer and keyboard interaction, driven through the headless harness
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use bento::*;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Action {
+    Row,
+    Button,
+    Panel,
+}
+
+#[derive(Default)]
+struct Clicks {
+    log: Vec<Action>,
+    stop: bool,
+    panel: bool,
+}
+
+impl App for Clicks {
+    type Action = Action;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<Action> {
+        let mut button = button("ok")
+            .width(px(50.0))
+            .height(px(50.0))
+            .on_click(Action::Button);
+        if self.stop {
+            button = button.stop_propagation();
+        }
+        let mut children = vec![
+            row(vec![button])
+                .width(px(200.0))
+                .height(px(100.0))
+                .on_click(Action::Row),
+        ];
+        if self.panel {
+            children.push(
+                rect(Color::WHITE)
+                    .absolute()
+                    .width(px(100.0))
+                    .height(px(100.0))
+                    .on_click(Action::Panel),
+            );
+        }
+        column(children)
+    }
+
+    fn update(&mut self, action: Action) -> Vec<Task<Action>> {
+        self.log.push(action);
+        vec![]
+    }
+}
+
+// clicks far enough apart that they never count as a double click
+fn click(h: &mut TestHarness<Clicks>, x: f32, y: f32) -> Vec<Action> {
+    h.advance(Duration::from_secs(1));
+    h.click(x, y);
+    std::mem::take(&mut h.app_mut().log)
+}
+
+#[test]
+fn click_bubbles_to_ancestors() {
+    let mut h = TestHarness::<Clicks>::new();
+    assert_eq!(click(&mut h, 20.0, 20.0), vec![Action::Button, Action::Row]);
+    assert_eq!(click(&mut h, 150.0, 20.0), vec![Action::Row]);
+}
+
+#[test]
+fn stop_propagation_keeps_click_on_target() {
+    let mut h = TestHarness::<Clicks>::new();
+    h.app_mut().stop = true;
+    assert_eq!(click(&mut h, 20.0, 20.0), vec![Action::Button]);
+}
+
+#[test]
+fn covered_elements_do_not_react() {
+    let mut h = TestHarness::<Clicks>::new();
+    h.app_mut().panel = true;
+    assert_eq!(click(&mut h, 20.0, 20.0), vec![Action::Panel]);
+    assert_eq!(click(&mut h, 150.0, 20.0), vec![Action::Row]);
+}
+
+#[test]
+fn overlay_is_hit_before_content() {
+    struct Menu(Vec<Action>);
+    impl App for Menu {
+        type Action = Action;
+        fn new() -> Self {
+            Self(vec![])
+        }
+        fn view(&self) -> Element<Action> {
+            column(vec![
+                button("under")
+                    .width(px(200.0))
+                    .height(px(100.0))
+                    .id("under")
+                    .on_click(Action::Button),
+                overlay(rect(Color::WHITE).width(px(80.0)).height(px(40.0)))
+                    .anchor("under")
+                    .placement(Placement::Over)
+                    .on_click(Action::Panel),
+                overlay(
+                    rect(Color::WHITE)
+                        .width(px(80.0))
+                        .height(px(40.0))
+                        .on_click(Action::Panel),
+                )
+                .anchor("under")
+                .placement(Placement::Over)
+                .offset(100.0, 0.0),
+            ])
+        }
+        fn update(&mut self, action: Action) -> Vec<Task<Action>> {
+            self.0.push(action);
+            vec![]
+        }
+    }
+
+    let mut h = TestHarness::<Menu>::new();
+    // the first overlay has no handler of its own but still covers the button
+    h.click(20.0, 20.0);
+    assert!(h.app().0.is_empty());
+    h.advance(Duration::from_secs(1));
+    h.click(120.0, 20.0);
+    assert_eq!(h.app().0, vec![Action::Panel]);
+    h.advance(Duration::from_secs(1));
+    h.click(20.0, 80.0);
+    assert_eq!(h.app().0, vec![Action::Panel, Action::Button]);
+}
+
+// ten 40px rows in a 100px tall scroll container
+struct List {
+    clicked: Vec<usize>,
+    tasks: Vec<Task<usize>>,
+}
+
+impl App for List {
+    type Action = usize;
+
+    fn new() -> Self {
+        Self {
+            clicked: vec![],
+            tasks: vec![],
+        }
+    }
+
+    fn view(&self) -> Element<usize> {
+        let rows = (0..10)
+            .map(|i| {
+                button(&format!("row {i}"))
+                    .id(&format!("row{i}"))
+                    .width(px(180.0))
+                    .height(px(40.0))
+                    .shrink(0.0)
+                    .on_click(i)
+            })
+            .collect();
+        column(rows)
+            .id("list")
+            .width(px(200.0))
+            .height(px(100.0))
+            .overflow_scroll()
+    }
+
+    fn update(&mut self, action: usize) -> Vec<Task<usize>> {
+        self.clicked.push(action);
+        std::mem::take(&mut self.tasks)
+    }
+}
+
+// which row is at the top left of the list
+fn top_row(h: &mut TestHarness<List>) -> usize {
+    h.advance(Duration::from_secs(1));
+    h.click(20.0, 5.0);
+    h.app_mut().clicked.pop().unwrap()
+}
+
+#[test]
+fn wheel_scrolls_and_clamps() {
+    let mut h = TestHarness::<List>::new();
+    h.mouse_move(50.0, 50.0);
+    h.scroll(0.0, -120.0);
+    assert_eq!(top_row(&mut h), 3);
+    // 400px of rows in 100px, so 300px is as far as it goes
+    h.scroll(0.0, -1000.0);
+    assert_eq!(top_row(&mut h), 7);
+    h.scroll(0.0, 1000.0);
+    assert_eq!(top_row(&mut h), 0);
+}
+
+#[test]
+fn wheel_outside_does_nothing() {
+    let mut h = TestHarness::<List>::new();
+    h.mouse_move(300.0, 50.0);
+    h.scroll(0.0, -120.0);
+    assert_eq!(top_row(&mut h), 0);
+}
+
+#[test]
+fn scroll_tasks() {
+    let mut h = TestHarness::<List>::new();
+    h.app_mut().tasks = vec![Task::scroll_to("list", 0.0, 200.0)];
+    assert_eq!(top_row(&mut h), 0);
+    h.frame();
+    assert_eq!(top_row(&mut h), 5);
+
+    h.app_mut().tasks = vec![Task::scroll_into_view("row1")];
+    h.click(20.0, 5.0);
+    h.frame();
+    assert_eq!(top_row(&mut h), 1);
+}
+
+#[test]
+fn scrollbar_drags() {
+    let mut h = TestHarness::<List>::new();
+    // the thumb is 25px of a 100px track at the right edge
+    h.mouse_move(196.0, 10.0);
+    h.mouse_press(MouseButton::Left);
+    h.mouse_move(196.0, 47.5);
+    h.mouse_release(MouseButton::Left);
+    h.frame();
+    assert_eq!(top_row(&mut h), 3);
+    assert!(h.app().clicked.is_empty());
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum FormAction {
+    Name(String),
+    Submit,
+}
+
+#[derive(Default)]
+struct Form {
+    name: String,
+    submitted: usize,
+}
+
+impl App for Form {
+    type Action = FormAction;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<FormAction> {
+        column(vec![
+            text_input()
+                .id("name")
+                .value(&self.name)
+                .on_change(FormAction::Name),
+            text_input().id("skipped").tab_index(-1),
+            button("submit").on_click(FormAction::Submit),
+            text_input().id("first").tab_index(1),
+        ])
+    }
+
+    fn update(&mut self, action: FormAction) -> Vec<Task<FormAction>> {
+        match action {
+            FormAction::Name(name) => self.name = name,
+            FormAction::Submit => self.submitted += 1,
+        }
+        vec![]
+    }
+}
+
+#[test]
+fn tab_cycles_focus() {
+    let mut h = TestHarness::<Form>::new();
+    assert_eq!(h.focused(), None);
+    // positive tab indices come first, then tree order, skipping negative ones
+    let mut seen = vec![];
+    for _ in 0..4 {
+        h.key(Key::Tab);
+        seen.push(h.focused().unwrap().to_string());
+    }
+    assert_eq!(seen, ["first", "name", "#/2", "first"]);
+
+    let shift = Modifiers {
+        shift: true,
+        ..Default::default()
+    };
+    h.key_with(Key::Tab, shift);
+    assert_eq!(h.focused(), Some("#/2"));
+    h.key_with(Key::Tab, shift);
+    assert_eq!(h.focused(), Some("name"));
+}
+
+#[test]
+fn keyboard_only_form() {
+    let mut h = TestHarness::<Form>::new();
+    h.key(Key::Tab);
+    h.key(Key::Tab);
+    h.type_text("ada");
+    assert_eq!(h.app().name, "ada");
+    h.key(Key::Tab);
+    h.key(Key::Enter);
+    h.key(Key::Space);
+    assert_eq!(h.app().submitted, 2);
+    // space on a button doesn't type into the input that had focus before
+    assert_eq!(h.app().name, "ada");
+}
+
+#[test]
+fn click_moves_focus() {
+    let mut h = TestHarness::<Form>::new();
+    h.key(Key::Tab);
+    assert_eq!(h.focused(), Some("first"));
+    h.click(10.0, 10.0);
+    assert_eq!(h.focused(), Some("name"));
+    h.advance(Duration::from_secs(1));
+    h.click(700.0, 500.0);
+    assert_eq!(h.focused(), None);
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum LoginAction {
+    Name(String),
+    Submit(String),
+    Focus,
+    Blur,
+}
+
+#[derive(Default)]
+struct Login {
+    name: String,
+    log: Vec<LoginAction>,
+}
+
+impl App for Login {
+    type Action = LoginAction;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<LoginAction> {
+        column(vec![
+            text_input()
+                .id("user")
+                .value(&self.name)
+                .on_change(LoginAction::Name)
+                .on_submit(LoginAction::Submit)
+                .on_focus(LoginAction::Focus)
+                .on_blur(LoginAction::Blur),
+            text_input().id("password"),
+        ])
+    }
+
+    fn update(&mut self, action: LoginAction) -> Vec<Task<LoginAction>> {
+        match action {
+            LoginAction::Name(name) => self.name = name,
+            other => self.log.push(other),
+        }
+        vec![]
+    }
+}
+
+#[test]
+fn submit_focus_and_blur() {
+    let mut h = TestHarness::<Login>::new();
+    h.click(10.0, 10.0);
+    h.type_text("ada");
+    h.key(Key::Enter);
+    assert_eq!(h.app().name, "ada");
+    h.key(Key::Escape);
+    assert_eq!(h.focused(), None);
+    assert_eq!(
+        std::mem::take(&mut h.app_mut().log),
+        [
+            LoginAction::Focus,
+            LoginAction::Submit("ada".into()),
+            LoginAction::Blur
+        ]
+    );
+
+    // focus moved with the keyboard counts too
+    h.key(Key::Tab);
+    h.key(Key::Tab);
+    assert_eq!(h.focused(), Some("password"));
+    assert_eq!(h.app().log, [LoginAction::Focus, LoginAction::Blur]);
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum FieldAction {
+    Password(String),
+    Age(String),
+    Code(String),
+    User(String),
+    UserError(Option<String>),
+}
+
+#[derive(Default)]
+struct Fields {
+    password: String,
+    age: String,
+    code: String,
+    user: String,
+    errors: Vec<Option<String>>,
+}
+
+impl App for Fields {
+    type Action = FieldAction;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<FieldAction> {
+        column(vec![
+            text_input()
+                .id("password")
+                .value(&self.password)
+                .password(true)
+                .on_change(FieldAction::Password),
+            text_input()
+                .id("age")
+                .value(&self.age)
+                .numeric(0.0, 10.0, 0.5)
+                .on_change(FieldAction::Age),
+            text_input()
+                .id("code")
+                .value(&self.code)
+                .max_length(3)
+                .on_change(FieldAction::Code),
+            text_input()
+                .id("user")
+                .value(&self.user)
+                .validate(|user| {
+                    if user.contains(' ') {
+                        Validation::Reject
+                    } else if user.len() < 3 {
+                        Validation::Invalid("too short".into())
+                    } else {
+                        Validation::Valid
+                    }
+                })
+                .on_error(FieldAction::UserError)
+                .on_change(FieldAction::User),
+        ])
+    }
+
+    fn update(&mut self, action: FieldAction) -> Vec<Task<FieldAction>> {
+        match action {
+            FieldAction::Password(v) => self.password = v,
+            FieldAction::Age(v) => self.age = v,
+            FieldAction::Code(v) => self.code = v,
+            FieldAction::User(v) => self.user = v,
+            FieldAction::UserError(e) => self.errors.push(e),
+        }
+        vec![]
+    }
+}
+
+#[test]
+fn input_modes() {
+    let mut h = TestHarness::<Fields>::new();
+    // an empty user is already too short
+    assert_eq!(h.app().errors, [Some("too short".to_string())]);
+
+    // a password shows as dots and can't be copied
+    h.key(Key::Tab);
+    h.type_text("hunter2");
+    assert_eq!(h.app().password, "hunter2");
+    assert!(texts(&h).contains(&"•••••••".to_string()));
+    assert!(!texts(&h).contains(&"hunter2".to_string()));
+    h.set_clipboard("before");
+    h.key_with(Key::A, ctrl());
+    h.key_with(Key::X, ctrl());
+    assert_eq!(h.clipboard().as_deref(), Some("before"));
+    assert_eq!(h.app().password, "hunter2");
+
+    // numbers only, stepped with the arrows and kept in range
+    h.key(Key::Tab);
+    h.type_text("4a");
+    assert_eq!(h.app().age, "4");
+    h.key(Key::Up);
+    assert_eq!(h.app().age, "4.5");
+    h.key(Key::Down);
+    h.key(Key::Down);
+    assert_eq!(h.app().age, "3.5");
+    h.key_with(Key::A, ctrl());
+    h.type_text("9.8");
+    h.key(Key::Up);
+    assert_eq!(h.app().age, "10.0");
+
+    h.key(Key::Tab);
+    h.type_text("abcdef");
+    assert_eq!(h.app().code, "abc");
+
+    // rejected edits don't happen, flagged ones do and report the error
+    h.key(Key::Tab);
+    h.type_text("a b");
+    assert_eq!(h.app().user, "ab");
+    h.type_text("c");
+    assert_eq!(h.app().user, "abc");
+    assert_eq!(h.app().errors, [Some("too short".to_string()), None]);
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum DialogAction {
+    Open,
+    Focus(&'static str),
+    Blur,
+    Next,
+}
+
+#[derive(Default)]
+struct Dialog {
+    open: bool,
+}
+
+impl App for Dialog {
+    type Action = DialogAction;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<DialogAction> {
+        let mut children = vec![button("open").id("open").on_click(DialogAction::Open)];
+        if self.open {
+            children.push(text_input().id("search"));
+            children.push(text_input().id("filter"));
+        }
+        column(children)
+    }
+
+    fn update(&mut self, action: DialogAction) -> Vec<Task<DialogAction>> {
+        match action {
+            DialogAction::Open => {
+                self.open = true;
+                vec![Task::focus("search")]
+            }
+            DialogAction::Focus(id) => vec![Task::focus(id)],
+            DialogAction::Blur => vec![Task::blur()],
+            DialogAction::Next => vec![Task::focus_next()],
+        }
+    }
+
+    fn event(&mut self, event: Event) -> Option<DialogAction> {
+        match event {
+            Event::KeyPressed { key, .. } => match key {
+                Key::Escape => Some(DialogAction::Blur),
+                Key::Down => Some(DialogAction::Next),
+                Key::Home => Some(DialogAction::Focus("open")),
+                Key::End => Some(DialogAction::Focus("missing")),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn focus_tasks() {
+    let mut h = TestHarness::<Dialog>::new();
+    // the dialog's input only exists from the frame after the click, so does its focus
+    h.click(10.0, 10.0);
+    h.frame();
+    assert_eq!(h.focused(), Some("search"));
+
+    for (key, expected) in [
+        (Key::Down, Some("filter")),
+        (Key::Escape, None),
+        (Key::Home, Some("open")),
+        (Key::End, Some("open")),
+    ] {
+        h.key(key);
+        assert_eq!(h.focused(), expected, "after {key:?}");
+    }
+}
+
+fn ctrl() -> Modifiers {
+    Modifiers {
+        ctrl: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn copy_cut_paste() {
+    let mut h = TestHarness::<Form>::new();
+    h.click(10.0, 10.0);
+    h.type_text("ada lovelace");
+    // double click selects the word under the pointer
+    h.advance(Duration::from_secs(1));
+    h.click(10.0, 10.0);
+    h.click(10.0, 10.0);
+
+    h.key_with(Key::C, ctrl());
+    assert_eq!(h.clipboard().as_deref(), Some("ada"));
+    assert_eq!(h.app().name, "ada lovelace");
+    h.key_with(Key::X, ctrl());
+    assert_eq!(h.app().name, " lovelace");
+
+    h.key(Key::End);
+    h.key_with(Key::V, ctrl());
+    assert_eq!(h.app().name, " lovelaceada");
+    // the input is a single line, pasted line breaks become spaces
+    h.set_clipboard("\nbyron\r\n");
+    h.key_with(Key::V, ctrl());
+    assert_eq!(h.app().name, " lovelaceada byron ");
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ClipAction {
+    Copy,
+    Paste,
+    Pasted(Option<String>),
+}
+
+#[derive(Default)]
+struct Clip {
+    pasted: Vec<Option<String>>,
+}
+
+impl App for Clip {
+    type Action = ClipAction;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<ClipAction> {
+        column(vec![])
+    }
+
+    fn update(&mut self, action: ClipAction) -> Vec<Task<ClipAction>> {
+        match action {
+            ClipAction::Copy => vec![Task::write_clipboard("copied")],
+            ClipAction::Paste => vec![Task::read_clipboard(ClipAction::Pasted)],
+            ClipAction::Pasted(text) => {
+                self.pasted.push(text);
+                vec![]
+            }
+        }
+    }
+
+    fn event(&mut self, event: Event) -> Option<ClipAction> {
+        match event {
+            Event::KeyPressed { key: Key::C, .. } => Some(ClipAction::Copy),
+            Event::KeyPressed { key: Key::V, .. } => Some(ClipAction::Paste),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn clipboard_tasks() {
+    let mut h = TestHarness::<Clip>::new();
+    h.key(Key::V);
+    h.key(Key::C);
+    assert_eq!(h.clipboard().as_deref(), Some("copied"));
+    h.key(Key::V);
+    assert_eq!(h.app().pasted, [None, Some("copied".to_string())]);
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum NotesAction {
+    Edit(String),
+    Load,
+}
+
+#[derive(Default)]
+struct Notes {
+    text: String,
+}
+
+impl App for Notes {
+    type Action = NotesAction;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<NotesAction> {
+        column(vec![
+            text_editor()
+                .id("notes")
+                .value(&self.text)
+                .on_change(NotesAction::Edit),
+        ])
+    }
+
+    fn update(&mut self, action: NotesAction) -> Vec<Task<NotesAction>> {
+        match action {
+            NotesAction::Edit(text) => {
+                self.text = text;
+                vec![]
+            }
+            NotesAction::Load => {
+                self.text = "loaded".to_string();
+                vec![Task::clear_history("notes")]
+            }
+        }
+    }
+
+    fn event(&mut self, event: Event) -> Option<NotesAction> {
+        match event {
+            Event::KeyPressed { key: Key::F5, .. } => Some(NotesAction::Load),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn undo_redo() {
+    let ctrl_shift = Modifiers {
+        ctrl: true,
+        shift: true,
+        ..Default::default()
+    };
+    let mut h = TestHarness::<Notes>::new();
+    h.click(10.0, 10.0);
+    // a typing run is one step, so is a run of deletes
+    h.type_text("hello world");
+    h.key(Key::Backspace);
+    h.key(Key::Backspace);
+    assert_eq!(h.app().text, "hello wor");
+    h.key_with(Key::Z, ctrl());
+    assert_eq!(h.app().text, "hello world");
+    h.key_with(Key::Z, ctrl());
+    assert_eq!(h.app().text, "");
+    h.key_with(Key::Z, ctrl_shift);
+    assert_eq!(h.app().text, "hello world");
+    h.key_with(Key::Y, ctrl());
+    assert_eq!(h.app().text, "hello wor");
+
+    // undoing a replaced selection brings the selection back too
+    h.advance(Duration::from_secs(1));
+    h.click(10.0, 10.0);
+    h.click(10.0, 10.0);
+    h.type_text("bye");
+    assert_eq!(h.app().text, "bye wor");
+    h.key_with(Key::Z, ctrl());
+    assert_eq!(h.app().text, "hello wor");
+    h.type_text("X");
+    assert_eq!(h.app().text, "X wor");
+
+    // nothing to undo past a value the app swapped in
+    h.key(Key::F5);
+    h.frame();
+    h.key_with(Key::Z, ctrl());
+    assert_eq!(h.app().text, "loaded");
+}
+
+#[test]
+fn keyboard_selection() {
+    let shift = Modifiers {
+        shift: true,
+        ..Default::default()
+    };
+    let ctrl_shift = Modifiers {
+        ctrl: true,
+        shift: true,
+        ..Default::default()
+    };
+    let mut h = TestHarness::<Form>::new();
+    h.click(10.0, 10.0);
+    h.type_text("one two three");
+
+    h.key_with(Key::Left, ctrl());
+    h.key_with(Key::Left, ctrl_shift);
+    h.key_with(Key::C, ctrl());
+    assert_eq!(h.clipboard().as_deref(), Some("two "));
+    h.key_with(Key::Right, shift);
+    h.key_with(Key::X, ctrl());
+    assert_eq!(h.app().name, "one tthree");
+
+    h.key_with(Key::Delete, ctrl());
+    assert_eq!(h.app().name, "one t");
+    h.key_with(Key::Backspace, ctrl());
+    assert_eq!(h.app().name, "one ");
+    h.key_with(Key::A, ctrl());
+    h.type_text("z");
+    assert_eq!(h.app().name, "z");
+}
+
+#[test]
+fn editor_line_and_page_movement() {
+    let shift = Modifiers {
+        shift: true,
+        ..Default::default()
+    };
+    let ctrl_shift = Modifiers {
+        ctrl: true,
+        shift: true,
+        ..Default::default()
+    };
+    let mut h = TestHarness::<Notes>::new();
+    h.click(10.0, 10.0);
+    let lines: Vec<String> = (0..30).map(|i| format!("l{i}")).collect();
+    h.type_text(&lines.join("\n"));
+
+    h.key_with(Key::Home, ctrl());
+    h.key_with(Key::Down, shift);
+    h.key_with(Key::Down, shift);
+    h.key_with(Key::C, ctrl());
+    assert_eq!(h.clipboard().as_deref(), Some("l0\nl1\n"));
+
+    // a page down from the top lands a screenful further, the rest is selected to the end
+    h.key_with(Key::Home, ctrl());
+    h.key(Key::PageDown);
+    h.key_with(Key::End, ctrl_shift);
+    h.key_with(Key::C, ctrl());
+    let rest = h.clipboard().unwrap();
+    let page: usize = rest.lines().next().unwrap()[1..].parse().unwrap();
+    assert!(page > 1, "page down moved {page} lines");
+    assert_eq!(rest, lines[page..].join("\n"));
+
+    // on the first line, page up goes to its start
+    h.key_with(Key::Home, ctrl());
+    h.key(Key::Right);
+    h.key(Key::PageUp);
+    h.key_with(Key::Right, shift);
+    h.key_with(Key::C, ctrl());
+    assert_eq!(h.clipboard().as_deref(), Some("l"));
+}
+
+#[derive(Default)]
+struct Wrapped {
+    text: String,
+}
+
+impl App for Wrapped {
+    type Action = String;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<String> {
+        column(vec![
+            text_editor()
+                .id("wrapped")
+                .width(px(140.0))
+                .soft_wrap(true)
+                .value(&self.text)
+                .on_change(|text| text),
+        ])
+    }
+
+    fn update(&mut self, text: String) -> Vec<Task<String>> {
+        self.text = text;
+        vec![]
+    }
+}
+
+#[test]
+fn soft_wrap() {
+    let shift = Modifiers {
+        shift: true,
+        ..Default::default()
+    };
+    let mut h = TestHarness::<Wrapped>::new();
+    h.click(10.0, 10.0);
+    let line = "alpha beta gamma delta epsilon zeta eta theta";
+    h.type_text(line);
+    h.key(Key::Enter);
+    h.type_text("end");
+
+    // one text run per visual row, the line broken between words
+    let rows = texts(&h);
+    assert!(rows.len() > 2, "{rows:?}");
+    assert_eq!(rows.concat(), format!("{line}end"));
+    assert!(rows[..rows.len() - 1].iter().all(|r| r.ends_with(' ')));
+
+    // Home, End and Down move by visual row
+    h.key_with(Key::Home, ctrl());
+    h.key_with(Key::End, shift);
+    h.key_with(Key::C, ctrl());
+    assert_eq!(h.clipboard().unwrap(), rows[0].trim_end());
+    h.key_with(Key::Home, ctrl());
+    h.key_with(Key::Down, shift);
+    h.key_with(Key::C, ctrl());
+    assert_eq!(h.clipboard().unwrap(), rows[0]);
+    h.key(Key::Right);
+    h.key(Key::Home);
+    h.key_with(Key::End, shift);
+    h.key_with(Key::C, ctrl());
+    assert_eq!(h.clipboard().unwrap(), rows[1].trim_end());
+}
+
+// a long document the app keeps in step through on_edit
+struct Log {
+    text: String,
+    edits: Vec<TextEdit>,
+}
+
+impl App for Log {
+    type Action = TextEdit;
+
+    fn new() -> Self {
+        let lines: Vec<String> = (0..10_000).map(|i| format!("line {i}")).collect();
+        Self {
+            text: lines.join("\n"),
+            edits: Vec::new(),
+        }
+    }
+
+    fn view(&self) -> Element<TextEdit> {
+        column(vec![
+            text_editor()
+                .id("log")
+                .value(&self.text)
+                .on_edit(|edit| edit),
+        ])
+    }
+
+    fn update(&mut self, edit: TextEdit) -> Vec<Task<TextEdit>> {
+        edit.apply(&mut self.text);
+        self.edits.push(edit);
+        vec![]
+    }
+}
+
+#[test]
+fn edits_as_deltas() {
+    let mut h = TestHarness::<Log>::new();
+    let len = h.app().text.len();
+    // only the rows in view are drawn
+    assert!(texts(&h).len() < 10, "{} text runs", texts(&h).len());
+
+    h.click(10.0, 10.0);
+    h.key_with(Key::Home, ctrl());
+    h.type_text("x");
+    h.key_with(Key::End, ctrl());
+    h.key(Key::Backspace);
+    h.key(Key::Backspace);
+    assert!(h.app().text.starts_with("xline 0\n"));
+    assert!(h.app().text.ends_with("\nline 99"));
+    assert_eq!(
+        h.app().edits,
+        vec![
+            TextEdit {
+                range: 0..0,
+                text: "x".into()
+            },
+            TextEdit {
+                range: len..len + 1,
+                text: String::new()
+            },
+            TextEdit {
+                range: len - 1..len,
+                text: String::new()
+            },
+        ]
+    );
+
+    // undo hands back just the part that changed, not the whole document
+    h.key_with(Key::Z, ctrl());
+    assert_eq!(
+        h.app().edits.last(),
+        Some(&TextEdit {
+            range: len - 1..len - 1,
+            text: "99".into()
+        })
+    );
+    assert!(h.app().text.ends_with("\nline 9999"));
+}
+
+static HIGHLIGHTED: AtomicUsize = AtomicUsize::new(0);
+
+// bold red keywords
+fn keywords(line: &str) -> Vec<(Range<usize>, SpanStyle)> {
+    HIGHLIGHTED.fetch_add(1, Ordering::SeqCst);
+    let style = SpanStyle::new().color(Color::rgb(220, 40, 40)).weight(700);
+    line.match_indices("let")
+        .map(|(i, m)| (i..i + m.len(), style))
+        .collect()
+}
+
+#[derive(Default)]
+struct Script {
+    text: String,
+}
+
+impl App for Script {
+    type Action = String;
+
+    fn new() -> Self {
+        Self {
+            text: "let a = 1\nlet b = 2\nlet c = 3".into(),
+        }
+    }
+
+    fn view(&self) -> Element<String> {
+        column(vec![
+            text_editor()
+                .id("script")
+                .value(&self.text)
+                .highlight(keywords)
+                .on_change(|text| text),
+        ])
+    }
+
+    fn update(&mut self, text: String) -> Vec<Task<String>> {
+        self.text = text;
+        vec![]
+    }
+}
+
+#[test]
+fn highlighting() {
+    let mut h = TestHarness::<Script>::new();
+    assert_eq!(HIGHLIGHTED.load(Ordering::SeqCst), 3);
+    let runs: Vec<TextRun> = h
+        .display_list()
+        .items()
+        .iter()
+        .filter_map(|p| match p {
+            Primitive::Text(run) => Some(run.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(runs.len(), 3);
+    for run in &runs {
+        assert_eq!(run.spans.len(), 1);
+        assert_eq!(run.spans[0].0, 0..3);
+        assert_eq!(run.spans[0].1.weight, Some(700));
+    }
+
+    // each keystroke highlights only the line it changed again
+    h.click(10.0, 10.0);
+    h.key_with(Key::End, ctrl());
+    h.type_text(" let");
+    assert_eq!(h.app().text, "let a = 1\nlet b = 2\nlet c = 3 let");
+    assert_eq!(HIGHLIGHTED.load(Ordering::SeqCst), 3 + 4);
+    let last = h
+        .display_list()
+        .items()
+        .iter()
+        .rev()
+        .find_map(|p| match p {
+            Primitive::Text(run) => Some(run.clone()),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(last.text, "let c = 3 let");
+    let ranges: Vec<_> = last.spans.iter().map(|(r, _)| r.clone()).collect();
+    assert_eq!(ranges, vec![0..3, 10..13]);
+}
+
+#[derive(Default)]
+struct Code {
+    text: String,
+}
+
+impl App for Code {
+    type Action = String;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<String> {
+        column(vec![
+            text_editor()
+                .id("code")
+                .value(&self.text)
+                .line_numbers(true)
+                .current_line(Color::rgb(40, 40, 50))
+                .indent("    ")
+                .on_change(|text| text),
+            text_input().id("after"),
+        ])
+    }
+
+    fn update(&mut self, text: String) -> Vec<Task<String>> {
+        self.text = text;
+        vec![]
+    }
+}
+
+#[test]
+fn gutter_and_indentation() {
+    let mut h = TestHarness::<Code>::new();
+    h.click(60.0, 10.0);
+    h.type_text("fn f() {\n");
+    // tab indents instead of moving focus, enter keeps the indent
+    h.key(Key::Tab);
+    assert_eq!(h.focused(), Some("code"));
+    h.type_text("a\nb");
+    assert_eq!(h.app().text, "fn f() {\n    a\n    b");
+    let numbers: Vec<String> = texts(&h).into_iter().filter(|t| t.len() == 1).collect();
+    assert_eq!(numbers, ["1", "2", "3"]);
+
+    // shift+tab and tab shift every selected line, which stay selected
+    h.key_with(Key::A, ctrl());
+    h.key_with(
+        Key::Tab,
+        Modifiers {
+            shift: true,
+            ..Default::default()
+        },
+    );
+    assert_eq!(h.app().text, "fn f() {\na\nb");
+    h.key(Key::Tab);
+    assert_eq!(h.app().text, "    fn f() {\n    a\n    b");
+    h.key_with(Key::C, ctrl());
+    assert_eq!(h.clipboard().unwrap(), h.app().text);
+    h.key_with(Key::Z, ctrl());
+    assert_eq!(h.app().text, "fn f() {\na\nb");
+
+    // ctrl+tab is how focus leaves
+    h.key_with(Key::Tab, ctrl());
+    assert_eq!(h.focused(), Some("after"));
+}
+
+#[derive(Clone, Debug)]
+enum FindAction {
+    Text(String),
+    Matches(Matches),
+    Next,
+    Previous,
+    Replace,
+    ReplaceAll,
+}
+
+struct Finder {
+    text: String,
+    query: Search,
+    with: String,
+    matches: Matches,
+}
+
+impl App for Finder {
+    type Action = FindAction;
+
+    fn new() -> Self {
+        Self {
+            text: "one two one\nOne three".into(),
+            query: Search::Text("one".into()),
+            with: "1".into(),
+            matches: Matches::default(),
+        }
+    }
+
+    fn view(&self) -> Element<FindAction> {
+        column(vec![
+            text_editor()
+                .id("doc")
+                .value(&self.text)
+                .search(self.query.clone())
+                .on_matches(FindAction::Matches)
+                .on_change(FindAction::Text),
+        ])
+    }
+
+    fn update(&mut self, action: FindAction) -> Vec<Task<FindAction>> {
+        match action {
+            FindAction::Text(text) => self.text = text,
+            FindAction::Matches(matches) => self.matches = matches,
+            FindAction::Next => return vec![Task::find_next("doc")],
+            FindAction::Previous => return vec![Task::find_previous("doc")],
+            FindAction::Replace => return vec![Task::replace("doc", &self.with)],
+            FindAction::ReplaceAll => return vec![Task::replace_all("doc", &self.with)],
+        }
+        vec![]
+    }
+
+    fn event(&mut self, event: Event) -> Option<FindAction> {
+        match event {
+            Event::KeyPressed { key, modifiers } => match key {
+                Key::F3 if modifiers.shift => Some(FindAction::Previous),
+                Key::F3 => Some(FindAction::Next),
+                Key::F4 => Some(FindAction::Replace),
+                Key::F5 => Some(FindAction::ReplaceAll),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn find_and_replace() {
+    let shift = Modifiers {
+        shift: true,
+        ..Default::default()
+    };
+    let mut h = TestHarness::<Finder>::new();
+    let matches = |h: &TestHarness<Finder>| (h.app().matches.count, h.app().matches.current);
+    assert_eq!(matches(&h), (2, None));
+
+    // next and previous select a match, wrapping around at either end
+    h.key(Key::F3);
+    assert_eq!(matches(&h), (2, Some(0)));
+    h.key(Key::F3);
+    assert_eq!(matches(&h), (2, Some(1)));
+    h.key(Key::F3);
+    assert_eq!(matches(&h), (2, Some(0)));
+    h.key_with(Key::F3, shift);
+    assert_eq!(matches(&h), (2, Some(1)));
+
+    h.app_mut().query = Search::IgnoreCase("one".into());
+    h.frame();
+    assert_eq!(matches(&h), (3, Some(1)));
+
+    // replace swaps the selected match and moves on to the next
+    h.key(Key::F4);
+    assert_eq!(h.app().text, "one two 1\nOne three");
+    assert_eq!(matches(&h), (2, Some(1)));
+    h.key(Key::F5);
+    assert_eq!(h.app().text, "1 two 1\n1 three");
+    assert_eq!(matches(&h), (0, None));
+
+    // replace all is one undo step
+    h.click(10.0, 10.0);
+    h.key_with(Key::Z, ctrl());
+    assert_eq!(h.app().text, "one two 1\nOne three");
+
+    // regex replacements fill in groups
+    h.app_mut().query = Search::Regex(r"^(\w+) (\w+)".into());
+    h.app_mut().with = "$2 $1".into();
+    h.key(Key::F5);
+    assert_eq!(h.app().text, "two one 1\nthree One");
+}
+
+// every text run the last frame painted
+fn texts<A: App>(h: &TestHarness<A>) -> Vec<String> {
+    h.display_list()
+        .items()
+        .iter()
+        .filter_map(|p| match p {
+            Primitive::Text(run) => Some(run.text.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn ime_composition() {
+    let mut h = TestHarness::<Form>::new();
+    assert_eq!(h.ime_area(), None);
+    h.click(10.0, 10.0);
+    h.type_text("ab");
+    let [x, ..] = h.ime_area().unwrap();
+
+    // the preedit shows at the cursor but isn't part of the value
+    h.key(Key::Left);
+    h.ime_preedit("にほ", Some(6));
+    assert!(texts(&h).contains(&"aにほb".to_string()));
+    assert_eq!(h.app().name, "ab");
+    assert!(h.ime_area().unwrap()[0] > x);
+
+    h.ime_commit("日本");
+    assert_eq!(h.app().name, "a日本b");
+    assert!(texts(&h).contains(&"a日本b".to_string()));
+
+    // the composition belongs to the input it started in
+    h.ime_preedit("ご", None);
+    h.advance(Duration::from_secs(1));
+    h.click(700.0, 500.0);
+    assert_eq!(h.ime_area(), None);
+    assert!(!texts(&h).iter().any(|t| t.contains('ご')));
+}
+
+// a custom widget: the bar is as long as the value, a press sets it and the arrows
+// nudge it while it has focus
+struct Slider {
+    id: Option<String>,
+    tab_index: i32,
+    value: f32,
+    bounds: [f32; 4],
+}
+
+impl Widget<f32> for Slider {
+    fn layout_node(&self, taffy: &mut taffy::TaffyTree<()>, _fonts: &mut Fonts) -> taffy::NodeId {
+        let size = taffy::Size {
+            width: taffy::prelude::length(200.0),
+            height: taffy::prelude::length(20.0),
+        };
+        taffy
+            .new_leaf(taffy::Style {
+                size,
+                ..Default::default()
+            })
+            .unwrap()
+    }
+
+    fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        self.bounds = [x, y, w, h];
+    }
+
+    fn draw(&mut self, ctx: &mut DrawCtx<f32>) {
+        let [x, y, w, h] = self.bounds;
+        let color = if ctx.focused(self.id.as_deref().unwrap()) {
+            Color::WHITE
+        } else {
+            Color::BLACK
+        };
+        let color = color.to_array();
+        ctx.list.rect(x, y, w * self.value, h, color, color, 0.0);
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        self.bounds
+    }
+
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.id = Some(id);
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn tab_index(&self) -> i32 {
+        self.tab_index
+    }
+
+    fn on_event(&mut self, event: &Event, ctx: &mut EventCtx<f32>) -> bool {
+        let value = match event {
+            Event::MousePressed { x, .. } if ctx.hovered => (x - self.bounds[0]) / self.bounds[2],
+            Event::KeyPressed {
+                key: Key::Right, ..
+            } => self.value + 0.25,
+            Event::KeyPressed { key: Key::Left, .. } => self.value - 0.25,
+            _ => return false,
+        };
+        ctx.actions.push(value.clamp(0.0, 1.0));
+        true
+    }
+}
+
+#[derive(Default)]
+struct Mixer {
+    volume: f32,
+}
+
+impl App for Mixer {
+    type Action = f32;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<f32> {
+        column(vec![
+            custom(Slider {
+                id: Some("volume".into()),
+                tab_index: 0,
+                value: self.volume,
+                bounds: [0.0; 4],
+            }),
+            text_input().id("name"),
+        ])
+    }
+
+    fn update(&mut self, volume: f32) -> Vec<Task<f32>> {
+        self.volume = volume;
+        vec![]
+    }
+}
+
+fn bar(h: &TestHarness<Mixer>) -> (f32, [f32; 4]) {
+    h.display_list()
+        .items()
+        .iter()
+        .find_map(|p| match p {
+            Primitive::Rect { w, color, .. } => Some((*w, *color)),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn custom_widget() {
+    let mut h = TestHarness::<Mixer>::new();
+    h.click(50.0, 10.0);
+    assert_eq!(h.app().volume, 0.25);
+    assert_eq!(h.focused(), Some("volume"));
+    h.frame();
+    assert_eq!(bar(&h), (50.0, Color::WHITE.to_array()));
+
+    // arrows only reach it while it has focus
+    h.key(Key::Right);
+    assert_eq!(h.app().volume, 0.5);
+    h.key(Key::Tab);
+    assert_eq!(h.focused(), Some("name"));
+    h.key(Key::Right);
+    h.frame();
+    assert_eq!(h.app().volume, 0.5);
+    assert_eq!(bar(&h), (100.0, Color::BLACK.to_array()));
+}
+
+// custom widgets without ids are given their path, mapped or not, and Tab goes by
+// their tab_index
+#[derive(Default)]
+struct Desk {
+    levels: [f32; 3],
+    tab_indices: [i32; 2],
+}
+
+impl App for Desk {
+    type Action = (usize, f32);
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<(usize, f32)> {
+        let slider = |i: usize, tab_index| {
+            custom(Slider {
+                id: None,
+                tab_index,
+                value: self.levels[i],
+                bounds: [0.0; 4],
+            })
+            .map(move |v| (i, v))
+        };
+        column(vec![
+            slider(0, 0),
+            slider(1, self.tab_indices[0]),
+            slider(2, self.tab_indices[1]),
+        ])
+    }
+
+    fn update(&mut self, (i, level): (usize, f32)) -> Vec<Task<(usize, f32)>> {
+        self.levels[i] = level;
+        vec![]
+    }
+}
+
+#[test]
+fn custom_widgets_without_ids() {
+    let mut h = TestHarness::<Desk>::new();
+    h.key(Key::Tab);
+    assert_eq!(h.focused(), Some("#/0"));
+    h.key(Key::Right);
+    h.key(Key::Tab);
+    assert_eq!(h.focused(), Some("#/1"));
+    h.key(Key::Right);
+    h.key(Key::Right);
+    assert_eq!(h.app().levels, [0.25, 0.5, 0.0]);
+}
+
+#[test]
+fn custom_widget_tab_index() {
+    let mut h = TestHarness::<Desk>::new();
+    h.app_mut().tab_indices = [1, -1];
+    h.frame();
+    let mut seen = vec![];
+    for _ in 0..3 {
+        h.key(Key::Tab);
+        seen.push(h.focused().unwrap().to_string());
+    }
+    assert_eq!(seen, ["#/1", "#/0", "#/1"]);
+}
+
+// a module with its own action type, embedded in Dashboard with Element::map and
+// Task::map
+#[derive(Clone, Debug, PartialEq)]
+enum CounterAction {
+    Increment,
+    Rename(String),
+    Pasted(Option<String>),
+}
+
+#[derive(Default)]
+struct Counter {
+    count: u32,
+    label: String,
+    pasted: Option<String>,
+}
+
+impl Counter {
+    fn view(&self) -> Element<CounterAction> {
+        column(vec![
+            button("+")
+                .width(px(50.0))
+                .height(px(50.0))
+                .on_click(CounterAction::Increment),
+            text_input()
+                .id("label")
+                .value(&self.label)
+                .on_change(CounterAction::Rename),
+        ])
+    }
+
+    fn update(&mut self, action: CounterAction) -> Vec<Task<CounterAction>> {
+        match action {
+            CounterAction::Increment => {
+                self.count += 1;
+                return vec![Task::read_clipboard(CounterAction::Pasted)];
+            }
+            CounterAction::Rename(label) => self.label = label,
+            CounterAction::Pasted(text) => self.pasted = text,
+        }
+        vec![]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum DashboardAction {
+    Counter(CounterAction),
+}
+
+#[derive(Default)]
+struct Dashboard {
+    counter: Counter,
+}
+
+impl App for Dashboard {
+    type Action = DashboardAction;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<DashboardAction> {
+        self.counter.view().map(DashboardAction::Counter)
+    }
+
+    fn update(&mut self, action: DashboardAction) -> Vec<Task<DashboardAction>> {
+        let DashboardAction::Counter(action) = action;
+        self.counter
+            .update(action)
+            .into_iter()
+            .map(|task| task.map(DashboardAction::Counter))
+            .collect()
+    }
+}
+
+#[test]
+fn mapped_child_view() {
+    let mut h = TestHarness::<Dashboard>::new();
+    h.set_clipboard("copied");
+    h.click(10.0, 10.0);
+    assert_eq!(h.app().counter.count, 1);
+    h.frame();
+    assert_eq!(h.app().counter.pasted.as_deref(), Some("copied"));
+
+    // handlers the shell calls on its own, outside the draw pass
+    h.key(Key::Enter);
+    assert_eq!(h.app().counter.count, 2);
+    h.advance(Duration::from_secs(1));
+    h.click(10.0, 60.0);
+    assert_eq!(h.focused(), Some("label"));
+    h.type_text("ab");
+    assert_eq!(h.app().counter.label, "ab");
+}
+
+// a component that keeps its own count and only tells the parent when it hits the limit
+#[derive(Clone, Debug, PartialEq)]
+enum StepperEvent {
+    Up,
+    Down,
+    Typed(String),
+}
+
+#[derive(Default)]
+struct StepperState {
+    value: i32,
+}
+
+struct Stepper {
+    name: &'static str,
+    limit: i32,
+}
+
+impl Component<PanelAction> for Stepper {
+    type State = StepperState;
+    type Event = StepperEvent;
+
+    fn view(&self, state: &StepperState) -> Element<StepperEvent> {
+        column(vec![
+            button("+")
+                .width(px(50.0))
+                .height(px(50.0))
+                .on_click(StepperEvent::Up),
+            button("-")
+                .width(px(50.0))
+                .height(px(50.0))
+                .on_click(StepperEvent::Down),
+            text_input()
+                .id(&format!("{}-input", self.name))
+                .width(px(50.0))
+                .value(&state.value.to_string())
+                .on_change(StepperEvent::Typed),
+        ])
+    }
+
+    fn update(&self, state: &mut StepperState, event: StepperEvent) -> Option<PanelAction> {
+        let before = state.value;
+        match event {
+            StepperEvent::Up => state.value += 1,
+            StepperEvent::Down => state.value -= 1,
+            StepperEvent::Typed(text) => state.value = text.parse().unwrap_or(before),
+        }
+        (before < self.limit && state.value >= self.limit)
+            .then_some(PanelAction::Reached(self.name))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PanelAction {
+    Reached(&'static str),
+}
+
+#[derive(Default)]
+struct Panel {
+    log: Vec<PanelAction>,
+}
+
+impl App for Panel {
+    type Action = PanelAction;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<PanelAction> {
+        row(vec![
+            component(Stepper {
+                name: "a",
+                limit: 2,
+            }),
+            component(Stepper {
+                name: "b",
+                limit: 2,
+            })
+            .key("b"),
+        ])
+    }
+
+    fn update(&mut self, action: PanelAction) -> Vec<Task<PanelAction>> {
+        self.log.push(action);
+        vec![]
+    }
+}
+
+#[test]
+fn stateful_component() {
+    let mut h = TestHarness::<Panel>::new();
+    assert_eq!(texts(&h), ["+", "-", "0", "+", "-", "0"]);
+
+    // internal events stay inside the component
+    h.click(10.0, 10.0);
+    h.frame();
+    assert_eq!(texts(&h), ["+", "-", "1", "+", "-", "0"]);
+    assert!(h.app().log.is_empty());
+
+    h.click(10.0, 10.0);
+    assert_eq!(h.app().log, [PanelAction::Reached("a")]);
+    h.click(10.0, 10.0);
+    assert_eq!(h.app().log.len(), 1);
+
+    // each instance keeps its own state
+    h.click(60.0, 10.0);
+    h.frame();
+    assert_eq!(texts(&h), ["+", "-", "3", "+", "-", "1"]);
+    assert_eq!(h.app().log.len(), 1);
+
+    // handlers the shell fires outside the draw pass go through update too
+    h.click(60.0, 110.0);
+    assert_eq!(h.focused(), Some("b-input"));
+    h.key(Key::End);
+    h.type_text("0");
+    h.frame();
+    assert_eq!(texts(&h), ["+", "-", "3", "+", "-", "10"]);
+    assert_eq!(
+        h.app().log,
+        [PanelAction::Reached("a"), PanelAction::Reached("b")]
+    );
+}
+
+// editors with no ids, told apart by their position and, in the list, by their key
+#[derive(Clone, Debug, PartialEq)]
+enum DraftsAction {
+    Swap,
+}
+
+#[derive(Default)]
+struct Drafts {
+    swapped: bool,
+}
+
+impl App for Drafts {
+    type Action = DraftsAction;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<DraftsAction> {
+        let mut notes = vec!["a", "b"];
+        if self.swapped {
+            notes.reverse();
+        }
+        column(vec![
+            button("swap")
+                .width(px(50.0))
+                .height(px(50.0))
+                .on_click(DraftsAction::Swap),
+            column(
+                notes
+                    .into_iter()
+                    .map(|key| text_editor().key(key).width(px(200.0)).height(px(40.0)))
+                    .collect(),
+            ),
+            text_editor().width(px(200.0)).height(px(40.0)),
+        ])
+    }
+
+    fn update(&mut self, action: DraftsAction) -> Vec<Task<DraftsAction>> {
+        match action {
+            DraftsAction::Swap => self.swapped = !self.swapped,
+        }
+        vec![]
+    }
+}
+
+#[test]
+fn derived_keys() {
+    let mut h = TestHarness::<Drafts>::new();
+    h.click(10.0, 60.0);
+    assert_eq!(h.focused(), Some("#/1/[a]"));
+    h.type_text("first");
+    h.click(10.0, 100.0);
+    assert_eq!(h.focused(), Some("#/1/[b]"));
+    h.type_text("second");
+    h.click(10.0, 140.0);
+    assert_eq!(h.focused(), Some("#/2"));
+    h.type_text("third");
+    h.frame();
+    assert_eq!(texts(&h), ["swap", "first", "second", "third"]);
+
+    // keyed state follows the item, the focus too
+    h.click(10.0, 100.0);
+    h.click(10.0, 10.0);
+    h.frame();
+    assert_eq!(texts(&h), ["swap", "second", "first", "third"]);
+    h.click(10.0, 60.0);
+    assert_eq!(h.focused(), Some("#/1/[b]"));
+}
+
+// buttons without ids are still in the tab order, under their path
+#[derive(Default)]
+struct Toolbar;
+
+impl App for Toolbar {
+    type Action = ();
+
+    fn new() -> Self {
+        Self
+    }
+
+    fn view(&self) -> Element<()> {
+        row(vec![
+            button("cut"),
+            button("copy"),
+            column(vec![button("paste")]),
+        ])
+    }
+
+    fn update(&mut self, _: ()) -> Vec<Task<()>> {
+        vec![]
+    }
+}
+
+#[test]
+fn tab_through_buttons_without_ids() {
+    let mut h = TestHarness::<Toolbar>::new();
+    let mut seen = vec![];
+    for _ in 0..4 {
+        h.key(Key::Tab);
+        seen.push(h.focused().unwrap().to_string());
+    }
+    assert_eq!(seen, ["#/0", "#/1", "#/2/0", "#/0"]);
+}
+
+// a button whose on_click comes and goes, at the top of a short scroll area
+struct Disarm {
+    armed: bool,
+    fired: usize,
+}
+
+impl App for Disarm {
+    type Action = ();
+
+    fn new() -> Self {
+        Self {
+            armed: true,
+            fired: 0,
+        }
+    }
+
+    fn view(&self) -> Element<()> {
+        let go = button("go").width(px(40.0)).height(px(20.0)).shrink(0.0);
+        column(vec![
+            column(vec![
+                if self.armed { go.on_click(()) } else { go },
+                rect(Color::WHITE)
+                    .width(px(40.0))
+                    .height(px(60.0))
+                    .shrink(0.0),
+            ])
+            .height(px(30.0))
+            .overflow_scroll(),
+        ])
+    }
+
+    fn update(&mut self, _: ()) -> Vec<Task<()>> {
+        self.fired += 1;
+        vec![]
+    }
+}
+
+#[test]
+fn button_without_on_click_does_nothing() {
+    let mut h = TestHarness::<Disarm>::new();
+    h.key(Key::Tab);
+    h.key(Key::Enter);
+    assert_eq!(h.app().fired, 1);
+    h.app_mut().armed = false;
+    h.frame();
+    h.key(Key::Enter);
+    h.key(Key::Space);
+    assert_eq!(h.app().fired, 1);
+}
+
+#[test]
+fn button_out_of_view_lets_go_of_focus() {
+    let mut h = TestHarness::<Disarm>::new();
+    h.key(Key::Tab);
+    assert_eq!(h.focused(), Some("#/0/0"));
+    // scrolled out of view, then a press somewhere else
+    h.mouse_move(10.0, 10.0);
+    h.scroll(0.0, -1000.0);
+    h.click(300.0, 300.0);
+    assert_eq!(h.focused(), None);
+}
+
+// a custom button, lighter while the pointer is over it
+struct Chip<M> {
+    interactions: Interactions<M>,
+    bounds: [f32; 4],
+}
+
+impl<M: Clone + 'static> Chip<M> {
+    fn new(action: M) -> Self {
+        let mut interactions = Interactions::default();
+        interactions.on_click = Some(Handler::new(action));
+        Self {
+            interactions,
+            bounds: [0.0; 4],
+        }
+    }
+}
+
+impl<M: Clone + 'static> Widget<M> for Chip<M> {
+    fn layout_node(&self, taffy: &mut taffy::TaffyTree<()>, _fonts: &mut Fonts) -> taffy::NodeId {
+        let size = taffy::Size {
+            width: taffy::prelude::length(40.0),
+            height: taffy::prelude::length(20.0),
+        };
+        taffy
+            .new_leaf(taffy::Style {
+                size,
+                ..Default::default()
+            })
+            .unwrap()
+    }
+
+    fn apply_layout(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        self.bounds = [x, y, w, h];
+    }
+
+    fn draw(&mut self, ctx: &mut DrawCtx<M>) {
+        let [x, y, w, h] = self.bounds;
+        let color = if self.interactions.hovered() {
+            Color::WHITE
+        } else {
+            Color::BLACK
+        };
+        let color = color.to_array();
+        ctx.list.rect(x, y, w, h, color, color, 0.0);
+    }
+
+    fn bounds(&self) -> [f32; 4] {
+        self.bounds
+    }
+
+    fn interactions(&self) -> Option<&Interactions<M>> {
+        Some(&self.interactions)
+    }
+
+    fn interactions_mut(&mut self) -> Option<&mut Interactions<M>> {
+        Some(&mut self.interactions)
+    }
+}
+
+fn chip_color<A: App>(h: &TestHarness<A>) -> [f32; 4] {
+    h.display_list()
+        .items()
+        .iter()
+        .find_map(|p| match p {
+            Primitive::Rect { color, .. } => Some(*color),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[derive(Default)]
+struct Chips {
+    clicks: usize,
+}
+
+impl App for Chips {
+    type Action = ();
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<()> {
+        custom(Chip::new(()))
+    }
+
+    fn update(&mut self, _: ()) -> Vec<Task<()>> {
+        self.clicks += 1;
+        vec![]
+    }
+}
+
+#[test]
+fn custom_widget_hover() {
+    let mut h = TestHarness::<Chips>::new();
+    h.mouse_move(100.0, 100.0);
+    assert_eq!(chip_color(&h), Color::BLACK.to_array());
+    h.mouse_move(10.0, 10.0);
+    assert_eq!(chip_color(&h), Color::WHITE.to_array());
+    h.click(10.0, 10.0);
+    assert_eq!(h.app().clicks, 1);
+}
+
+// the same chip inside a view mapped to the parent's action type
+#[derive(Clone, Debug, PartialEq)]
+enum ShelfAction {
+    Chip(()),
+}
+
+#[derive(Default)]
+struct Shelf {
+    log: Vec<ShelfAction>,
+}
+
+impl App for Shelf {
+    type Action = ShelfAction;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<ShelfAction> {
+        custom(Chip::new(())).map(ShelfAction::Chip)
+    }
+
+    fn update(&mut self, action: ShelfAction) -> Vec<Task<ShelfAction>> {
+        self.log.push(action);
+        vec![]
+    }
+}
+
+#[test]
+fn mapped_custom_widget() {
+    let mut h = TestHarness::<Shelf>::new();
+    h.mouse_move(100.0, 100.0);
+    assert_eq!(chip_color(&h), Color::BLACK.to_array());
+    h.mouse_move(10.0, 10.0);
+    assert_eq!(chip_color(&h), Color::WHITE.to_array());
+    h.click(10.0, 10.0);
+    assert_eq!(h.app().log, [ShelfAction::Chip(())]);
+}
+
+// a long document, edited through on_edit so the app never hands the editor a new value
+struct Manuscript {
+    text: String,
+    wrap: bool,
+    search: bool,
+}
+
+impl App for Manuscript {
+    type Action = TextEdit;
+
+    fn new() -> Self {
+        Self::with_lines(1, false, false)
+    }
+
+    fn view(&self) -> Element<TextEdit> {
+        let editor = text_editor()
+            .id("doc")
+            .value(&self.text)
+            .revision(0)
+            .soft_wrap(self.wrap)
+            .width(px(300.0))
+            .height(px(300.0))
+            .on_edit(|edit| edit);
+        match self.search {
+            true => editor.search(Search::Text("amet".into())),
+            false => editor,
+        }
+    }
+
+    fn update(&mut self, edit: TextEdit) -> Vec<Task<TextEdit>> {
+        edit.apply(&mut self.text);
+        vec![]
+    }
+}
+
+impl Manuscript {
+    fn with_lines(lines: usize, wrap: bool, search: bool) -> Self {
+        let text = (0..lines)
+            .map(|i| format!("{i} lorem ipsum dolor sit amet consectetur adipiscing elit sed do\n"))
+            .collect();
+        Self { text, wrap, search }
+    }
+}
+
+// how long the first frame and the quickest of a few keystrokes take
+fn timed(app: Manuscript) -> (Duration, Duration) {
+    let start = Instant::now();
+    let mut h = TestHarness::with_app(app);
+    let first = start.elapsed();
+    h.click(50.0, 50.0);
+    let keystroke = (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            h.type_text("x");
+            h.frame();
+            start.elapsed()
+        })
+        .min()
+        .unwrap();
+    (first, keystroke)
+}
+
+// a benchmark rather than a test, wall clock ratios don't hold up next to the other
+// tests running in parallel. run it with cargo test -- --ignored large_document
+#[test]
+#[ignore]
+fn large_document() {
+    // timings are compared with each other rather than with fixed budgets, so it holds
+    // on slow machines and in debug builds. only the lines in view are wrapped, so what
+    // wrapping adds to the first frame doesn't grow with the document
+    let overhead = |lines| {
+        let (plain, _) = timed(Manuscript::with_lines(lines, false, false));
+        let (wrapped, _) = timed(Manuscript::with_lines(lines, true, false));
+        wrapped.saturating_sub(plain)
+    };
+    let (short, long) = (overhead(5_000), overhead(50_000));
+    assert!(long < short * 3, "{long:?} at 50k lines, {short:?} at 5k");
+
+    // and an edit only wraps and searches the lines it touched, ten times the document
+    // isn't ten times the keystroke
+    for (wrap, search) in [(true, false), (false, true)] {
+        let (_, short) = timed(Manuscript::with_lines(5_000, wrap, search));
+        let (_, long) = timed(Manuscript::with_lines(50_000, wrap, search));
+        assert!(long < short * 3, "{long:?} at 50k lines, {short:?} at 5k");
+    }
+}
+
+// a bold editor, its text is wider than the same text at the regular weight
+struct Heading {
+    text: String,
+}
+
+impl App for Heading {
+    type Action = String;
+
+    fn new() -> Self {
+        Self {
+            text: "m".repeat(20),
+        }
+    }
+
+    fn view(&self) -> Element<String> {
+        text_editor()
+            .value(&self.text)
+            .font_weight(700)
+            .width(px(400.0))
+            .height(px(100.0))
+            .on_change(|text| text)
+    }
+
+    fn update(&mut self, text: String) -> Vec<Task<String>> {
+        self.text = text;
+        vec![]
+    }
+}
+
+#[test]
+fn click_in_bold_editor() {
+    let mut h = TestHarness::<Heading>::new();
+    h.click(5.0, 10.0);
+    h.key(Key::Home);
+    let start = h.ime_area().unwrap()[0];
+    h.key(Key::End);
+    let end = h.ime_area().unwrap()[0];
+
+    // clicks are measured at the editor's weight, as the text is drawn
+    h.click(start + (end - start) * 0.75, 10.0);
+    h.type_text("x");
+    assert_eq!(
+        h.app().text,
+        format!("{}x{}", "m".repeat(15), "m".repeat(5))
+    );
+}
+
+// tasks that start more tasks, next to one that never ends
+#[derive(Clone, Debug, PartialEq)]
+enum Job {
+    Step(u32),
+    Tick,
+}
+
+#[derive(Default)]
+struct Pipeline {
+    steps: Vec<u32>,
+}
+
+impl App for Pipeline {
+    type Action = Job;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn view(&self) -> Element<Job> {
+        column(vec![])
+    }
+
+    fn start(&mut self) -> Vec<Task<Job>> {
+        vec![
+            Task::every(Duration::from_millis(10), Job::Tick),
+            Task::background(|| Job::Step(1)),
+        ]
+    }
+
+    fn update(&mut self, job: Job) -> Vec<Task<Job>> {
+        match job {
+            Job::Step(n) => {
+                self.steps.push(n);
+                match n {
+                    1 => vec![Task::run(async { Job::Step(2) })],
+                    2 => vec![Task::delay(Duration::from_millis(20), Job::Step(3))],
+                    _ => vec![],
+                }
+            }
+            Job::Tick => vec![],
+        }
+    }
+}
+
+#[test]
+fn wait_for_task_chains() {
+    let mut h = TestHarness::<Pipeline>::new();
+    let start = Instant::now();
+    h.wait_for_tasks(Duration::from_secs(10));
+    assert_eq!(h.app().steps, [1, 2, 3]);
+    // done once the chain is, the repeating task isn't waited on
+    assert!(start.elapsed() < Duration::from_secs(5));
+}